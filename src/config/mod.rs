@@ -3,7 +3,12 @@
 //! Charge la configuration depuis les variables d'environnement
 //! et fournit un accès typé aux paramètres.
 
-use serde::Deserialize;
+use crate::domain::{CsvSeparator, RecipientPolicy};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::path::PathBuf;
+use std::time::Duration;
 
 /// Configuration complète de l'application
 #[derive(Debug, Clone, Deserialize)]
@@ -11,6 +16,13 @@ pub struct AppConfig {
     pub server: ServerConfig,
     pub email: EmailConfig,
     pub security: SecurityConfig,
+    pub database: DatabaseConfig,
+    pub outbox: OutboxConfig,
+    pub export: ExportConfig,
+    pub photo: PhotoConfig,
+    pub upload: UploadConfig,
+    pub photo_store: PhotoStoreConfig,
+    pub rate_limit: RateLimitConfig,
 }
 
 /// Configuration du serveur HTTP
@@ -22,19 +34,581 @@ pub struct ServerConfig {
     pub port: u16,
 }
 
-/// Configuration email (Resend)
+/// Configuration email
 #[derive(Debug, Clone, Deserialize)]
 pub struct EmailConfig {
+    /// Providers utilisés pour l'envoi, par ordre de préférence
+    pub providers: Vec<EmailProviderKind>,
+    /// Durée pendant laquelle un provider en échec est écarté
+    pub failover_cooldown: Duration,
+    /// Clé API Resend (obligatoire uniquement avec le provider Resend)
     pub resend_api_key: String,
+    /// Paramètres SMTP (obligatoires uniquement avec le provider SMTP)
+    pub smtp: Option<SmtpConfig>,
     pub from_name: String,
     pub from_email: String,
     pub default_recipient: String,
+    /// Destinataires autorisés (sauf politique propre à une clé)
+    pub recipients: RecipientPolicy,
 }
 
+/// Providers email disponibles
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmailProviderKind {
+    Resend,
+    Smtp,
+}
+
+impl std::str::FromStr for EmailProviderKind {
+    type Err = ConfigError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "resend" => Ok(Self::Resend),
+            "smtp" => Ok(Self::Smtp),
+            other => Err(ConfigError::InvalidValue("EMAIL_PROVIDER", other.to_string())),
+        }
+    }
+}
+
+/// Configuration d'un relais SMTP
+#[derive(Debug, Clone, Deserialize)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub tls: SmtpTls,
+    pub auth: SmtpAuth,
+}
+
+/// Mode de chiffrement de la connexion SMTP
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpTls {
+    /// Connexion en clair puis STARTTLS obligatoire (port 587)
+    StartTls,
+    /// TLS implicite dès la connexion (port 465)
+    Implicit,
+    /// Aucun chiffrement (relais local ou tests uniquement)
+    None,
+}
+
+impl SmtpTls {
+    fn default_port(self) -> u16 {
+        match self {
+            Self::StartTls => 587,
+            Self::Implicit => 465,
+            Self::None => 25,
+        }
+    }
+}
+
+/// Mécanisme d'authentification SMTP
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpAuth {
+    Plain,
+    Login,
+}
+
+/// Clé d'administration utilisée à défaut de `API_KEY` (développement uniquement)
+pub const DEV_API_KEY: &str = "dev-api-key";
+
+/// Longueur minimale de `API_KEY` en production
+pub const MIN_API_KEY_LEN: usize = 32;
+
 /// Configuration de sécurité
 #[derive(Debug, Clone, Deserialize)]
 pub struct SecurityConfig {
+    pub mode: AppMode,
     pub api_key: String,
+    /// Écart toléré entre l'horodatage d'une requête signée et l'horloge du serveur
+    pub signature_max_skew: Duration,
+    /// Nombre maximal de nonces retenus pour détecter les rejeux
+    pub nonce_cache_size: usize,
+    /// Secret de signature des jetons d'accès du back-office (HS256)
+    pub jwt_secret: String,
+    pub access_token_ttl: Duration,
+    pub refresh_token_ttl: Duration,
+    /// Secret de signature des liens vers les photos (`PHOTO_LINK_SECRET`) ;
+    /// à défaut, dérivé de la clé d'administration
+    pub photo_link_secret: Option<String>,
+}
+
+impl SecurityConfig {
+    /// Refuse en production une clé d'administration ou un secret de jetons
+    /// par défaut ou trop courts, et un secret des liens vers les photos
+    /// absent alors que le serveur sert ces liens
+    fn check(&self, photo_store: &PhotoStoreConfig) -> Result<(), ConfigError> {
+        if self.mode != AppMode::Production {
+            return Ok(());
+        }
+        if photo_store.serves_links() && self.photo_link_secret.is_none() {
+            return Err(ConfigError::MissingEnvVar("PHOTO_LINK_SECRET"));
+        }
+        if self.api_key == DEV_API_KEY {
+            return Err(ConfigError::InsecureSecret(
+                "API_KEY",
+                "clé de développement par défaut".to_string(),
+            ));
+        }
+        let secrets = [
+            ("API_KEY", Some(&self.api_key)),
+            ("JWT_SECRET", Some(&self.jwt_secret)),
+            ("PHOTO_LINK_SECRET", self.photo_link_secret.as_ref()),
+        ];
+        for (name, secret) in secrets {
+            let Some(secret) = secret else { continue };
+            if secret.chars().count() < MIN_API_KEY_LEN {
+                return Err(ConfigError::InsecureSecret(
+                    name,
+                    format!("{} caractères minimum", MIN_API_KEY_LEN),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Mode d'exécution du serveur
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AppMode {
+    /// Clé d'administration par défaut tolérée
+    Development,
+    /// Clé d'administration robuste obligatoire
+    Production,
+}
+
+impl std::str::FromStr for AppMode {
+    type Err = ConfigError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "dev" | "development" => Ok(Self::Development),
+            "prod" | "production" => Ok(Self::Production),
+            other => Err(ConfigError::InvalidValue("APP_ENV", other.to_string())),
+        }
+    }
+}
+
+/// Limites de débit, par groupe de routes
+#[derive(Debug, Clone, Deserialize)]
+pub struct RateLimitConfig {
+    /// Toutes les routes `/api`
+    pub api: RouteLimits,
+    /// Connexion au back-office (`/auth`)
+    pub auth: RouteLimits,
+    /// Exports des fiches (`/api/export-fiches`, `/api/v2/export-fiches`)
+    pub export: RouteLimits,
+    /// Envoi de l'historique (`/api/send-history-email`)
+    pub history: RouteLimits,
+    /// Adresse du client lue dans `X-Forwarded-For` (derrière un proxy de
+    /// confiance, comme sur Railway) plutôt que celle de la connexion
+    pub trust_forwarded_for: bool,
+}
+
+/// Groupe de routes soumis à ses propres limites
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RateRoute {
+    Api,
+    Auth,
+    Export,
+    History,
+}
+
+impl RateRoute {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Api => "api",
+            Self::Auth => "auth",
+            Self::Export => "export",
+            Self::History => "history",
+        }
+    }
+}
+
+impl RateLimitConfig {
+    pub fn limits(&self, route: RateRoute) -> &RouteLimits {
+        match route {
+            RateRoute::Api => &self.api,
+            RateRoute::Auth => &self.auth,
+            RateRoute::Export => &self.export,
+            RateRoute::History => &self.history,
+        }
+    }
+
+    /// Lit `RATE_LIMIT_<GROUPE>_PER_KEY`, `_PER_IP` et `_PER_DOMAIN`
+    fn from_env() -> Result<Self, ConfigError> {
+        let defaults = Self::default();
+        Ok(Self {
+            api: RouteLimits::from_env(
+                ["RATE_LIMIT_API_PER_KEY", "RATE_LIMIT_API_PER_IP", "RATE_LIMIT_API_PER_DOMAIN"],
+                defaults.api,
+            )?,
+            auth: RouteLimits::from_env(
+                ["RATE_LIMIT_AUTH_PER_KEY", "RATE_LIMIT_AUTH_PER_IP", "RATE_LIMIT_AUTH_PER_DOMAIN"],
+                defaults.auth,
+            )?,
+            export: RouteLimits::from_env(
+                [
+                    "RATE_LIMIT_EXPORT_PER_KEY",
+                    "RATE_LIMIT_EXPORT_PER_IP",
+                    "RATE_LIMIT_EXPORT_PER_DOMAIN",
+                ],
+                defaults.export,
+            )?,
+            history: RouteLimits::from_env(
+                [
+                    "RATE_LIMIT_HISTORY_PER_KEY",
+                    "RATE_LIMIT_HISTORY_PER_IP",
+                    "RATE_LIMIT_HISTORY_PER_DOMAIN",
+                ],
+                defaults.history,
+            )?,
+            trust_forwarded_for: env_parse(
+                "RATE_LIMIT_TRUST_FORWARDED_FOR",
+                defaults.trust_forwarded_for,
+            )?,
+        })
+    }
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        let quota = |requests, secs| Some(Quota::new(requests, Duration::from_secs(secs)));
+        Self {
+            api: RouteLimits {
+                per_key: quota(600, 60),
+                per_ip: quota(1200, 60),
+                per_domain: None,
+            },
+            auth: RouteLimits {
+                per_key: None,
+                per_ip: quota(10, 60),
+                per_domain: None,
+            },
+            export: RouteLimits {
+                per_key: quota(60, 3600),
+                per_ip: quota(120, 3600),
+                per_domain: quota(60, 3600),
+            },
+            history: RouteLimits {
+                per_key: quota(20, 3600),
+                per_ip: quota(40, 3600),
+                per_domain: quota(20, 3600),
+            },
+            trust_forwarded_for: false,
+        }
+    }
+}
+
+/// Limites d'un groupe de routes ; `None` désactive la limite
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct RouteLimits {
+    /// Par clé d'API ou utilisateur authentifié
+    pub per_key: Option<Quota>,
+    /// Par adresse IP du client
+    pub per_ip: Option<Quota>,
+    /// Par domaine de l'adresse destinataire choisie par le client
+    pub per_domain: Option<Quota>,
+}
+
+impl RouteLimits {
+    fn from_env(names: [&'static str; 3], defaults: Self) -> Result<Self, ConfigError> {
+        Ok(Self {
+            per_key: env_quota(names[0], defaults.per_key)?,
+            per_ip: env_quota(names[1], defaults.per_ip)?,
+            per_domain: env_quota(names[2], defaults.per_domain)?,
+        })
+    }
+}
+
+/// Nombre de requêtes autorisées par période (seau à jetons : la période
+/// entière peut être consommée d'un coup, puis les jetons reviennent au fil
+/// de l'eau)
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct Quota {
+    pub requests: u32,
+    pub period: Duration,
+}
+
+impl Quota {
+    pub fn new(requests: u32, period: Duration) -> Self {
+        Self { requests, period }
+    }
+}
+
+impl std::str::FromStr for Quota {
+    type Err = ();
+
+    /// `30/min`, `5/s`, `100/h` ou `500/d`
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (requests, unit) = value.trim().split_once('/').ok_or(())?;
+        let requests: u32 = requests.trim().parse().map_err(|_| ())?;
+        let secs = match unit.trim() {
+            "s" | "sec" => 1,
+            "m" | "min" => 60,
+            "h" => 3600,
+            "d" => 86_400,
+            _ => return Err(()),
+        };
+        if requests == 0 {
+            return Err(());
+        }
+        Ok(Self::new(requests, Duration::from_secs(secs)))
+    }
+}
+
+/// Lit une limite (`30/min`) ; `off` la désactive
+fn env_quota(name: &'static str, default: Option<Quota>) -> Result<Option<Quota>, ConfigError> {
+    match std::env::var(name) {
+        Ok(value) if value.trim().eq_ignore_ascii_case("off") => Ok(None),
+        Ok(value) => value
+            .parse()
+            .map(Some)
+            .map_err(|_| ConfigError::InvalidValue(name, value)),
+        Err(_) => Ok(default),
+    }
+}
+
+/// Configuration de la base de données
+#[derive(Debug, Clone, Deserialize)]
+pub struct DatabaseConfig {
+    #[serde(default = "default_database_url")]
+    pub url: String,
+}
+
+/// Configuration de la file d'envoi des emails
+#[derive(Debug, Clone, Deserialize)]
+pub struct OutboxConfig {
+    /// Nombre de tentatives avant abandon (dead-letter)
+    pub max_attempts: u32,
+    /// Délai avant la première nouvelle tentative, doublé à chaque échec
+    pub retry_base_delay: Duration,
+    /// Délai maximal entre deux tentatives
+    pub retry_max_delay: Duration,
+    /// Intervalle de scrutation des envois à reprendre
+    pub poll_interval: Duration,
+}
+
+impl Default for OutboxConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 8,
+            retry_base_delay: Duration::from_secs(30),
+            retry_max_delay: Duration::from_secs(3600),
+            poll_interval: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Pièces jointes ajoutées par défaut aux exports
+///
+/// Chaque option peut être surchargée par la requête d'export.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExportConfig {
+    /// Joindre un CSV des fiches
+    pub include_csv: bool,
+    pub csv_separator: CsvSeparator,
+    /// Joindre un classeur Excel des fiches
+    pub include_xlsx: bool,
+    /// Joindre les fiches au format vCard
+    pub include_vcard: bool,
+    /// Un fichier vCard par fiche plutôt qu'un fichier unique
+    pub vcard_per_contact: bool,
+    /// Joindre le rapport PDF des fiches
+    pub include_pdf: bool,
+    /// Salon affiché sur le rapport PDF quand la requête n'en précise pas
+    pub salon: Option<String>,
+    /// Regrouper les photos dans une archive ZIP plutôt que de les joindre une à une
+    pub zip_photos: bool,
+    /// Liens temporaires vers les photos stockées plutôt que des pièces jointes
+    pub photo_links: bool,
+    /// Taille maximale d'un email d'export une fois encodé, en octets ; au-delà,
+    /// l'export est découpé en plusieurs emails
+    pub max_email_bytes: usize,
+}
+
+/// Traitement des photos de cartes de visite reçues
+#[derive(Debug, Clone, Deserialize)]
+pub struct PhotoConfig {
+    /// Plus grande dimension (largeur ou hauteur) après réduction, en pixels
+    pub max_dimension: u32,
+    /// Qualité de recompression JPEG (1 à 100)
+    pub jpeg_quality: u8,
+}
+
+impl Default for PhotoConfig {
+    fn default() -> Self {
+        Self {
+            max_dimension: 1600,
+            jpeg_quality: 80,
+        }
+    }
+}
+
+/// Limites des envois multipart (photos transmises en fichiers)
+#[derive(Debug, Clone, Deserialize)]
+pub struct UploadConfig {
+    /// Taille maximale d'une photo reçue, en octets
+    pub max_photo_bytes: usize,
+    /// Taille maximale de l'ensemble des photos d'un envoi, en octets
+    pub max_total_bytes: usize,
+    /// Nombre maximal de parties photo d'un envoi (un fichier temporaire chacune)
+    pub max_photos: usize,
+}
+
+impl Default for UploadConfig {
+    fn default() -> Self {
+        Self {
+            max_photo_bytes: 20 * 1024 * 1024,
+            max_total_bytes: 500 * 1024 * 1024,
+            max_photos: 500,
+        }
+    }
+}
+
+/// Stockage des photos de cartes de visite
+#[derive(Debug, Clone, Deserialize)]
+pub struct PhotoStoreConfig {
+    pub backend: PhotoStoreBackend,
+    /// Durée de validité des liens temporaires vers les photos
+    pub link_ttl: Duration,
+    /// Adresse publique du serveur, pour les liens signés par le serveur
+    pub public_base_url: Option<String>,
+    /// Secret de signature des liens servis par le serveur
+    pub link_secret: String,
+}
+
+/// Emplacement des photos
+#[derive(Debug, Clone, Deserialize)]
+pub enum PhotoStoreBackend {
+    /// Dans la base SQLite, avec les fiches (défaut)
+    Database,
+    /// Un fichier par photo sous `root`
+    Filesystem { root: PathBuf },
+    /// Bucket compatible S3 (AWS, MinIO, ...)
+    S3(S3Config),
+}
+
+/// Accès à un bucket compatible S3
+#[derive(Debug, Clone, Deserialize)]
+pub struct S3Config {
+    /// Adresse du service (`https://s3.eu-west-3.amazonaws.com`, `http://minio:9000`)
+    pub endpoint: String,
+    pub bucket: String,
+    pub region: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    /// Bucket dans le chemin (`endpoint/bucket/clé`, MinIO) plutôt que dans l'hôte
+    pub path_style: bool,
+}
+
+/// Durée maximale d'une URL présignée S3
+const MAX_S3_LINK_TTL: Duration = Duration::from_secs(7 * 24 * 3600);
+
+impl PhotoStoreConfig {
+    /// Charge la configuration du stockage (`PHOTO_STORE`, `PHOTO_STORE_PATH`, `S3_*`, ...)
+    ///
+    /// Sans `PHOTO_LINK_SECRET`, le secret des liens est dérivé de la clé
+    /// d'administration (HMAC de `photo-links`) : un lien publié ne révèle
+    /// jamais la clé elle-même.
+    fn from_env(security: &SecurityConfig) -> Result<Self, ConfigError> {
+        let backend = match std::env::var("PHOTO_STORE")
+            .unwrap_or_else(|_| "database".to_string())
+            .to_lowercase()
+            .as_str()
+        {
+            "database" => PhotoStoreBackend::Database,
+            "filesystem" => PhotoStoreBackend::Filesystem {
+                root: std::env::var("PHOTO_STORE_PATH")
+                    .unwrap_or_else(|_| "photos".to_string())
+                    .into(),
+            },
+            "s3" => PhotoStoreBackend::S3(S3Config::from_env()?),
+            other => return Err(ConfigError::InvalidValue("PHOTO_STORE", other.to_string())),
+        };
+
+        let link_ttl = env_secs("PHOTO_LINK_TTL_SECS", MAX_S3_LINK_TTL)?;
+        if link_ttl.is_zero()
+            || (matches!(backend, PhotoStoreBackend::S3(_)) && link_ttl > MAX_S3_LINK_TTL)
+        {
+            return Err(ConfigError::InvalidValue(
+                "PHOTO_LINK_TTL_SECS",
+                link_ttl.as_secs().to_string(),
+            ));
+        }
+
+        Ok(Self {
+            backend,
+            link_ttl,
+            public_base_url: std::env::var("PUBLIC_BASE_URL")
+                .ok()
+                .map(|url| url.trim_end_matches('/').to_string())
+                .filter(|url| !url.is_empty()),
+            link_secret: security
+                .photo_link_secret
+                .clone()
+                .unwrap_or_else(|| derive_secret(&security.api_key, "photo-links")),
+        })
+    }
+
+    /// Le serveur sert lui-même des liens signés vers les photos stockées
+    pub fn serves_links(&self) -> bool {
+        !matches!(self.backend, PhotoStoreBackend::Database) && self.public_base_url.is_some()
+    }
+}
+
+/// Secret propre à un usage, dérivé d'une clé (HMAC-SHA256 hexadécimal)
+fn derive_secret(key: &str, purpose: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key.as_bytes())
+        .expect("HMAC accepte toute taille de clé");
+    mac.update(purpose.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+impl S3Config {
+    /// Charge la configuration S3 (`S3_BUCKET`, `S3_REGION`, `S3_ENDPOINT`, ...)
+    fn from_env() -> Result<Self, ConfigError> {
+        let required =
+            |name: &'static str| std::env::var(name).map_err(|_| ConfigError::MissingEnvVar(name));
+        let region = std::env::var("S3_REGION").unwrap_or_else(|_| "us-east-1".to_string());
+        // Un endpoint explicite désigne en général un service compatible (MinIO, ...)
+        let (endpoint, default_path_style) = match std::env::var("S3_ENDPOINT") {
+            Ok(endpoint) => (endpoint.trim_end_matches('/').to_string(), true),
+            Err(_) => (format!("https://s3.{}.amazonaws.com", region), false),
+        };
+
+        Ok(Self {
+            endpoint,
+            bucket: required("S3_BUCKET")?,
+            region,
+            access_key_id: required("S3_ACCESS_KEY_ID")?,
+            secret_access_key: required("S3_SECRET_ACCESS_KEY")?,
+            path_style: env_parse("S3_PATH_STYLE", default_path_style)?,
+        })
+    }
+}
+
+impl Default for ExportConfig {
+    fn default() -> Self {
+        Self {
+            include_csv: false,
+            csv_separator: CsvSeparator::default(),
+            include_xlsx: false,
+            include_vcard: false,
+            vcard_per_contact: false,
+            include_pdf: false,
+            salon: None,
+            zip_photos: true,
+            photo_links: false,
+            max_email_bytes: default_max_email_bytes(),
+        }
+    }
 }
 
 // Valeurs par défaut
@@ -46,11 +620,134 @@ fn default_port() -> u16 {
     8080
 }
 
+fn default_failover_cooldown() -> Duration {
+    Duration::from_secs(60)
+}
+
+/// Sous la limite par défaut de Postfix (10 240 000 octets) et de Resend (40 Mo)
+fn default_max_email_bytes() -> usize {
+    10_000_000
+}
+
+fn default_database_url() -> String {
+    "sqlite:contacts.db?mode=rwc".to_string()
+}
+
 impl AppConfig {
     /// Charge la configuration depuis les variables d'environnement
     pub fn from_env() -> Result<Self, ConfigError> {
         dotenvy::dotenv().ok();
 
+        let providers = parse_providers(
+            &std::env::var("EMAIL_PROVIDER").unwrap_or_else(|_| "resend".to_string()),
+        )?;
+
+        let resend_api_key = if providers.contains(&EmailProviderKind::Resend) {
+            std::env::var("RESEND_API_KEY")
+                .map_err(|_| ConfigError::MissingEnvVar("RESEND_API_KEY"))?
+        } else {
+            std::env::var("RESEND_API_KEY").unwrap_or_default()
+        };
+
+        let smtp = if providers.contains(&EmailProviderKind::Smtp) {
+            Some(SmtpConfig::from_env()?)
+        } else {
+            None
+        };
+
+        let failover_cooldown =
+            env_secs("EMAIL_FAILOVER_COOLDOWN_SECS", default_failover_cooldown())?;
+
+        let default_recipient = std::env::var("DEFAULT_EXPORT_EMAIL")
+            .unwrap_or_else(|_| "commercial@smp-moules.com".to_string());
+        let recipients = recipient_policy(&default_recipient)?;
+
+        let outbox_defaults = OutboxConfig::default();
+        let outbox = OutboxConfig {
+            max_attempts: env_parse("OUTBOX_MAX_ATTEMPTS", outbox_defaults.max_attempts)?,
+            retry_base_delay: env_secs("OUTBOX_RETRY_BASE_SECS", outbox_defaults.retry_base_delay)?,
+            retry_max_delay: env_secs("OUTBOX_RETRY_MAX_SECS", outbox_defaults.retry_max_delay)?,
+            poll_interval: env_secs("OUTBOX_POLL_INTERVAL_SECS", outbox_defaults.poll_interval)?,
+        };
+
+        let export = ExportConfig {
+            include_csv: env_parse("EXPORT_CSV", false)?,
+            csv_separator: match std::env::var("EXPORT_CSV_SEPARATOR") {
+                Ok(value) => CsvSeparator::parse(&value)
+                    .ok_or(ConfigError::InvalidValue("EXPORT_CSV_SEPARATOR", value))?,
+                Err(_) => CsvSeparator::default(),
+            },
+            include_xlsx: env_parse("EXPORT_XLSX", false)?,
+            include_vcard: env_parse("EXPORT_VCARD", false)?,
+            vcard_per_contact: env_parse("EXPORT_VCARD_PER_CONTACT", false)?,
+            include_pdf: env_parse("EXPORT_PDF", false)?,
+            zip_photos: env_parse("EXPORT_ZIP_PHOTOS", true)?,
+            photo_links: env_parse("EXPORT_PHOTO_LINKS", false)?,
+            salon: std::env::var("SALON_NAME").ok().filter(|s| !s.trim().is_empty()),
+            max_email_bytes: env_parse("EXPORT_MAX_EMAIL_BYTES", default_max_email_bytes())?,
+        };
+        if export.max_email_bytes == 0 {
+            return Err(ConfigError::InvalidValue("EXPORT_MAX_EMAIL_BYTES", "0".to_string()));
+        }
+
+        let photo_defaults = PhotoConfig::default();
+        let photo = PhotoConfig {
+            max_dimension: env_parse("PHOTO_MAX_DIMENSION", photo_defaults.max_dimension)?,
+            jpeg_quality: env_parse("PHOTO_JPEG_QUALITY", photo_defaults.jpeg_quality)?,
+        };
+        if photo.max_dimension == 0 {
+            return Err(ConfigError::InvalidValue("PHOTO_MAX_DIMENSION", "0".to_string()));
+        }
+        if !(1..=100).contains(&photo.jpeg_quality) {
+            return Err(ConfigError::InvalidValue(
+                "PHOTO_JPEG_QUALITY",
+                photo.jpeg_quality.to_string(),
+            ));
+        }
+
+        let upload_defaults = UploadConfig::default();
+        let upload = UploadConfig {
+            max_photo_bytes: env_parse("UPLOAD_MAX_PHOTO_BYTES", upload_defaults.max_photo_bytes)?,
+            max_total_bytes: env_parse("UPLOAD_MAX_TOTAL_BYTES", upload_defaults.max_total_bytes)?,
+            max_photos: env_parse("UPLOAD_MAX_PHOTOS", upload_defaults.max_photos)?,
+        };
+
+        let mode = env_parse("APP_ENV", AppMode::Development)?;
+        let api_key = match std::env::var("API_KEY") {
+            Ok(api_key) => api_key,
+            Err(_) if mode == AppMode::Production => {
+                return Err(ConfigError::MissingEnvVar("API_KEY"))
+            }
+            Err(_) => DEV_API_KEY.to_string(),
+        };
+        let jwt_secret = match std::env::var("JWT_SECRET") {
+            Ok(secret) => secret,
+            Err(_) if mode == AppMode::Production => {
+                return Err(ConfigError::MissingEnvVar("JWT_SECRET"))
+            }
+            Err(_) => api_key.clone(),
+        };
+        let security = SecurityConfig {
+            mode,
+            api_key,
+            signature_max_skew: env_secs("SIGNATURE_MAX_SKEW_SECS", Duration::from_secs(300))?,
+            nonce_cache_size: env_parse("NONCE_CACHE_SIZE", 50_000)?,
+            jwt_secret,
+            access_token_ttl: env_secs("ACCESS_TOKEN_TTL_SECS", Duration::from_secs(15 * 60))?,
+            refresh_token_ttl: env_secs(
+                "REFRESH_TOKEN_TTL_SECS",
+                Duration::from_secs(30 * 24 * 3600),
+            )?,
+            photo_link_secret: std::env::var("PHOTO_LINK_SECRET")
+                .ok()
+                .filter(|secret| !secret.is_empty()),
+        };
+        if security.nonce_cache_size == 0 {
+            return Err(ConfigError::InvalidValue("NONCE_CACHE_SIZE", "0".to_string()));
+        }
+        let photo_store = PhotoStoreConfig::from_env(&security)?;
+        security.check(&photo_store)?;
+
         Ok(Self {
             server: ServerConfig {
                 host: std::env::var("HOST").unwrap_or_else(|_| default_host()),
@@ -60,28 +757,188 @@ impl AppConfig {
                     .unwrap_or_else(default_port),
             },
             email: EmailConfig {
-                resend_api_key: std::env::var("RESEND_API_KEY")
-                    .map_err(|_| ConfigError::MissingEnvVar("RESEND_API_KEY"))?,
+                providers,
+                failover_cooldown,
+                resend_api_key,
+                smtp,
                 from_name: std::env::var("EMAIL_FROM_NAME")
                     .unwrap_or_else(|_| "SMP Moules".to_string()),
                 from_email: std::env::var("EMAIL_FROM_ADDRESS")
                     .unwrap_or_else(|_| "onboarding@resend.dev".to_string()),
-                default_recipient: std::env::var("DEFAULT_EXPORT_EMAIL")
-                    .unwrap_or_else(|_| "commercial@smp-moules.com".to_string()),
+                default_recipient,
+                recipients,
             },
-            security: SecurityConfig {
-                api_key: std::env::var("API_KEY")
-                    .unwrap_or_else(|_| "dev-api-key".to_string()),
+            security,
+            database: DatabaseConfig {
+                url: std::env::var("DATABASE_URL").unwrap_or_else(|_| default_database_url()),
             },
+            outbox,
+            export,
+            photo,
+            upload,
+            photo_store,
+            rate_limit: RateLimitConfig::from_env()?,
+        })
+    }
+}
+
+/// Lit une variable numérique, avec une valeur par défaut si absente
+fn env_parse<T: std::str::FromStr>(name: &'static str, default: T) -> Result<T, ConfigError> {
+    match std::env::var(name) {
+        Ok(value) => value
+            .trim()
+            .parse()
+            .map_err(|_| ConfigError::InvalidValue(name, value)),
+        Err(_) => Ok(default),
+    }
+}
+
+/// Lit une durée exprimée en secondes
+fn env_secs(name: &'static str, default: Duration) -> Result<Duration, ConfigError> {
+    env_parse(name, default.as_secs()).map(Duration::from_secs)
+}
+
+/// Destinataires autorisés (`RECIPIENT_ALLOWED_DOMAINS`,
+/// `RECIPIENT_ALLOWED_ADDRESSES`, listes séparées par des virgules)
+///
+/// Sans l'une ni l'autre, seul le domaine du destinataire par défaut est
+/// autorisé : le serveur ne relaie pas vers des adresses quelconques.
+fn recipient_policy(default_recipient: &str) -> Result<RecipientPolicy, ConfigError> {
+    let list = |name| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+    let (domains, addresses) = match (
+        list("RECIPIENT_ALLOWED_DOMAINS"),
+        list("RECIPIENT_ALLOWED_ADDRESSES"),
+    ) {
+        (None, None) => {
+            let domain = default_recipient
+                .rsplit_once('@')
+                .map(|(_, domain)| domain.to_string())
+                .ok_or_else(|| {
+                    ConfigError::InvalidValue("DEFAULT_EXPORT_EMAIL", default_recipient.to_string())
+                })?;
+            (domain, String::new())
+        }
+        (domains, addresses) => (domains.unwrap_or_default(), addresses.unwrap_or_default()),
+    };
+    Ok(RecipientPolicy::new(domains.split(','), addresses.split(',')))
+}
+
+/// Liste ordonnée de providers (`EMAIL_PROVIDER=resend,smtp`)
+fn parse_providers(value: &str) -> Result<Vec<EmailProviderKind>, ConfigError> {
+    let mut providers = Vec::new();
+    for kind in value.split(',').filter(|v| !v.trim().is_empty()) {
+        let kind: EmailProviderKind = kind.parse()?;
+        if !providers.contains(&kind) {
+            providers.push(kind);
+        }
+    }
+
+    if providers.is_empty() {
+        return Err(ConfigError::InvalidValue("EMAIL_PROVIDER", value.to_string()));
+    }
+    Ok(providers)
+}
+
+impl SmtpConfig {
+    /// Charge la configuration SMTP (`SMTP_HOST`, `SMTP_PORT`, `SMTP_TLS`, ...)
+    fn from_env() -> Result<Self, ConfigError> {
+        let tls = match std::env::var("SMTP_TLS")
+            .unwrap_or_else(|_| "starttls".to_string())
+            .to_lowercase()
+            .as_str()
+        {
+            "starttls" => SmtpTls::StartTls,
+            "tls" | "implicit" | "ssl" => SmtpTls::Implicit,
+            "none" => SmtpTls::None,
+            other => return Err(ConfigError::InvalidValue("SMTP_TLS", other.to_string())),
+        };
+
+        let auth = match std::env::var("SMTP_AUTH")
+            .unwrap_or_else(|_| "plain".to_string())
+            .to_lowercase()
+            .as_str()
+        {
+            "plain" => SmtpAuth::Plain,
+            "login" => SmtpAuth::Login,
+            other => return Err(ConfigError::InvalidValue("SMTP_AUTH", other.to_string())),
+        };
+
+        let port = match std::env::var("SMTP_PORT") {
+            Ok(port) => port
+                .parse()
+                .map_err(|_| ConfigError::InvalidValue("SMTP_PORT", port))?,
+            Err(_) => tls.default_port(),
+        };
+
+        Ok(Self {
+            host: std::env::var("SMTP_HOST").map_err(|_| ConfigError::MissingEnvVar("SMTP_HOST"))?,
+            port,
+            username: std::env::var("SMTP_USERNAME").ok().filter(|v| !v.is_empty()),
+            password: std::env::var("SMTP_PASSWORD").ok().filter(|v| !v.is_empty()),
+            tls,
+            auth,
         })
     }
 }
 
+#[cfg(test)]
+impl AppConfig {
+    /// Configuration minimale pour les tests (clé API `secret`, base en mémoire)
+    pub fn for_tests() -> Self {
+        Self {
+            server: ServerConfig {
+                host: "127.0.0.1".to_string(),
+                port: 0,
+            },
+            email: EmailConfig {
+                providers: vec![EmailProviderKind::Resend],
+                failover_cooldown: default_failover_cooldown(),
+                resend_api_key: "test".to_string(),
+                smtp: None,
+                from_name: "Test".to_string(),
+                from_email: "from@example.com".to_string(),
+                default_recipient: "default@example.com".to_string(),
+                recipients: RecipientPolicy::new(["example.com"], ["partenaire@gmail.com"]),
+            },
+            security: SecurityConfig {
+                mode: AppMode::Development,
+                api_key: "secret".to_string(),
+                signature_max_skew: Duration::from_secs(300),
+                nonce_cache_size: 100,
+                jwt_secret: "jwt-secret".to_string(),
+                access_token_ttl: Duration::from_secs(900),
+                refresh_token_ttl: Duration::from_secs(3600),
+                photo_link_secret: None,
+            },
+            database: DatabaseConfig {
+                url: "sqlite::memory:".to_string(),
+            },
+            outbox: OutboxConfig::default(),
+            photo: PhotoConfig::default(),
+            upload: UploadConfig::default(),
+            photo_store: PhotoStoreConfig {
+                backend: PhotoStoreBackend::Database,
+                link_ttl: Duration::from_secs(3600),
+                public_base_url: None,
+                link_secret: "secret".to_string(),
+            },
+            export: ExportConfig::default(),
+            rate_limit: RateLimitConfig::default(),
+        }
+    }
+}
+
 /// Erreurs de configuration
 #[derive(Debug, thiserror::Error)]
 pub enum ConfigError {
     #[error("Variable d'environnement manquante: {0}")]
     MissingEnvVar(&'static str),
+
+    #[error("Valeur invalide pour {0}: {1}")]
+    InvalidValue(&'static str, String),
+
+    #[error("{0} refusée en production: {1}")]
+    InsecureSecret(&'static str, String),
 }
 
 #[cfg(test)]
@@ -92,5 +949,92 @@ mod tests {
     fn test_default_values() {
         assert_eq!(default_host(), "0.0.0.0");
         assert_eq!(default_port(), 8080);
+        assert!(default_database_url().starts_with("sqlite:"));
+    }
+
+    #[test]
+    fn test_email_provider_kind_parsing() {
+        assert_eq!("SMTP".parse::<EmailProviderKind>().unwrap(), EmailProviderKind::Smtp);
+        assert_eq!(" resend ".parse::<EmailProviderKind>().unwrap(), EmailProviderKind::Resend);
+        assert!("sendgrid".parse::<EmailProviderKind>().is_err());
+        assert_eq!(SmtpTls::Implicit.default_port(), 465);
+    }
+
+    #[test]
+    fn test_provider_list_parsing() {
+        assert_eq!(
+            parse_providers("smtp, resend,smtp").unwrap(),
+            vec![EmailProviderKind::Smtp, EmailProviderKind::Resend]
+        );
+        assert!(parse_providers(" , ").is_err());
+    }
+
+    #[test]
+    fn test_quota_parsing() {
+        assert_eq!("30/min".parse(), Ok(Quota::new(30, Duration::from_secs(60))));
+        assert_eq!(" 5 / s ".parse(), Ok(Quota::new(5, Duration::from_secs(1))));
+        assert_eq!("500/d".parse(), Ok(Quota::new(500, Duration::from_secs(86_400))));
+        assert!("0/h".parse::<Quota>().is_err());
+        assert!("30".parse::<Quota>().is_err());
+        assert!("30/semaine".parse::<Quota>().is_err());
+    }
+
+    #[test]
+    fn test_production_refuses_weak_api_key() {
+        let strong = "k".repeat(MIN_API_KEY_LEN);
+        let security = |mode: &str, api_key: &str| SecurityConfig {
+            mode: mode.parse().unwrap(),
+            api_key: api_key.to_string(),
+            jwt_secret: strong.clone(),
+            ..AppConfig::for_tests().security
+        };
+        let photo_store = AppConfig::for_tests().photo_store;
+
+        assert!(security("dev", DEV_API_KEY).check(&photo_store).is_ok());
+        assert!(security("prod", DEV_API_KEY).check(&photo_store).is_err());
+        assert!(security("prod", "trop-courte").check(&photo_store).is_err());
+        assert!(security(" Production ", &strong).check(&photo_store).is_ok());
+        assert!("staging".parse::<AppMode>().is_err());
+
+        let weak_jwt = SecurityConfig {
+            jwt_secret: "court".to_string(),
+            ..security("prod", &strong)
+        };
+        assert!(matches!(
+            weak_jwt.check(&photo_store),
+            Err(ConfigError::InsecureSecret("JWT_SECRET", _))
+        ));
+    }
+
+    #[test]
+    fn test_production_requires_photo_link_secret_for_served_links() {
+        let strong = "k".repeat(MIN_API_KEY_LEN);
+        let security = SecurityConfig {
+            mode: AppMode::Production,
+            api_key: strong.clone(),
+            jwt_secret: strong.clone(),
+            ..AppConfig::for_tests().security
+        };
+        let served = PhotoStoreConfig {
+            backend: PhotoStoreBackend::Filesystem { root: "photos".into() },
+            public_base_url: Some("https://api.smp-moules.com".to_string()),
+            ..AppConfig::for_tests().photo_store
+        };
+
+        assert!(security.check(&AppConfig::for_tests().photo_store).is_ok());
+        assert!(matches!(
+            security.check(&served),
+            Err(ConfigError::MissingEnvVar("PHOTO_LINK_SECRET"))
+        ));
+        let with_secret = SecurityConfig {
+            photo_link_secret: Some(strong.clone()),
+            ..security.clone()
+        };
+        assert!(with_secret.check(&served).is_ok());
+
+        // Secret dérivé : propre aux liens, distinct de la clé d'administration
+        let derived = derive_secret(&strong, "photo-links");
+        assert_ne!(derived, strong);
+        assert_eq!(derived, derive_secret(&strong, "photo-links"));
     }
 }
@@ -0,0 +1,119 @@
+//! Clés d'API des appareils.
+//!
+//! Chaque tablette reçoit sa propre clé, limitée à des droits (`scopes`) et
+//! révocable individuellement. Seule l'empreinte de la clé est conservée.
+
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use super::RecipientPolicy;
+
+/// Droit accordé à une clé d'API
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum Scope {
+    /// Export des fiches par email et suivi des envois
+    #[serde(rename = "export:send")]
+    ExportSend,
+    /// Envoi de l'historique des contacts
+    #[serde(rename = "history:send")]
+    HistorySend,
+    #[serde(rename = "contacts:read")]
+    ContactsRead,
+    /// Création, modification, fusion et suppression des fiches
+    #[serde(rename = "contacts:write")]
+    ContactsWrite,
+    /// Synchronisation bidirectionnelle d'un appareil
+    #[serde(rename = "sync")]
+    Sync,
+    /// Gestion des clés et compteurs d'audit
+    #[serde(rename = "admin")]
+    Admin,
+}
+
+impl Scope {
+    /// Tous les droits (clé d'administration)
+    pub const ALL: [Scope; 6] = [
+        Self::ExportSend,
+        Self::HistorySend,
+        Self::ContactsRead,
+        Self::ContactsWrite,
+        Self::Sync,
+        Self::Admin,
+    ];
+
+    /// Représentation texte (identique à la sérialisation JSON)
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ExportSend => "export:send",
+            Self::HistorySend => "history:send",
+            Self::ContactsRead => "contacts:read",
+            Self::ContactsWrite => "contacts:write",
+            Self::Sync => "sync",
+            Self::Admin => "admin",
+        }
+    }
+
+    /// Relit un droit depuis sa représentation texte
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|scope| scope.as_str() == value)
+    }
+}
+
+/// Clé d'API enregistrée (sans son secret)
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiKey {
+    pub id: String,
+
+    /// Appareil auquel la clé est remise
+    pub device_id: Option<String>,
+
+    pub label: String,
+
+    /// Début de la clé, pour la reconnaître sans la révéler
+    pub prefix: String,
+
+    pub scopes: Vec<Scope>,
+
+    /// Destinataires autorisés pour cette clé, à la place de la politique globale
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recipients: Option<RecipientPolicy>,
+
+    /// Dates (ms)
+    pub created_at: i64,
+    pub expires_at: Option<i64>,
+    pub last_used_at: Option<i64>,
+    pub revoked_at: Option<i64>,
+}
+
+/// Secrets d'une clé, remis une seule fois à l'émission
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiKeySecrets {
+    /// Clé à présenter dans `X-API-Key`
+    pub key: String,
+    /// Secret de signature des requêtes (HMAC-SHA256)
+    pub signing_secret: String,
+}
+
+/// Demande d'émission d'une clé d'API
+#[derive(Debug, Deserialize, Validate)]
+pub struct IssueApiKeyRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub label: String,
+
+    #[serde(default)]
+    #[validate(length(max = 100))]
+    pub device_id: Option<String>,
+
+    #[validate(length(min = 1))]
+    pub scopes: Vec<Scope>,
+
+    /// Durée de validité en jours (sans limite si absente)
+    #[serde(default)]
+    #[validate(range(min = 1, max = 3650))]
+    pub expires_in_days: Option<u32>,
+
+    /// Destinataires autorisés, à la place de la politique globale
+    #[serde(default)]
+    #[validate]
+    pub recipients: Option<RecipientPolicy>,
+}
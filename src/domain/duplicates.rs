@@ -0,0 +1,269 @@
+//! Détection et fusion des fiches en double.
+//!
+//! Un même visiteur est souvent scanné deux fois sur un stand. Deux fiches
+//! sont considérées comme doublons probables si elles partagent le même
+//! email normalisé, le même téléphone normalisé ou le même couple
+//! société + contact.
+
+use super::{split_sectors, ContactFiche, ContactStatus};
+use serde::Serialize;
+
+/// Critère ayant déclenché la détection d'un doublon
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateReason {
+    Email,
+    Telephone,
+    SocieteContact,
+}
+
+/// Doublon probable détecté à l'ingestion
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateMatch {
+    /// Position de la fiche dans la requête
+    pub index: usize,
+
+    /// Fiche de la même requête dont celle-ci est le doublon
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duplicate_of_index: Option<usize>,
+
+    /// Fiche déjà enregistrée dont celle-ci est le doublon
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duplicate_of_id: Option<String>,
+
+    pub reasons: Vec<DuplicateReason>,
+}
+
+/// Clés normalisées utilisées pour la comparaison
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DuplicateKeys {
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub name: Option<String>,
+}
+
+impl DuplicateKeys {
+    pub fn of(fiche: &ContactFiche) -> Self {
+        Self {
+            email: normalize_email(&fiche.email),
+            phone: normalize_phone(&fiche.telephone),
+            name: normalize_name(&fiche.societe, &fiche.contact),
+        }
+    }
+
+    /// Critères communs avec une autre fiche (vide si pas de doublon)
+    pub fn reasons(&self, other: &DuplicateKeys) -> Vec<DuplicateReason> {
+        let same = |a: &Option<String>, b: &Option<String>| a.is_some() && a == b;
+
+        let mut reasons = Vec::new();
+        if same(&self.email, &other.email) {
+            reasons.push(DuplicateReason::Email);
+        }
+        if same(&self.phone, &other.phone) {
+            reasons.push(DuplicateReason::Telephone);
+        }
+        if same(&self.name, &other.name) {
+            reasons.push(DuplicateReason::SocieteContact);
+        }
+        reasons
+    }
+}
+
+/// Détecte les doublons à l'intérieur d'un même lot
+///
+/// Chaque fiche est rattachée à la première fiche antérieure qui lui ressemble.
+pub fn find_batch_duplicates(fiches: &[ContactFiche]) -> Vec<DuplicateMatch> {
+    let keys: Vec<DuplicateKeys> = fiches.iter().map(DuplicateKeys::of).collect();
+
+    keys.iter()
+        .enumerate()
+        .filter_map(|(index, current)| {
+            keys[..index].iter().enumerate().find_map(|(earlier, other)| {
+                let reasons = current.reasons(other);
+                (!reasons.is_empty()).then_some(DuplicateMatch {
+                    index,
+                    duplicate_of_index: Some(earlier),
+                    duplicate_of_id: None,
+                    reasons,
+                })
+            })
+        })
+        .collect()
+}
+
+/// Fusionne deux fiches en conservant les champs les plus complets
+///
+/// Les notes différentes sont concaténées et les secteurs réunis ; la date
+/// de création retenue est la plus ancienne.
+pub fn merge_fiches(primary: &ContactFiche, other: &ContactFiche) -> ContactFiche {
+    ContactFiche {
+        societe: richest(&primary.societe, &other.societe),
+        contact: richest(&primary.contact, &other.contact),
+        email: richest(&primary.email, &other.email),
+        telephone: richest(&primary.telephone, &other.telephone),
+        notes: merge_notes(&primary.notes, &other.notes),
+        sectors: merge_sectors(&primary.sectors, &other.sectors),
+        status: merge_status(&primary.status, &other.status),
+        created_at: primary.created_at.min(other.created_at),
+        photo_base64: None,
+        photo_filename: primary.photo_filename.clone().or_else(|| other.photo_filename.clone()),
+        photo_key: None,
+    }
+}
+
+/// Garde la valeur la plus longue (la principale en cas d'égalité)
+fn richest(primary: &str, other: &str) -> String {
+    if other.trim().chars().count() > primary.trim().chars().count() {
+        other.trim().to_string()
+    } else {
+        primary.trim().to_string()
+    }
+}
+
+fn merge_notes(primary: &str, other: &str) -> String {
+    let (primary, other) = (primary.trim(), other.trim());
+    if other.is_empty() || primary.contains(other) {
+        primary.to_string()
+    } else if primary.is_empty() || other.contains(primary) {
+        other.to_string()
+    } else {
+        format!("{}\n---\n{}", primary, other)
+    }
+}
+
+fn merge_sectors(primary: &str, other: &str) -> String {
+    let mut sectors: Vec<&str> = Vec::new();
+    for sector in split_sectors(primary).chain(split_sectors(other)) {
+        if !sectors.iter().any(|s| s.eq_ignore_ascii_case(sector)) {
+            sectors.push(sector);
+        }
+    }
+    sectors.join(", ")
+}
+
+/// Un contact déjà envoyé le reste après fusion
+fn merge_status(
+    primary: &Option<ContactStatus>,
+    other: &Option<ContactStatus>,
+) -> Option<ContactStatus> {
+    match (primary, other) {
+        (Some(ContactStatus::Sent), _) | (_, Some(ContactStatus::Sent)) => {
+            Some(ContactStatus::Sent)
+        }
+        (Some(status), _) | (None, Some(status)) => Some(status.clone()),
+        (None, None) => None,
+    }
+}
+
+/// Email en minuscules, sans espaces
+pub fn normalize_email(email: &str) -> Option<String> {
+    let email = email.trim().to_lowercase();
+    email.contains('@').then_some(email)
+}
+
+/// Téléphone réduit à ses chiffres, indicatif français ramené au format national
+pub fn normalize_phone(phone: &str) -> Option<String> {
+    let digits: String = phone.chars().filter(|c| c.is_ascii_digit()).collect();
+
+    let digits = if let Some(national) = digits.strip_prefix("0033") {
+        format!("0{}", national)
+    } else if digits.len() == 11 && digits.starts_with("33") {
+        format!("0{}", &digits[2..])
+    } else {
+        digits
+    };
+
+    (digits.len() >= 6).then_some(digits)
+}
+
+/// Couple société + contact sans casse, accents ni ponctuation
+pub fn normalize_name(societe: &str, contact: &str) -> Option<String> {
+    let societe = fold(societe);
+    let contact = fold(contact);
+    (!societe.is_empty() && !contact.is_empty()).then(|| format!("{}|{}", societe, contact))
+}
+
+fn fold(value: &str) -> String {
+    let folded: String = value
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'à' | 'â' | 'ä' | 'á' | 'ã' => 'a',
+            'é' | 'è' | 'ê' | 'ë' => 'e',
+            'î' | 'ï' | 'í' => 'i',
+            'ô' | 'ö' | 'ó' | 'õ' => 'o',
+            'ù' | 'û' | 'ü' | 'ú' => 'u',
+            'ç' => 'c',
+            'ÿ' => 'y',
+            c if c.is_alphanumeric() => c,
+            _ => ' ',
+        })
+        .collect();
+
+    folded.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fiche(societe: &str, contact: &str, email: &str, telephone: &str) -> ContactFiche {
+        ContactFiche {
+            contact: contact.to_string(),
+            email: email.to_string(),
+            telephone: telephone.to_string(),
+            sectors: String::new(),
+            ..ContactFiche::sample(societe)
+        }
+    }
+
+    #[test]
+    fn test_normalization() {
+        assert_eq!(normalize_email(" Jean@ACME.fr "), Some("jean@acme.fr".to_string()));
+        assert_eq!(normalize_phone("+33 6 01 02 03 04"), normalize_phone("06.01.02.03.04"));
+        assert_eq!(normalize_phone("12"), None);
+        assert_eq!(
+            normalize_name("Société Générale", "Jean-Luc"),
+            normalize_name("SOCIETE  GENERALE", "jean luc")
+        );
+    }
+
+    #[test]
+    fn test_batch_duplicates() {
+        let fiches = vec![
+            fiche("ACME", "Jean", "jean@acme.fr", "0601020304"),
+            fiche("Globex", "Paul", "paul@globex.fr", ""),
+            fiche("Acme", "Jean", "JEAN@acme.fr", "+33 6 01 02 03 04"),
+        ];
+
+        let duplicates = find_batch_duplicates(&fiches);
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].index, 2);
+        assert_eq!(duplicates[0].duplicate_of_index, Some(0));
+        assert_eq!(duplicates[0].reasons.len(), 3);
+    }
+
+    #[test]
+    fn test_merge_keeps_richest_fields() {
+        let mut primary = fiche("ACME", "J. Dupont", "jean@acme.fr", "");
+        primary.notes = "Intéressé par les moules".to_string();
+        primary.sectors = "PHARMA".to_string();
+        primary.created_at = 200;
+
+        let mut other = fiche("ACME Industries", "Jean Dupont", "", "0601020304");
+        other.notes = "Rappeler en mars".to_string();
+        other.sectors = "pharma, AUTO".to_string();
+        other.status = Some(ContactStatus::Sent);
+        other.created_at = 100;
+
+        let merged = merge_fiches(&primary, &other);
+        assert_eq!(merged.societe, "ACME Industries");
+        assert_eq!(merged.contact, "Jean Dupont");
+        assert_eq!(merged.email, "jean@acme.fr");
+        assert_eq!(merged.telephone, "0601020304");
+        assert!(merged.notes.contains("moules") && merged.notes.contains("mars"));
+        assert_eq!(merged.sectors, "PHARMA, AUTO");
+        assert_eq!(merged.status, Some(ContactStatus::Sent));
+        assert_eq!(merged.created_at, 100);
+    }
+}
@@ -3,9 +3,23 @@
 //! Ces structures représentent les entités métier indépendamment
 //! de la couche HTTP ou du provider email.
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::ops::Range;
 use validator::Validate;
 
+mod api_keys;
+mod duplicates;
+mod recipients;
+mod users;
+
+pub use api_keys::{ApiKey, ApiKeySecrets, IssueApiKeyRequest, Scope};
+pub use duplicates::{
+    find_batch_duplicates, merge_fiches, DuplicateKeys, DuplicateMatch, DuplicateReason,
+};
+pub use recipients::RecipientPolicy;
+pub use users::{CreateUserRequest, LoginRequest, RefreshRequest, Role, TokenPair, User};
+
 // =============================================================================
 // CONTACT
 // =============================================================================
@@ -39,20 +53,40 @@ pub struct ContactFiche {
     /// Nom du fichier photo
     #[serde(default)]
     pub photo_filename: Option<String>,
+
+    /// Clé de la photo dans le stockage objet, renseignée par le serveur
+    #[serde(default, skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub photo_key: Option<String>,
 }
 
 /// Statut d'un contact
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum ContactStatus {
+    #[default]
     Pending,
     Sent,
     Error,
 }
 
-impl Default for ContactStatus {
-    fn default() -> Self {
-        Self::Pending
+impl ContactStatus {
+    /// Représentation texte (identique à la sérialisation JSON)
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Sent => "sent",
+            Self::Error => "error",
+        }
+    }
+
+    /// Relit un statut depuis sa représentation texte
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pending" => Some(Self::Pending),
+            "sent" => Some(Self::Sent),
+            "error" => Some(Self::Error),
+            _ => None,
+        }
     }
 }
 
@@ -74,6 +108,87 @@ impl ContactFiche {
                 format!("carte_visite_{}.jpg", safe_name)
             })
     }
+
+    /// Décode la photo base64 en octets bruts
+    pub fn decode_photo(&self) -> Result<Option<Vec<u8>>, base64::DecodeError> {
+        use base64::Engine;
+
+        self.photo_base64
+            .as_deref()
+            .map(|data| base64::engine::general_purpose::STANDARD.decode(data.trim()))
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+impl ContactFiche {
+    /// Fiche de test complète, sans photo ; chaque test ne remplace que les
+    /// champs qu'il vérifie
+    pub fn sample(societe: &str) -> Self {
+        Self {
+            societe: societe.to_string(),
+            contact: "Jean Dupont".to_string(),
+            email: "jean@acme.fr".to_string(),
+            telephone: "0601020304".to_string(),
+            notes: String::new(),
+            sectors: "PHARMA".to_string(),
+            status: None,
+            created_at: 1704067200000,
+            photo_base64: None,
+            photo_filename: None,
+            photo_key: None,
+        }
+    }
+}
+
+/// Découpe le champ `sectors` (séparateurs `,` ou `;`)
+pub fn split_sectors(sectors: &str) -> impl Iterator<Item = &str> {
+    sectors.split([',', ';']).map(str::trim).filter(|s| !s.is_empty())
+}
+
+/// Une fiche contact persistée côté serveur
+#[derive(Debug, Clone, Serialize)]
+pub struct StoredContact {
+    /// Identifiant serveur (UUID)
+    pub id: String,
+
+    #[serde(flatten)]
+    pub fiche: ContactFiche,
+
+    /// Appareil ayant transmis la fiche
+    pub device_id: Option<String>,
+
+    /// Photos associées (sans le contenu binaire)
+    pub photos: Vec<PhotoRef>,
+
+    /// Date de réception par le serveur (ms)
+    pub received_at: i64,
+
+    /// Date de dernière modification (ms)
+    pub updated_at: i64,
+
+    /// Version serveur, incrémentée à chaque modification
+    pub version: i64,
+
+    /// Marqueur de suppression (propagé aux appareils lors de la synchronisation)
+    pub deleted: bool,
+}
+
+/// Référence vers une photo stockée
+#[derive(Debug, Clone, Serialize)]
+pub struct PhotoRef {
+    pub id: String,
+    pub filename: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+}
+
+/// Liens temporaires vers les photos d'un export
+#[derive(Debug, Clone)]
+pub struct PhotoLinks {
+    /// Lien de chaque fiche, dans l'ordre de l'export (`None` : fiche sans photo)
+    pub urls: Vec<Option<String>>,
+    pub expires_at: DateTime<Utc>,
 }
 
 // =============================================================================
@@ -81,7 +196,7 @@ impl ContactFiche {
 // =============================================================================
 
 /// Un email à envoyer
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Email {
     pub to: String,
     pub subject: String,
@@ -89,12 +204,52 @@ pub struct Email {
     pub attachments: Vec<EmailAttachment>,
 }
 
+impl Email {
+    /// Taille estimée de l'email une fois encodé en MIME, en octets
+    ///
+    /// Le corps HTML et les pièces jointes sont comptés en base64 découpé en
+    /// lignes de 76 caractères, en-têtes de chaque partie compris.
+    pub fn encoded_size(&self) -> usize {
+        const MESSAGE_HEADERS: usize = 1024;
+        const PART_HEADERS: usize = 256;
+
+        fn with_line_breaks(base64_len: usize) -> usize {
+            base64_len + base64_len.div_ceil(76) * 2
+        }
+
+        let body = PART_HEADERS + with_line_breaks(self.html_body.len().div_ceil(3) * 4);
+        let attachments: usize = self
+            .attachments
+            .iter()
+            .map(|a| PART_HEADERS + a.filename.len() + with_line_breaks(a.content_base64.len()))
+            .sum();
+
+        MESSAGE_HEADERS + self.to.len() + self.subject.len() + body + attachments
+    }
+}
+
+/// Partie d'un export découpé en plusieurs emails
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportPart {
+    /// Numéro de la partie, à partir de 1
+    pub number: usize,
+    pub total: usize,
+    /// Positions dans la requête des fiches de la partie
+    pub range: Range<usize>,
+}
+
+impl ExportPart {
+    /// Libellé de la partie (« 2/3 »)
+    pub fn label(&self) -> String {
+        format!("{}/{}", self.number, self.total)
+    }
+}
+
 /// Pièce jointe d'un email
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EmailAttachment {
     pub filename: String,
     pub content_base64: String,
-    #[allow(dead_code)]
     pub content_type: String,
 }
 
@@ -106,6 +261,102 @@ impl EmailAttachment {
             content_type: "image/jpeg".to_string(),
         }
     }
+
+    /// Pièce jointe générée côté serveur, encodée en base64
+    pub fn from_bytes(filename: String, content_type: &str, content: &[u8]) -> Self {
+        use base64::Engine;
+
+        Self {
+            filename,
+            content_base64: base64::engine::general_purpose::STANDARD.encode(content),
+            content_type: content_type.to_string(),
+        }
+    }
+}
+
+/// État d'un envoi dans la file d'envoi
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportJobState {
+    /// En attente d'envoi (première tentative ou nouvelle tentative planifiée)
+    Queued,
+    /// En cours d'envoi par le worker
+    Sending,
+    Sent,
+    /// Refusé définitivement par le provider (destinataire ou contenu invalide)
+    Failed,
+    /// Abandonné après épuisement des tentatives
+    DeadLettered,
+}
+
+impl ExportJobState {
+    /// Représentation texte (identique à la sérialisation JSON)
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Queued => "queued",
+            Self::Sending => "sending",
+            Self::Sent => "sent",
+            Self::Failed => "failed",
+            Self::DeadLettered => "dead_lettered",
+        }
+    }
+
+    /// Relit un état depuis sa représentation texte
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "queued" => Some(Self::Queued),
+            "sending" => Some(Self::Sending),
+            "sent" => Some(Self::Sent),
+            "failed" => Some(Self::Failed),
+            "dead_lettered" => Some(Self::DeadLettered),
+            _ => None,
+        }
+    }
+}
+
+/// Un envoi d'export et son suivi
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportJob {
+    pub id: String,
+
+    /// Appareil ayant demandé l'export
+    pub device_id: Option<String>,
+
+    pub recipient: String,
+    pub subject: String,
+    pub state: ExportJobState,
+
+    /// Nombre de tentatives d'envoi effectuées
+    pub attempts: u32,
+
+    /// Variante de la dernière `EmailError` rencontrée (`rate_limited`, ...)
+    pub last_error_kind: Option<String>,
+    pub last_error: Option<String>,
+
+    /// Identifiant du message chez le provider, une fois envoyé
+    pub message_id: Option<String>,
+
+    /// Provider ayant délivré l'email
+    pub provider: Option<String>,
+
+    /// Fiches contenues dans l'export
+    pub contact_ids: Vec<String>,
+
+    /// Date de création de l'envoi (ms)
+    pub created_at: i64,
+
+    /// Date de dernière mise à jour (ms)
+    pub updated_at: i64,
+
+    /// Date de la prochaine tentative, si l'envoi est en attente (ms)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_attempt_at: Option<i64>,
+
+    /// Date d'envoi effectif (ms)
+    pub sent_at: Option<i64>,
+
+    /// Salon pendant lequel les fiches ont été collectées
+    pub salon: Option<String>,
 }
 
 // =============================================================================
@@ -128,6 +379,75 @@ pub struct ExportFichesRequest {
 
     #[allow(dead_code)]
     pub app_version: Option<String>,
+
+    /// Identifiant de l'appareil émetteur
+    #[serde(default)]
+    pub device_id: Option<String>,
+
+    /// Joindre un fichier CSV des fiches (défaut : configuration serveur)
+    #[serde(default)]
+    pub include_csv: Option<bool>,
+
+    /// Séparateur du CSV (défaut : configuration serveur)
+    #[serde(default)]
+    pub csv_separator: Option<CsvSeparator>,
+
+    /// Joindre un classeur Excel des fiches (défaut : configuration serveur)
+    #[serde(default)]
+    pub include_xlsx: Option<bool>,
+
+    /// Joindre les fiches au format vCard (défaut : configuration serveur)
+    #[serde(default)]
+    pub include_vcard: Option<bool>,
+
+    /// Un fichier vCard par fiche plutôt qu'un fichier unique
+    #[serde(default)]
+    pub vcard_per_contact: Option<bool>,
+
+    /// Joindre le rapport PDF (défaut : configuration serveur)
+    #[serde(default)]
+    pub include_pdf: Option<bool>,
+
+    /// Salon pendant lequel les fiches ont été collectées (défaut : `SALON_NAME`)
+    #[serde(default)]
+    pub salon: Option<String>,
+
+    /// Regrouper les photos dans une archive ZIP (défaut : configuration serveur)
+    #[serde(default)]
+    pub zip_photos: Option<bool>,
+
+    /// Liens temporaires vers les photos plutôt que des pièces jointes
+    /// (défaut : configuration serveur, sans effet si les photos sont en base)
+    #[serde(default)]
+    pub photo_links: Option<bool>,
+}
+
+/// Séparateur de colonnes des exports CSV
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CsvSeparator {
+    #[default]
+    Comma,
+    /// Excel en locale française attend `;`
+    Semicolon,
+}
+
+impl CsvSeparator {
+    pub fn as_char(self) -> char {
+        match self {
+            Self::Comma => ',',
+            Self::Semicolon => ';',
+        }
+    }
+
+    /// Relit un séparateur depuis `,`, `;`, `comma` ou `semicolon`
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "," | "comma" => Some(Self::Comma),
+            ";" | "semicolon" => Some(Self::Semicolon),
+            _ => None,
+        }
+    }
 }
 
 /// Réponse d'export
@@ -136,14 +456,44 @@ pub struct ExportFichesResponse {
     pub success: bool,
     pub message: String,
     pub contacts_count: usize,
+    /// Identifiants serveur des fiches enregistrées
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub contact_ids: Vec<String>,
+    /// Doublons probables détectés à l'ingestion
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub duplicates: Vec<DuplicateMatch>,
+    /// Identifiant de l'envoi dans la file d'envoi (première partie si l'export
+    /// est découpé)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub job_id: Option<String>,
+    /// Identifiants des envois de chaque partie, dans l'ordre
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub job_ids: Vec<String>,
+    /// Code de l'erreur, pour les refus que l'application traite elle-même
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<&'static str>,
 }
 
 impl ExportFichesResponse {
-    pub fn success(count: usize, recipient: &str) -> Self {
+    /// L'export est enregistré et sera envoyé par le worker de la file d'envoi,
+    /// en un email par partie
+    pub fn queued(count: usize, recipient: &str, job_ids: Vec<String>) -> Self {
+        let message = match job_ids.len() {
+            1 => format!("{} fiche(s) en cours d'envoi à {}", count, recipient),
+            parts => format!(
+                "{} fiche(s) en cours d'envoi à {} en {} emails",
+                count, recipient, parts
+            ),
+        };
         Self {
             success: true,
-            message: format!("{} fiche(s) envoyée(s) avec succès à {}", count, recipient),
+            message,
             contacts_count: count,
+            contact_ids: vec![],
+            duplicates: vec![],
+            job_id: job_ids.first().cloned(),
+            job_ids,
+            error: None,
         }
     }
 
@@ -152,8 +502,110 @@ impl ExportFichesResponse {
             success: false,
             message: message.into(),
             contacts_count: 0,
+            contact_ids: vec![],
+            duplicates: vec![],
+            job_id: None,
+            job_ids: vec![],
+            error: None,
         }
     }
+
+    pub fn with_error(mut self, code: &'static str) -> Self {
+        self.error = Some(code);
+        self
+    }
+
+    pub fn with_contact_ids(mut self, contact_ids: Vec<String>) -> Self {
+        self.contact_ids = contact_ids;
+        self
+    }
+
+    pub fn with_duplicates(mut self, duplicates: Vec<DuplicateMatch>) -> Self {
+        self.duplicates = duplicates;
+        self
+    }
+}
+
+// =============================================================================
+// SYNCHRONISATION
+// =============================================================================
+
+/// Requête de synchronisation d'un appareil
+#[derive(Debug, Deserialize, Validate)]
+pub struct SyncRequest {
+    #[validate(length(min = 1, message = "Identifiant d'appareil requis"))]
+    pub device_id: String,
+
+    /// Dernier curseur reçu du serveur (0 pour une synchronisation complète)
+    #[serde(default)]
+    pub cursor: i64,
+
+    /// Modifications locales depuis la dernière synchronisation
+    #[serde(default)]
+    pub changes: Vec<SyncChange>,
+}
+
+/// Une modification locale envoyée par l'appareil
+#[derive(Debug, Clone, Deserialize)]
+pub struct SyncChange {
+    /// Identifiant de la fiche (UUID généré par l'appareil pour une création)
+    pub id: String,
+
+    /// Version serveur sur laquelle la modification se base (absente pour une création)
+    #[serde(default)]
+    pub base_version: Option<i64>,
+
+    #[serde(default)]
+    pub deleted: bool,
+
+    /// Contenu de la fiche (ignoré pour une suppression)
+    #[serde(default)]
+    pub fiche: Option<ContactFiche>,
+}
+
+/// Réponse de synchronisation
+#[derive(Debug, Serialize)]
+pub struct SyncResponse {
+    pub success: bool,
+    pub message: String,
+    /// Curseur à renvoyer lors de la prochaine synchronisation
+    pub cursor: i64,
+    /// D'autres modifications serveur restent à récupérer
+    pub has_more: bool,
+    pub applied: Vec<SyncApplied>,
+    pub conflicts: Vec<SyncConflict>,
+    /// Modifications serveur depuis le curseur (créations, corrections, suppressions)
+    pub changes: Vec<StoredContact>,
+}
+
+impl SyncResponse {
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            success: false,
+            message: message.into(),
+            cursor: 0,
+            has_more: false,
+            applied: vec![],
+            conflicts: vec![],
+            changes: vec![],
+        }
+    }
+}
+
+/// Modification acceptée par le serveur
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncApplied {
+    pub id: String,
+    pub version: i64,
+}
+
+/// Modification refusée car la fiche a changé côté serveur
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncConflict {
+    pub id: String,
+    pub reason: String,
+    /// Version serveur actuelle, à fusionner par l'appareil
+    pub server: Option<StoredContact>,
 }
 
 // =============================================================================
@@ -194,22 +646,38 @@ mod tests {
 
     #[test]
     fn test_safe_photo_filename() {
-        let contact = ContactFiche {
-            societe: "Test Company & Co.".to_string(),
-            contact: "".to_string(),
-            email: "".to_string(),
-            telephone: "".to_string(),
-            notes: "".to_string(),
-            sectors: "".to_string(),
-            status: None,
-            created_at: 0,
-            photo_base64: None,
-            photo_filename: None,
-        };
+        let contact = ContactFiche::sample("Test Company & Co.");
 
         let filename = contact.safe_photo_filename();
         assert!(filename.starts_with("carte_visite_"));
         assert!(!filename.contains('&'));
         assert!(!filename.contains(' '));
     }
+
+    #[test]
+    fn test_contact_status_round_trip() {
+        for status in [ContactStatus::Pending, ContactStatus::Sent, ContactStatus::Error] {
+            assert_eq!(ContactStatus::parse(status.as_str()), Some(status));
+        }
+        assert_eq!(ContactStatus::parse("unknown"), None);
+    }
+
+    #[test]
+    fn test_encoded_size_counts_base64_lines() {
+        let mut email = Email {
+            to: "commercial@smp-moules.com".to_string(),
+            subject: "Export".to_string(),
+            html_body: String::new(),
+            attachments: vec![],
+        };
+        let empty = email.encoded_size();
+
+        email.attachments.push(EmailAttachment::from_bytes(
+            "photos.zip".to_string(),
+            "application/zip",
+            &[0; 570],
+        ));
+        // 570 octets : 760 caractères base64 sur 10 lignes
+        assert_eq!(email.encoded_size() - empty, 256 + "photos.zip".len() + 760 + 20);
+    }
 }
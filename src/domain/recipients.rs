@@ -0,0 +1,77 @@
+//! Destinataires autorisés des emails sortants.
+//!
+//! Sans politique, toute personne disposant d'une clé pourrait faire envoyer
+//! des emails à n'importe qui. La politique globale peut être remplacée,
+//! clé par clé, par une politique propre à l'appareil.
+
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+/// Domaines et adresses auxquels un email peut être envoyé
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, Validate)]
+pub struct RecipientPolicy {
+    /// Domaines autorisés (`*` : tous)
+    #[serde(default)]
+    #[validate(length(max = 100))]
+    pub domains: Vec<String>,
+
+    /// Adresses autorisées en plus des domaines
+    #[serde(default)]
+    #[validate(length(max = 100))]
+    pub addresses: Vec<String>,
+}
+
+impl RecipientPolicy {
+    /// Politique normalisée (minuscules, sans espaces ni `@` initial)
+    pub fn new<D, A>(domains: D, addresses: A) -> Self
+    where
+        D: IntoIterator,
+        D::Item: AsRef<str>,
+        A: IntoIterator,
+        A::Item: AsRef<str>,
+    {
+        let normalize = |value: &str| value.trim().trim_start_matches('@').to_lowercase();
+        Self {
+            domains: domains
+                .into_iter()
+                .map(|d| normalize(d.as_ref()))
+                .filter(|d| !d.is_empty())
+                .collect(),
+            addresses: addresses
+                .into_iter()
+                .map(|a| a.as_ref().trim().to_lowercase())
+                .filter(|a| !a.is_empty())
+                .collect(),
+        }
+    }
+
+    /// Le destinataire est-il autorisé ?
+    pub fn allows(&self, recipient: &str) -> bool {
+        let recipient = recipient.trim().to_lowercase();
+        let Some((_, domain)) = recipient.rsplit_once('@') else {
+            return false;
+        };
+        self.addresses.contains(&recipient)
+            || self.domains.iter().any(|allowed| allowed == "*" || allowed == domain)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_matches_domains_and_addresses() {
+        let policy = RecipientPolicy::new(["@SMP-Moules.com "], ["Partenaire@Gmail.com"]);
+        assert!(policy.allows("commercial@smp-moules.com"));
+        assert!(policy.allows("COMMERCIAL@smp-moules.COM"));
+        assert!(policy.allows("partenaire@gmail.com"));
+        assert!(!policy.allows("autre@gmail.com"));
+        // Un sous-domaine n'est pas le domaine
+        assert!(!policy.allows("x@evil.smp-moules.com"));
+        assert!(!policy.allows("smp-moules.com"));
+
+        assert!(RecipientPolicy::new(["*"], [""; 0]).allows("x@n-importe.quoi"));
+        assert!(!RecipientPolicy::default().allows("x@smp-moules.com"));
+    }
+}
@@ -0,0 +1,105 @@
+//! Comptes utilisateurs du back-office.
+//!
+//! Les responsables commerciaux se connectent avec un identifiant et un mot
+//! de passe ; leur rôle détermine les droits accordés, comme les droits
+//! (`scopes`) d'une clé d'API.
+
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use super::Scope;
+
+/// Rôle d'un utilisateur
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Tous les droits, dont la gestion des clés et des comptes
+    Admin,
+    /// Consultation et correction des fiches, exports
+    Manager,
+    /// Consultation des fiches
+    Viewer,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Admin => "admin",
+            Self::Manager => "manager",
+            Self::Viewer => "viewer",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        [Self::Admin, Self::Manager, Self::Viewer]
+            .into_iter()
+            .find(|role| role.as_str() == value)
+    }
+
+    /// Droits accordés au rôle
+    pub fn scopes(&self) -> Vec<Scope> {
+        match self {
+            Self::Admin => Scope::ALL.to_vec(),
+            Self::Manager => vec![
+                Scope::ContactsRead,
+                Scope::ContactsWrite,
+                Scope::ExportSend,
+                Scope::HistorySend,
+            ],
+            Self::Viewer => vec![Scope::ContactsRead],
+        }
+    }
+}
+
+/// Compte utilisateur (sans son mot de passe)
+#[derive(Debug, Clone, Serialize)]
+pub struct User {
+    pub id: String,
+    pub username: String,
+    pub role: Role,
+
+    /// Dates (ms)
+    pub created_at: i64,
+    pub disabled_at: Option<i64>,
+}
+
+/// Demande de création d'un compte
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateUserRequest {
+    #[validate(length(min = 3, max = 64))]
+    pub username: String,
+
+    #[validate(length(min = 12, max = 128))]
+    pub password: String,
+
+    pub role: Role,
+}
+
+/// Demande de connexion
+#[derive(Debug, Deserialize, Validate)]
+pub struct LoginRequest {
+    #[validate(length(min = 1, max = 64))]
+    pub username: String,
+
+    #[validate(length(min = 1, max = 128))]
+    pub password: String,
+}
+
+/// Demande de renouvellement (ou de révocation) d'un jeton de rafraîchissement
+#[derive(Debug, Deserialize, Validate)]
+pub struct RefreshRequest {
+    #[validate(length(min = 1, max = 128))]
+    pub refresh_token: String,
+}
+
+/// Jetons remis à la connexion et à chaque renouvellement
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenPair {
+    /// Jeton d'accès (JWT), à présenter dans `Authorization: Bearer`
+    pub access_token: String,
+    pub token_type: &'static str,
+    /// Durée de validité du jeton d'accès, en secondes
+    pub expires_in: u64,
+    /// Jeton de rafraîchissement, à usage unique
+    pub refresh_token: String,
+}
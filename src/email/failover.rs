@@ -0,0 +1,270 @@
+//! Provider composite avec bascule automatique.
+//!
+//! Essaie les providers dans l'ordre configuré et passe au suivant en cas
+//! d'erreur liée au provider (connexion, limite de taux, erreur interne,
+//! authentification). Les erreurs liées au contenu de l'email
+//! (`InvalidRequest`, `InvalidRecipient`) sont renvoyées immédiatement.
+//!
+//! Un provider en échec est écarté pendant une période de refroidissement ;
+//! à son expiration, son `health_check` décide s'il est de nouveau utilisé.
+
+use super::{Delivery, EmailError, EmailProvider, EmailResult};
+use crate::domain::Email;
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Provider email avec bascule sur une liste ordonnée de providers
+pub struct FailoverProvider {
+    providers: Vec<Arc<dyn EmailProvider>>,
+    /// Date jusqu'à laquelle chaque provider est écarté
+    unhealthy_until: Vec<Mutex<Option<Instant>>>,
+    cooldown: Duration,
+}
+
+impl FailoverProvider {
+    /// Crée un provider de bascule ; l'ordre de `providers` est l'ordre de préférence
+    pub fn new(providers: Vec<Arc<dyn EmailProvider>>, cooldown: Duration) -> Self {
+        let unhealthy_until = providers.iter().map(|_| Mutex::new(None)).collect();
+        Self {
+            providers,
+            unhealthy_until,
+            cooldown,
+        }
+    }
+
+    fn mark_unhealthy(&self, index: usize) {
+        let mut until = self.unhealthy_until[index].lock().expect("verrou de santé");
+        *until = Some(Instant::now() + self.cooldown);
+    }
+
+    fn mark_healthy(&self, index: usize) {
+        let mut until = self.unhealthy_until[index].lock().expect("verrou de santé");
+        *until = None;
+    }
+
+    fn cooling_down(&self, index: usize) -> Option<Instant> {
+        *self.unhealthy_until[index].lock().expect("verrou de santé")
+    }
+
+    /// Le provider peut-il être utilisé ? Après la période de refroidissement,
+    /// son `health_check` doit réussir pour qu'il soit réintégré.
+    async fn is_available(&self, index: usize) -> bool {
+        match self.cooling_down(index) {
+            None => true,
+            Some(until) if Instant::now() < until => false,
+            Some(_) => {
+                let provider = &self.providers[index];
+                if provider.health_check().await {
+                    info!(provider = provider.provider_name(), "Provider email réintégré");
+                    self.mark_healthy(index);
+                    true
+                } else {
+                    self.mark_unhealthy(index);
+                    false
+                }
+            }
+        }
+    }
+
+    /// Tente l'envoi via un provider ; `Ok(Err(e))` signifie « essayer le suivant »
+    async fn try_send(
+        &self,
+        index: usize,
+        email: &Email,
+    ) -> EmailResult<Result<Delivery, EmailError>> {
+        let provider = &self.providers[index];
+
+        match provider.send(email).await {
+            Ok(delivery) => {
+                self.mark_healthy(index);
+                Ok(Ok(delivery))
+            }
+            Err(e) if e.is_permanent() => Err(e),
+            Err(e) => {
+                warn!(
+                    provider = provider.provider_name(),
+                    error = %e,
+                    cooldown_secs = self.cooldown.as_secs(),
+                    "Provider email en échec, bascule sur le suivant"
+                );
+                self.mark_unhealthy(index);
+                Ok(Err(e))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl EmailProvider for FailoverProvider {
+    async fn send(&self, email: &Email) -> EmailResult<Delivery> {
+        let mut last_error = None;
+        let mut skipped = Vec::new();
+
+        for index in 0..self.providers.len() {
+            if !self.is_available(index).await {
+                skipped.push(index);
+                continue;
+            }
+            match self.try_send(index, email).await? {
+                Ok(delivery) => return Ok(delivery),
+                Err(e) => last_error = Some(e),
+            }
+        }
+
+        // Tous les providers disponibles ont échoué : dernier recours sur ceux écartés
+        for index in skipped {
+            match self.try_send(index, email).await? {
+                Ok(delivery) => return Ok(delivery),
+                Err(e) => last_error = Some(e),
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| {
+            EmailError::ProviderError("Aucun provider email configuré".to_string())
+        }))
+    }
+
+    async fn health_check(&self) -> bool {
+        for provider in &self.providers {
+            if provider.health_check().await {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn provider_name(&self) -> &'static str {
+        "failover"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    /// Provider de test dont l'erreur et l'état de santé sont pilotables
+    struct ScriptedProvider {
+        name: &'static str,
+        error: Mutex<Option<fn() -> EmailError>>,
+        healthy: AtomicBool,
+        sends: AtomicUsize,
+    }
+
+    impl ScriptedProvider {
+        fn new(name: &'static str, error: Option<fn() -> EmailError>) -> Arc<Self> {
+            Arc::new(Self {
+                name,
+                error: Mutex::new(error),
+                healthy: AtomicBool::new(true),
+                sends: AtomicUsize::new(0),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl EmailProvider for ScriptedProvider {
+        async fn send(&self, _email: &Email) -> EmailResult<Delivery> {
+            self.sends.fetch_add(1, Ordering::SeqCst);
+            match *self.error.lock().unwrap() {
+                Some(error) => Err(error()),
+                None => Ok(Delivery {
+                    message_id: format!("{}-id", self.name),
+                    provider: self.name,
+                }),
+            }
+        }
+
+        async fn health_check(&self) -> bool {
+            self.healthy.load(Ordering::SeqCst)
+        }
+
+        fn provider_name(&self) -> &'static str {
+            self.name
+        }
+    }
+
+    fn email() -> Email {
+        Email {
+            to: "commercial@smp-moules.com".to_string(),
+            subject: "Test".to_string(),
+            html_body: String::new(),
+            attachments: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_on_provider_errors() {
+        let primary = ScriptedProvider::new("primary", Some(|| {
+            EmailError::RateLimited { retry_after: None }
+        }));
+        let secondary = ScriptedProvider::new("secondary", None);
+        let failover = FailoverProvider::new(
+            vec![primary.clone(), secondary.clone()],
+            Duration::from_secs(60),
+        );
+
+        let delivery = failover.send(&email()).await.unwrap();
+        assert_eq!(delivery.provider, "secondary");
+
+        // Le provider en échec est écarté pendant le refroidissement
+        failover.send(&email()).await.unwrap();
+        assert_eq!(primary.sends.load(Ordering::SeqCst), 1);
+        assert_eq!(secondary.sends.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_does_not_fall_back_on_invalid_recipient() {
+        let primary = ScriptedProvider::new("primary", Some(|| {
+            EmailError::InvalidRecipient("x@example.com".to_string())
+        }));
+        let secondary = ScriptedProvider::new("secondary", None);
+        let failover =
+            FailoverProvider::new(vec![primary, secondary.clone()], Duration::from_secs(60));
+
+        let result = failover.send(&email()).await;
+        assert!(matches!(result, Err(EmailError::InvalidRecipient(_))));
+        assert_eq!(secondary.sends.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_provider_reinstated_after_cooldown_if_healthy() {
+        let primary = ScriptedProvider::new("primary", Some(|| {
+            EmailError::ConnectionError("timeout".to_string())
+        }));
+        let secondary = ScriptedProvider::new("secondary", None);
+        let failover = FailoverProvider::new(
+            vec![primary.clone(), secondary.clone()],
+            Duration::from_millis(20),
+        );
+
+        failover.send(&email()).await.unwrap();
+
+        // Refroidissement écoulé mais health_check en échec : toujours écarté
+        primary.healthy.store(false, Ordering::SeqCst);
+        *primary.error.lock().unwrap() = None;
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert_eq!(failover.send(&email()).await.unwrap().provider, "secondary");
+
+        // Health check de nouveau OK après refroidissement : réintégré
+        primary.healthy.store(true, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert_eq!(failover.send(&email()).await.unwrap().provider, "primary");
+    }
+
+    #[tokio::test]
+    async fn test_all_providers_failing_returns_last_error() {
+        let primary = ScriptedProvider::new("primary", Some(|| {
+            EmailError::ProviderError("500".to_string())
+        }));
+        let secondary = ScriptedProvider::new("secondary", Some(|| {
+            EmailError::ConnectionError("refused".to_string())
+        }));
+        let failover = FailoverProvider::new(vec![primary, secondary], Duration::from_secs(60));
+
+        let result = failover.send(&email()).await;
+        assert!(matches!(result, Err(EmailError::ConnectionError(_))));
+    }
+}
@@ -5,10 +5,43 @@
 //! - Tests avec mock
 //! - Changement de provider sans modifier le code métier
 
+mod failover;
 mod provider;
 mod resend;
+mod smtp;
 mod templates;
+mod worker;
 
-pub use provider::{EmailProvider, EmailError, EmailResult};
+pub use failover::FailoverProvider;
+pub use provider::{Delivery, EmailProvider, EmailError, EmailResult};
+#[cfg(test)]
+pub use provider::mock;
 pub use resend::ResendProvider;
+pub use smtp::SmtpProvider;
 pub use templates::EmailTemplates;
+pub use worker::OutboxWorker;
+
+use crate::config::{EmailConfig, EmailProviderKind};
+use std::sync::Arc;
+
+/// Construit le provider email sélectionné par la configuration
+///
+/// Plusieurs providers configurés sont combinés dans un `FailoverProvider`,
+/// dans l'ordre de préférence.
+pub fn build_provider(config: &EmailConfig) -> EmailResult<Arc<dyn EmailProvider>> {
+    let mut providers = config
+        .providers
+        .iter()
+        .map(|kind| -> EmailResult<Arc<dyn EmailProvider>> {
+            Ok(match kind {
+                EmailProviderKind::Resend => Arc::new(ResendProvider::new(config)),
+                EmailProviderKind::Smtp => Arc::new(SmtpProvider::new(config)?),
+            })
+        })
+        .collect::<EmailResult<Vec<_>>>()?;
+
+    if providers.len() == 1 {
+        return Ok(providers.remove(0));
+    }
+    Ok(Arc::new(FailoverProvider::new(providers, config.failover_cooldown)))
+}
@@ -2,6 +2,7 @@
 
 use crate::domain::Email;
 use async_trait::async_trait;
+use std::time::Duration;
 
 /// Résultat d'envoi d'email
 pub type EmailResult<T> = Result<T, EmailError>;
@@ -19,9 +20,11 @@ pub enum EmailError {
     InvalidRequest(String),
 
     #[error("Limite de taux dépassée")]
-    RateLimited,
+    RateLimited {
+        /// Délai d'attente indiqué par le provider
+        retry_after: Option<Duration>,
+    },
 
-    #[allow(dead_code)]
     #[error("Destinataire invalide: {0}")]
     InvalidRecipient(String),
 
@@ -29,6 +32,35 @@ pub enum EmailError {
     ProviderError(String),
 }
 
+impl EmailError {
+    /// L'erreur tient au contenu de l'email (requête ou destinataire) :
+    /// la renvoyer ou changer de provider n'y changera rien
+    pub fn is_permanent(&self) -> bool {
+        matches!(self, Self::InvalidRequest(_) | Self::InvalidRecipient(_))
+    }
+
+    /// Nom stable de la variante (persisté avec les envois en échec)
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::ConnectionError(_) => "connection_error",
+            Self::AuthenticationError(_) => "authentication_error",
+            Self::InvalidRequest(_) => "invalid_request",
+            Self::RateLimited { .. } => "rate_limited",
+            Self::InvalidRecipient(_) => "invalid_recipient",
+            Self::ProviderError(_) => "provider_error",
+        }
+    }
+}
+
+/// Résultat d'un envoi réussi
+#[derive(Debug, Clone)]
+pub struct Delivery {
+    /// Identifiant du message chez le provider
+    pub message_id: String,
+    /// Provider ayant effectivement délivré l'email
+    pub provider: &'static str,
+}
+
 /// Trait pour les providers d'email
 ///
 /// Permet d'abstraire le service d'envoi d'email concret (Resend, SendGrid, etc.)
@@ -36,7 +68,7 @@ pub enum EmailError {
 #[async_trait]
 pub trait EmailProvider: Send + Sync {
     /// Envoie un email
-    async fn send(&self, email: &Email) -> EmailResult<String>;
+    async fn send(&self, email: &Email) -> EmailResult<Delivery>;
 
     /// Vérifie si le service est disponible
     async fn health_check(&self) -> bool;
@@ -72,10 +104,13 @@ pub mod mock {
 
     #[async_trait]
     impl EmailProvider for MockEmailProvider {
-        async fn send(&self, _email: &Email) -> EmailResult<String> {
+        async fn send(&self, _email: &Email) -> EmailResult<Delivery> {
             self.send_count.fetch_add(1, Ordering::SeqCst);
             if self.should_succeed {
-                Ok("mock-email-id".to_string())
+                Ok(Delivery {
+                    message_id: "mock-email-id".to_string(),
+                    provider: "mock",
+                })
             } else {
                 Err(EmailError::ProviderError("Mock error".to_string()))
             }
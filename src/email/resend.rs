@@ -1,6 +1,6 @@
 //! Implémentation du provider Resend.
 
-use super::{EmailError, EmailProvider, EmailResult};
+use super::{Delivery, EmailError, EmailProvider, EmailResult};
 use crate::config::EmailConfig;
 use crate::domain::Email;
 use async_trait::async_trait;
@@ -33,7 +33,7 @@ impl ResendProvider {
 
 #[async_trait]
 impl EmailProvider for ResendProvider {
-    async fn send(&self, email: &Email) -> EmailResult<String> {
+    async fn send(&self, email: &Email) -> EmailResult<Delivery> {
         debug!(
             to = %email.to,
             subject = %email.subject,
@@ -85,15 +85,24 @@ impl EmailProvider for ResendProvider {
             })?;
 
             info!(email_id = %result.id, to = %email.to, "Email envoyé avec succès");
-            Ok(result.id)
+            Ok(Delivery {
+                message_id: result.id,
+                provider: self.provider_name(),
+            })
         } else {
+            let retry_after = response
+                .headers()
+                .get("retry-after")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.trim().parse().ok())
+                .map(std::time::Duration::from_secs);
             let error_body = response.text().await.unwrap_or_default();
 
             // Classifier l'erreur selon le code HTTP
             let error = match status.as_u16() {
                 401 => EmailError::AuthenticationError("Clé API invalide".to_string()),
                 422 => EmailError::InvalidRequest(error_body),
-                429 => EmailError::RateLimited,
+                429 => EmailError::RateLimited { retry_after },
                 _ => EmailError::ProviderError(format!("HTTP {}: {}", status, error_body)),
             };
 
@@ -150,14 +159,18 @@ struct ResendSuccessResponse {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::EmailConfig;
+    use crate::config::{EmailConfig, EmailProviderKind};
 
     fn test_config() -> EmailConfig {
         EmailConfig {
+            providers: vec![EmailProviderKind::Resend],
+            failover_cooldown: std::time::Duration::from_secs(60),
             resend_api_key: "test_key".to_string(),
+            smtp: None,
             from_name: "Test".to_string(),
             from_email: "test@example.com".to_string(),
             default_recipient: "recipient@example.com".to_string(),
+            recipients: Default::default(),
         }
     }
 
@@ -0,0 +1,326 @@
+//! Implémentation du provider SMTP.
+//!
+//! Permet de continuer à envoyer les exports via n'importe quel relais SMTP
+//! (hébergeur, Office 365, relais interne) en cas d'indisponibilité de Resend.
+
+use super::{Delivery, EmailError, EmailProvider, EmailResult};
+use crate::config::{EmailConfig, SmtpAuth, SmtpConfig, SmtpTls};
+use crate::domain::Email;
+use async_trait::async_trait;
+use base64::Engine;
+use lettre::message::header::ContentType;
+use lettre::message::{Attachment, Mailbox, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::{Credentials, Mechanism};
+use lettre::transport::smtp::client::{Tls, TlsParameters};
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use tracing::{debug, error, info};
+use uuid::Uuid;
+
+/// Provider SMTP pour l'envoi d'emails
+pub struct SmtpProvider {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpProvider {
+    /// Crée un nouveau provider SMTP
+    pub fn new(config: &EmailConfig) -> EmailResult<Self> {
+        let smtp = config.smtp.as_ref().ok_or_else(|| {
+            EmailError::InvalidRequest("Configuration SMTP manquante".to_string())
+        })?;
+
+        let from = format!("{} <{}>", config.from_name, config.from_email)
+            .parse()
+            .map_err(|e| EmailError::InvalidRequest(format!("Expéditeur invalide: {}", e)))?;
+
+        Ok(Self {
+            transport: Self::build_transport(smtp)?,
+            from,
+        })
+    }
+
+    fn build_transport(smtp: &SmtpConfig) -> EmailResult<AsyncSmtpTransport<Tokio1Executor>> {
+        let tls = match smtp.tls {
+            SmtpTls::None => Tls::None,
+            SmtpTls::StartTls => Tls::Required(Self::tls_parameters(&smtp.host)?),
+            SmtpTls::Implicit => Tls::Wrapper(Self::tls_parameters(&smtp.host)?),
+        };
+
+        let mut builder = AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&smtp.host)
+            .port(smtp.port)
+            .tls(tls)
+            .timeout(Some(std::time::Duration::from_secs(30)));
+
+        if let (Some(username), Some(password)) = (&smtp.username, &smtp.password) {
+            let mechanism = match smtp.auth {
+                SmtpAuth::Plain => Mechanism::Plain,
+                SmtpAuth::Login => Mechanism::Login,
+            };
+            builder = builder
+                .credentials(Credentials::new(username.clone(), password.clone()))
+                .authentication(vec![mechanism]);
+        }
+
+        Ok(builder.build())
+    }
+
+    fn tls_parameters(host: &str) -> EmailResult<TlsParameters> {
+        TlsParameters::new(host.to_string())
+            .map_err(|e| EmailError::ConnectionError(format!("Configuration TLS: {}", e)))
+    }
+
+    /// Construit le message MIME : HTML seul, ou multipart/mixed avec les pièces jointes
+    fn build_message(&self, email: &Email, message_id: &str) -> EmailResult<Message> {
+        let to: Mailbox = email
+            .to
+            .parse()
+            .map_err(|_| EmailError::InvalidRecipient(email.to.clone()))?;
+
+        let builder = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(email.subject.clone())
+            .message_id(Some(message_id.to_string()));
+
+        let html = SinglePart::html(email.html_body.clone());
+
+        let message = if email.attachments.is_empty() {
+            builder.singlepart(html)
+        } else {
+            let mut multipart = MultiPart::mixed().singlepart(html);
+            for attachment in &email.attachments {
+                let content = base64::engine::general_purpose::STANDARD
+                    .decode(attachment.content_base64.trim())
+                    .map_err(|e| {
+                        EmailError::InvalidRequest(format!(
+                            "Pièce jointe {} invalide: {}",
+                            attachment.filename, e
+                        ))
+                    })?;
+                let content_type = ContentType::parse(&attachment.content_type)
+                    .map_err(|_| {
+                        EmailError::InvalidRequest(format!(
+                            "Type MIME invalide: {}",
+                            attachment.content_type
+                        ))
+                    })?;
+
+                multipart = multipart.singlepart(
+                    Attachment::new(attachment.filename.clone()).body(content, content_type),
+                );
+            }
+            builder.multipart(multipart)
+        };
+
+        message.map_err(|e| EmailError::InvalidRequest(format!("Message invalide: {}", e)))
+    }
+
+    /// Identifiant de message unique dans le domaine de l'expéditeur
+    fn new_message_id(&self) -> String {
+        format!("<{}@{}>", Uuid::new_v4(), self.from.email.domain())
+    }
+}
+
+#[async_trait]
+impl EmailProvider for SmtpProvider {
+    async fn send(&self, email: &Email) -> EmailResult<Delivery> {
+        debug!(
+            to = %email.to,
+            subject = %email.subject,
+            attachments = email.attachments.len(),
+            "Envoi email via SMTP"
+        );
+
+        let message_id = self.new_message_id();
+        let message = self.build_message(email, &message_id)?;
+
+        match self.transport.send(message).await {
+            Ok(_) => {
+                info!(email_id = %message_id, to = %email.to, "Email envoyé avec succès");
+                Ok(Delivery {
+                    message_id,
+                    provider: self.provider_name(),
+                })
+            }
+            Err(e) => {
+                let error = classify_error(&e, &email.to);
+                error!(error = %error, "Erreur SMTP");
+                Err(error)
+            }
+        }
+    }
+
+    async fn health_check(&self) -> bool {
+        self.transport.test_connection().await.unwrap_or(false)
+    }
+
+    fn provider_name(&self) -> &'static str {
+        "smtp"
+    }
+}
+
+/// Classe une erreur SMTP selon son code de réponse
+fn classify_error(e: &lettre::transport::smtp::Error, recipient: &str) -> EmailError {
+    let code = e.status().map(u16::from);
+
+    match code {
+        Some(530 | 534 | 535 | 538) => EmailError::AuthenticationError(e.to_string()),
+        Some(550 | 551 | 553) => EmailError::InvalidRecipient(recipient.to_string()),
+        Some(421 | 450 | 451 | 452) => EmailError::RateLimited { retry_after: None },
+        _ if e.is_permanent() => EmailError::InvalidRequest(e.to_string()),
+        _ if e.is_transient() => EmailError::ProviderError(e.to_string()),
+        _ => EmailError::ConnectionError(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::EmailProviderKind;
+    use crate::domain::EmailAttachment;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    /// Puits SMTP minimal : accepte tout et conserve les commandes et le DATA reçus
+    async fn start_sink() -> (u16, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let log = received.clone();
+
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                let log = log.clone();
+                tokio::spawn(async move {
+                    let (read, mut write) = socket.into_split();
+                    let mut lines = BufReader::new(read).lines();
+                    write.write_all(b"220 sink ESMTP\r\n").await.unwrap();
+
+                    let mut in_data = false;
+                    let mut auth_login_step = 0;
+                    let mut data = String::new();
+                    while let Ok(Some(line)) = lines.next_line().await {
+                        // AUTH LOGIN : utilisateur puis mot de passe, encodés en base64
+                        if auth_login_step > 0 {
+                            auth_login_step += 1;
+                            let reply: &[u8] = if auth_login_step == 2 {
+                                b"334 UGFzc3dvcmQ6\r\n"
+                            } else {
+                                auth_login_step = 0;
+                                b"235 2.7.0 ok\r\n"
+                            };
+                            write.write_all(reply).await.unwrap();
+                            continue;
+                        }
+
+                        if in_data {
+                            if line == "." {
+                                in_data = false;
+                                log.lock().unwrap().push(std::mem::take(&mut data));
+                                write.write_all(b"250 2.0.0 queued\r\n").await.unwrap();
+                            } else {
+                                data.push_str(&line);
+                                data.push('\n');
+                            }
+                            continue;
+                        }
+
+                        log.lock().unwrap().push(line.clone());
+                        let command = line.to_uppercase();
+                        let reply: &[u8] = if command.starts_with("EHLO") {
+                            b"250-sink\r\n250-AUTH PLAIN LOGIN\r\n250 8BITMIME\r\n"
+                        } else if command.starts_with("AUTH LOGIN") {
+                            auth_login_step = 1;
+                            b"334 VXNlcm5hbWU6\r\n"
+                        } else if command.starts_with("AUTH PLAIN") {
+                            b"235 2.7.0 ok\r\n"
+                        } else if command.starts_with("DATA") {
+                            in_data = true;
+                            b"354 go ahead\r\n"
+                        } else if command.starts_with("QUIT") {
+                            write.write_all(b"221 bye\r\n").await.unwrap();
+                            break;
+                        } else if command.starts_with("RCPT") && command.contains("REJECT") {
+                            b"550 5.1.1 no such user\r\n"
+                        } else {
+                            b"250 ok\r\n"
+                        };
+                        write.write_all(reply).await.unwrap();
+                    }
+                });
+            }
+        });
+
+        (port, received)
+    }
+
+    fn sink_config(port: u16, auth: SmtpAuth, credentials: bool) -> EmailConfig {
+        EmailConfig {
+            providers: vec![EmailProviderKind::Smtp],
+            failover_cooldown: std::time::Duration::from_secs(60),
+            resend_api_key: String::new(),
+            smtp: Some(SmtpConfig {
+                host: "127.0.0.1".to_string(),
+                port,
+                username: credentials.then(|| "user".to_string()),
+                password: credentials.then(|| "pass".to_string()),
+                tls: SmtpTls::None,
+                auth,
+            }),
+            from_name: "SMP Moules".to_string(),
+            from_email: "export@smp-moules.com".to_string(),
+            default_recipient: "commercial@smp-moules.com".to_string(),
+            recipients: Default::default(),
+        }
+    }
+
+    fn test_email(to: &str) -> Email {
+        Email {
+            to: to.to_string(),
+            subject: "Export 1 fiche".to_string(),
+            html_body: "<p>Bonjour</p>".to_string(),
+            attachments: vec![EmailAttachment::jpeg(
+                "carte_visite_ACME.jpg".to_string(),
+                "/9j/4AAQ".to_string(),
+            )],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_multipart_with_attachment() {
+        let (port, received) = start_sink().await;
+        let provider = SmtpProvider::new(&sink_config(port, SmtpAuth::Plain, true)).unwrap();
+
+        let delivery = provider.send(&test_email("commercial@smp-moules.com")).await.unwrap();
+        assert!(delivery.message_id.ends_with("@smp-moules.com>"));
+        assert_eq!(delivery.provider, "smtp");
+
+        let received = received.lock().unwrap().join("\n");
+        assert!(received.contains("AUTH PLAIN"));
+        assert!(received.contains("RCPT TO:<commercial@smp-moules.com>"));
+        assert!(received.contains("multipart/mixed"));
+        assert!(received.contains("text/html"));
+        assert!(received.contains("filename=\"carte_visite_ACME.jpg\""));
+        assert!(received.contains("Content-Transfer-Encoding: base64"));
+        assert!(received.contains("/9j/4AAQ"));
+    }
+
+    #[tokio::test]
+    async fn test_auth_login_mechanism() {
+        let (port, received) = start_sink().await;
+        let provider = SmtpProvider::new(&sink_config(port, SmtpAuth::Login, true)).unwrap();
+
+        provider.send(&test_email("commercial@smp-moules.com")).await.unwrap();
+        assert!(received.lock().unwrap().iter().any(|l| l.starts_with("AUTH LOGIN")));
+    }
+
+    #[tokio::test]
+    async fn test_rejected_recipient_is_classified() {
+        let (port, _) = start_sink().await;
+        let provider = SmtpProvider::new(&sink_config(port, SmtpAuth::Plain, false)).unwrap();
+
+        let result = provider.send(&test_email("reject@smp-moules.com")).await;
+        assert!(matches!(result, Err(EmailError::InvalidRecipient(_))));
+    }
+}
@@ -3,18 +3,33 @@
 //! Génère le contenu HTML des emails de manière isolée
 //! et testable.
 
-use crate::domain::{ContactData, ContactFiche, ContactStatus};
+use crate::domain::{
+    ContactData, ContactFiche, ContactStatus, DuplicateMatch, DuplicateReason, ExportPart,
+    PhotoLinks,
+};
 use chrono::Utc;
+use std::ops::Range;
 
 /// Générateur de templates email
 pub struct EmailTemplates;
 
 impl EmailTemplates {
     /// Génère l'email HTML pour l'export de fiches contacts
-    pub fn export_fiches_html(contacts: &[ContactFiche]) -> String {
-        let rows = Self::build_contact_rows(contacts);
+    ///
+    /// Les doublons probables sont signalés par un badge sur la ligne concernée.
+    /// Pour un export découpé, seules les fiches de `part` sont listées. Avec
+    /// `photo_links`, les photos sont accessibles par un lien temporaire sur
+    /// chaque ligne plutôt qu'en pièces jointes.
+    pub fn export_fiches_html(
+        contacts: &[ContactFiche],
+        duplicates: &[DuplicateMatch],
+        part: Option<&ExportPart>,
+        photo_links: Option<&PhotoLinks>,
+    ) -> String {
+        let range = part.map_or(0..contacts.len(), |p| p.range.clone());
+        let rows = Self::build_contact_rows(contacts, duplicates, range.clone(), photo_links);
         let now = Utc::now().format("%d/%m/%Y à %H:%M").to_string();
-        let photo_count = contacts.iter().filter(|c| c.has_photo()).count();
+        let photo_count = contacts[range.clone()].iter().filter(|c| c.has_photo()).count();
 
         format!(
             r#"<!DOCTYPE html>
@@ -37,7 +52,7 @@ impl EmailTemplates {
             <table style="width:100%;">
                 <tr>
                     <td style="color:#333;">
-                        <strong>{count}</strong> fiche(s) contact exportée(s) le <strong>{date}</strong>
+                        <strong>{count}</strong> fiche(s) contact exportée(s) le <strong>{date}</strong>{part_info}{duplicate_info}
                     </td>
                     <td style="text-align:right;color:#666;">
                         {photo_info}
@@ -76,18 +91,32 @@ impl EmailTemplates {
     </div>
 </body>
 </html>"#,
-            count = contacts.len(),
+            count = range.len(),
             date = now,
-            photo_info = if photo_count > 0 {
-                format!("📷 {} photo(s) jointe(s)", photo_count)
-            } else {
-                String::new()
+            part_info = match part {
+                Some(part) if part.total > 1 => format!(
+                    "<br>Partie <strong>{}</strong> : fiches {} à {} sur {}",
+                    part.label(),
+                    range.start + 1,
+                    range.end,
+                    contacts.len()
+                ),
+                _ => String::new(),
+            },
+            photo_info = match photo_links {
+                _ if photo_count == 0 => String::new(),
+                Some(_) => format!("📷 {} photo(s) en lien", photo_count),
+                None => format!("📷 {} photo(s) jointe(s)", photo_count),
             },
+            duplicate_info = Self::duplicate_summary(range, duplicates),
             rows = rows,
-            photo_footer = if photo_count > 0 {
-                r#"<p style="margin:0;font-size:14px;">📷 Les photos de cartes de visite sont disponibles en pièces jointes</p>"#
-            } else {
-                ""
+            photo_footer = match photo_links {
+                _ if photo_count == 0 => String::new(),
+                Some(links) => format!(
+                    r#"<p style="margin:0;font-size:14px;">📷 Les photos de cartes de visite sont accessibles par les liens du tableau jusqu'au {}</p>"#,
+                    links.expires_at.format("%d/%m/%Y à %H:%M UTC")
+                ),
+                None => r#"<p style="margin:0;font-size:14px;">📷 Les photos de cartes de visite sont disponibles en pièces jointes</p>"#.to_string(),
             }
         )
     }
@@ -140,14 +169,29 @@ impl EmailTemplates {
     }
 
     /// Construit les lignes du tableau pour l'export
-    fn build_contact_rows(contacts: &[ContactFiche]) -> String {
-        contacts
+    fn build_contact_rows(
+        contacts: &[ContactFiche],
+        duplicates: &[DuplicateMatch],
+        range: Range<usize>,
+        photo_links: Option<&PhotoLinks>,
+    ) -> String {
+        contacts[range.clone()]
             .iter()
             .enumerate()
             .map(|(i, c)| {
                 let bg = if i % 2 == 0 { "#ffffff" } else { "#f9f9f9" };
                 let status_badge = Self::status_badge(&c.status);
-                let photo_icon = if c.has_photo() { " 📷" } else { "" };
+                let duplicate_badge =
+                    Self::duplicate_badge(range.start + i, contacts, duplicates);
+                let link = photo_links.and_then(|links| links.urls[range.start + i].as_deref());
+                let photo_icon = match link {
+                    Some(url) => format!(
+                        r#" <a href="{}" style="text-decoration:none;">📷</a>"#,
+                        html_escape(url)
+                    ),
+                    None if c.has_photo() => " 📷".to_string(),
+                    None => String::new(),
+                };
 
                 format!(
                     r#"<tr style="background:{bg};border-bottom:1px solid #eee;">
@@ -157,7 +201,7 @@ impl EmailTemplates {
                         <td style="padding:12px;">{telephone}</td>
                         <td style="padding:12px;"><span style="background:#f0f0f0;padding:2px 8px;border-radius:4px;font-size:12px;">{sectors}</span></td>
                         <td style="padding:12px;font-size:12px;color:#666;max-width:150px;overflow:hidden;text-overflow:ellipsis;">{notes}</td>
-                        <td style="padding:12px;">{status}{photo}</td>
+                        <td style="padding:12px;">{status}{photo}{duplicate}</td>
                     </tr>"#,
                     bg = bg,
                     societe = html_escape(&c.societe),
@@ -167,7 +211,8 @@ impl EmailTemplates {
                     sectors = html_escape(&c.sectors),
                     notes = html_escape(&c.notes),
                     status = status_badge,
-                    photo = photo_icon
+                    photo = photo_icon,
+                    duplicate = duplicate_badge
                 )
             })
             .collect::<Vec<_>>()
@@ -206,6 +251,58 @@ impl EmailTemplates {
             .join("\n")
     }
 
+    /// Nombre de doublons probables (vide s'il n'y en a pas)
+    fn duplicate_summary(
+        range: Range<usize>,
+        duplicates: &[DuplicateMatch],
+    ) -> String {
+        let count = range
+            .filter(|i| duplicates.iter().any(|d| d.index == *i))
+            .count();
+
+        if count == 0 {
+            String::new()
+        } else {
+            format!(
+                r#"<br><span style="color:#E65100;">⚠️ {} doublon(s) probable(s) signalé(s)</span>"#,
+                count
+            )
+        }
+    }
+
+    /// Génère le badge HTML signalant un doublon probable
+    fn duplicate_badge(
+        index: usize,
+        contacts: &[ContactFiche],
+        duplicates: &[DuplicateMatch],
+    ) -> String {
+        let Some(duplicate) = duplicates.iter().find(|d| d.index == index) else {
+            return String::new();
+        };
+
+        let reasons = duplicate
+            .reasons
+            .iter()
+            .map(|r| match r {
+                DuplicateReason::Email => "même email",
+                DuplicateReason::Telephone => "même téléphone",
+                DuplicateReason::SocieteContact => "même société et contact",
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let target = match duplicate.duplicate_of_index.and_then(|i| contacts.get(i)) {
+            Some(other) => format!(" de {}", html_escape(&other.societe)),
+            None => " d'une fiche déjà reçue".to_string(),
+        };
+
+        format!(
+            r#"<br><span style="background:#FFE0B2;color:#E65100;padding:2px 8px;border-radius:12px;font-size:11px;" title="{reasons}">⚠️ Doublon probable{target}</span>"#,
+            reasons = reasons,
+            target = target
+        )
+    }
+
     /// Génère le badge de statut HTML
     fn status_badge(status: &Option<ContactStatus>) -> String {
         match status {
@@ -245,22 +342,57 @@ mod tests {
     #[test]
     fn test_export_html_not_empty() {
         let contacts = vec![ContactFiche {
-            societe: "Test".to_string(),
-            contact: "John".to_string(),
             email: "john@test.com".to_string(),
-            telephone: "0123456789".to_string(),
-            notes: "Notes".to_string(),
-            sectors: "PHARMA".to_string(),
             status: Some(ContactStatus::Sent),
-            created_at: 1704067200000,
-            photo_base64: None,
-            photo_filename: None,
+            ..ContactFiche::sample("Test")
         }];
 
-        let html = EmailTemplates::export_fiches_html(&contacts);
+        let html = EmailTemplates::export_fiches_html(&contacts, &[], None, None);
         assert!(html.contains("Test"));
         assert!(html.contains("john@test.com"));
-        assert!(html.contains("1 fiche(s)"));
+        assert!(html.contains("<strong>1</strong> fiche(s)"));
+        assert!(!html.contains("Doublon"));
+    }
+
+    #[test]
+    fn test_export_html_links_photos() {
+        let contact = ContactFiche {
+            photo_base64: Some("AAAA".to_string()),
+            photo_key: Some("photos/a.jpg".to_string()),
+            ..ContactFiche::sample("ACME")
+        };
+        let links = PhotoLinks {
+            urls: vec![Some("https://cdn.example.com/photos/a.jpg?a=1&b=2".to_string())],
+            expires_at: Utc::now(),
+        };
+
+        let html = EmailTemplates::export_fiches_html(&[contact], &[], None, Some(&links));
+        assert!(html.contains(r#"<a href="https://cdn.example.com/photos/a.jpg?a=1&amp;b=2""#));
+        assert!(html.contains("1 photo(s) en lien"));
+        assert!(!html.contains("pièces jointes"));
+    }
+
+    #[test]
+    fn test_export_html_flags_duplicates() {
+        let contact = ContactFiche::sample("ACME");
+        let duplicates = vec![DuplicateMatch {
+            index: 1,
+            duplicate_of_index: Some(0),
+            duplicate_of_id: None,
+            reasons: vec![DuplicateReason::Email],
+        }];
+
+        let contacts = [contact.clone(), contact];
+        let html = EmailTemplates::export_fiches_html(&contacts, &duplicates, None, None);
+        assert!(html.contains("Doublon probable de ACME"));
+        assert!(html.contains("1 doublon(s) probable(s)"));
+
+        // Seconde partie d'un export découpé : le doublon reste rattaché à sa fiche
+        let part = ExportPart { number: 2, total: 2, range: 1..2 };
+        let html = EmailTemplates::export_fiches_html(&contacts, &duplicates, Some(&part), None);
+        assert!(html.contains("<strong>1</strong> fiche(s)"));
+        assert!(html.contains("Partie <strong>2/2</strong> : fiches 2 à 2 sur 2"));
+        assert!(html.contains("Doublon probable de ACME"));
     }
 
     #[test]
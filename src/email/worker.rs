@@ -0,0 +1,206 @@
+//! Worker de la file d'envoi.
+//!
+//! Envoie en arrière-plan les emails enregistrés dans la file, avec des
+//! nouvelles tentatives à délai exponentiel. Une limite de taux signalée par
+//! le provider (`RateLimited`) repousse la tentative suivante d'au moins le
+//! délai demandé. Les erreurs définitives (destinataire ou contenu invalide)
+//! ne sont pas retentées.
+
+use super::{EmailError, EmailProvider};
+use crate::config::OutboxConfig;
+use crate::domain::ExportJobState;
+use crate::storage::{OutboxJob, OutboxRepository, StorageResult};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+/// Nombre maximal d'envois réservés à chaque passage
+const CLAIM_BATCH_SIZE: u32 = 20;
+
+/// Worker d'envoi des emails de la file
+pub struct OutboxWorker {
+    outbox: OutboxRepository,
+    provider: Arc<dyn EmailProvider>,
+    config: OutboxConfig,
+}
+
+impl OutboxWorker {
+    pub fn new(
+        outbox: OutboxRepository,
+        provider: Arc<dyn EmailProvider>,
+        config: OutboxConfig,
+    ) -> Self {
+        Self {
+            outbox,
+            provider,
+            config,
+        }
+    }
+
+    /// Démarre le worker en tâche de fond
+    pub fn spawn(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            match self.outbox.requeue_interrupted().await {
+                Ok(0) => {}
+                Ok(count) => info!(count, "Envois interrompus remis en file"),
+                Err(e) => error!(error = %e, "Reprise des envois interrompus impossible"),
+            }
+
+            loop {
+                match self.process_due().await {
+                    // Lot complet : d'autres envois attendent peut-être déjà
+                    Ok(count) if count == CLAIM_BATCH_SIZE as usize => continue,
+                    Ok(_) => {}
+                    Err(e) => error!(error = %e, "Erreur de la file d'envoi"),
+                }
+                self.outbox.wait_for_jobs(self.config.poll_interval).await;
+            }
+        })
+    }
+
+    /// Traite les envois arrivés à échéance ; retourne le nombre d'envois traités
+    pub async fn process_due(&self) -> StorageResult<usize> {
+        let jobs = self.outbox.claim_due(CLAIM_BATCH_SIZE).await?;
+        let count = jobs.len();
+
+        // Un envoi dont l'état n'a pu être enregistré ne bloque pas le reste du lot
+        for job in jobs {
+            let job_id = job.id.clone();
+            if let Err(e) = self.process(job).await {
+                error!(job_id = %job_id, error = %e, "Suivi de l'envoi non enregistré");
+            }
+        }
+        Ok(count)
+    }
+
+    async fn process(&self, job: OutboxJob) -> StorageResult<()> {
+        match self.provider.send(&job.email).await {
+            Ok(delivery) => {
+                info!(
+                    job_id = %job.id,
+                    email_id = %delivery.message_id,
+                    provider = delivery.provider,
+                    attempt = job.attempt,
+                    "Export envoyé"
+                );
+                self.outbox.mark_sent(&job.id, delivery.message_id, delivery.provider).await
+            }
+            Err(e) if e.is_permanent() => {
+                error!(job_id = %job.id, error = %e, "Export refusé définitivement");
+                self.outbox
+                    .mark_failed(&job.id, ExportJobState::Failed, e.kind(), e.to_string())
+                    .await
+            }
+            Err(e) if job.attempt >= self.config.max_attempts => {
+                error!(
+                    job_id = %job.id,
+                    error = %e,
+                    attempts = job.attempt,
+                    "Export abandonné après épuisement des tentatives"
+                );
+                self.outbox
+                    .mark_failed(&job.id, ExportJobState::DeadLettered, e.kind(), e.to_string())
+                    .await
+            }
+            Err(e) => {
+                let delay = retry_delay(&self.config, job.attempt, &e);
+                warn!(
+                    job_id = %job.id,
+                    error = %e,
+                    attempt = job.attempt,
+                    retry_in_secs = delay.as_secs(),
+                    "Échec d'envoi, nouvelle tentative planifiée"
+                );
+                self.outbox.mark_retry(&job.id, e.kind(), e.to_string(), delay).await
+            }
+        }
+    }
+}
+
+/// Délai avant la tentative suivant la tentative `attempt` (1 pour le premier envoi)
+fn retry_delay(config: &OutboxConfig, attempt: u32, error: &EmailError) -> Duration {
+    let exponent = attempt.saturating_sub(1).min(16);
+    let backoff = config
+        .retry_base_delay
+        .saturating_mul(1 << exponent)
+        .min(config.retry_max_delay);
+
+    match error {
+        EmailError::RateLimited {
+            retry_after: Some(retry_after),
+        } => backoff.max(*retry_after),
+        _ => backoff,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Email;
+    use crate::email::mock::MockEmailProvider;
+    use crate::storage::Database;
+
+    fn email() -> Email {
+        Email {
+            to: "commercial@smp-moules.com".to_string(),
+            subject: "Export".to_string(),
+            html_body: String::new(),
+            attachments: vec![],
+        }
+    }
+
+    #[test]
+    fn test_retry_delay_is_exponential_and_honors_rate_limits() {
+        let config = OutboxConfig {
+            retry_base_delay: Duration::from_secs(10),
+            retry_max_delay: Duration::from_secs(60),
+            ..OutboxConfig::default()
+        };
+        let error = EmailError::ConnectionError("timeout".to_string());
+
+        assert_eq!(retry_delay(&config, 1, &error), Duration::from_secs(10));
+        assert_eq!(retry_delay(&config, 3, &error), Duration::from_secs(40));
+        assert_eq!(retry_delay(&config, 30, &error), Duration::from_secs(60));
+
+        let rate_limited = EmailError::RateLimited {
+            retry_after: Some(Duration::from_secs(120)),
+        };
+        assert_eq!(retry_delay(&config, 1, &rate_limited), Duration::from_secs(120));
+    }
+
+    #[tokio::test]
+    async fn test_jobs_are_retried_then_dead_lettered() {
+        let outbox = OutboxRepository::new(Database::open_in_memory().unwrap());
+        let config = OutboxConfig {
+            max_attempts: 2,
+            retry_base_delay: Duration::ZERO,
+            ..OutboxConfig::default()
+        };
+        let failing = OutboxWorker::new(
+            outbox.clone(),
+            Arc::new(MockEmailProvider::new(false)),
+            config.clone(),
+        );
+
+        let id = outbox.enqueue(email(), None, vec![], None).await.unwrap();
+        failing.process_due().await.unwrap();
+        let job = outbox.get(&id).await.unwrap().unwrap();
+        assert_eq!(job.state, ExportJobState::Queued);
+        assert_eq!(job.last_error_kind.as_deref(), Some("provider_error"));
+
+        failing.process_due().await.unwrap();
+        let job = outbox.get(&id).await.unwrap().unwrap();
+        assert_eq!(job.state, ExportJobState::DeadLettered);
+        assert_eq!(job.attempts, 2);
+
+        // Un envoi réussi est marqué comme envoyé
+        let provider = Arc::new(MockEmailProvider::new(true));
+        let worker = OutboxWorker::new(outbox.clone(), provider.clone(), config);
+        let id = outbox.enqueue(email(), None, vec![], None).await.unwrap();
+        worker.process_due().await.unwrap();
+        let job = outbox.get(&id).await.unwrap().unwrap();
+        assert_eq!(job.state, ExportJobState::Sent);
+        assert_eq!(job.provider.as_deref(), Some("mock"));
+        assert_eq!(provider.get_send_count(), 1);
+    }
+}
@@ -0,0 +1,141 @@
+//! Export CSV des fiches contacts.
+//!
+//! Le fichier est encodé en UTF-8 avec BOM pour qu'Excel reconnaisse les
+//! accents ; le séparateur `;` est attendu par Excel en locale française.
+
+use super::{format_created_at, photo_filenames};
+use crate::domain::{ContactFiche, CsvSeparator};
+
+/// Marque d'ordre des octets UTF-8
+pub(super) const UTF8_BOM: &str = "\u{FEFF}";
+
+const HEADERS: [&str; 9] = [
+    "Société",
+    "Contact",
+    "Email",
+    "Téléphone",
+    "Notes",
+    "Secteurs",
+    "Statut",
+    "Date de création",
+    "Photo",
+];
+
+/// Génère le CSV des fiches (une ligne d'en-tête puis une ligne par fiche)
+pub fn fiches_csv(contacts: &[ContactFiche], separator: CsvSeparator) -> Vec<u8> {
+    let separator = separator.as_char();
+    let mut csv = String::from(UTF8_BOM);
+
+    write_row(&mut csv, separator, HEADERS.iter().copied());
+    for (contact, photo) in contacts.iter().zip(photo_filenames(contacts)) {
+        let status = contact.status.clone().unwrap_or_default();
+        let created_at = format_created_at(contact.created_at);
+        let photo = photo.unwrap_or_default();
+
+        write_row(
+            &mut csv,
+            separator,
+            [
+                contact.societe.as_str(),
+                &contact.contact,
+                &contact.email,
+                &contact.telephone,
+                &contact.notes,
+                &contact.sectors,
+                status.as_str(),
+                &created_at,
+                &photo,
+            ],
+        );
+    }
+
+    csv.into_bytes()
+}
+
+pub(super) fn write_row<'a>(csv: &mut String, separator: char, fields: impl IntoIterator<Item = &'a str>) {
+    for (index, field) in fields.into_iter().enumerate() {
+        if index > 0 {
+            csv.push(separator);
+        }
+        write_field(csv, separator, field);
+    }
+    csv.push_str("\r\n");
+}
+
+/// Premiers caractères qu'un tableur interprète comme le début d'une formule
+const FORMULA_PREFIXES: [char; 6] = ['=', '+', '-', '@', '\t', '\r'];
+
+/// Écrit un champ, entre guillemets s'il contient un séparateur, un guillemet
+/// ou un retour à la ligne (RFC 4180)
+///
+/// Un champ qui commencerait une formule est préfixé d'une apostrophe : le
+/// tableur l'affiche comme du texte au lieu de l'évaluer.
+fn write_field(csv: &mut String, separator: char, field: &str) {
+    let field = if field.starts_with(FORMULA_PREFIXES) {
+        format!("'{}", field)
+    } else {
+        field.to_string()
+    };
+    let needs_quotes = field.contains([separator, '"', '\n', '\r']);
+    if needs_quotes {
+        csv.push('"');
+        csv.push_str(&field.replace('"', "\"\""));
+        csv.push('"');
+    } else {
+        csv.push_str(&field);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::ContactStatus;
+
+    fn fiche() -> ContactFiche {
+        ContactFiche {
+            contact: "Jean \"JD\" Dupont".to_string(),
+            telephone: "+33 6 01 02 03 04".to_string(),
+            notes: "Ligne 1\nLigne 2".to_string(),
+            sectors: "PHARMA, AUTO".to_string(),
+            status: Some(ContactStatus::Sent),
+            ..ContactFiche::sample("ACME; Fils")
+        }
+    }
+
+    #[test]
+    fn test_csv_with_bom_and_quoting() {
+        let csv = String::from_utf8(fiches_csv(&[fiche()], CsvSeparator::Comma)).unwrap();
+        assert!(csv.starts_with('\u{FEFF}'));
+
+        let lines: Vec<&str> = csv.trim_start_matches('\u{FEFF}').split("\r\n").collect();
+        assert!(lines[0].starts_with("Société,Contact,Email"));
+        assert_eq!(
+            lines[1],
+            "ACME; Fils,\"Jean \"\"JD\"\" Dupont\",jean@acme.fr,'+33 6 01 02 03 04,\
+             \"Ligne 1\nLigne 2\",\"PHARMA, AUTO\",sent,01/01/2024 00:00,"
+        );
+    }
+
+    #[test]
+    fn test_csv_neutralizes_formulas() {
+        let fiche = ContactFiche {
+            societe: "=HYPERLINK(\"http://evil\")".to_string(),
+            contact: "@SUM(A1)".to_string(),
+            notes: "-2+3".to_string(),
+            sectors: "\tPHARMA".to_string(),
+            ..fiche()
+        };
+        let csv = String::from_utf8(fiches_csv(&[fiche], CsvSeparator::Semicolon)).unwrap();
+        let row = csv.split("\r\n").nth(1).unwrap();
+        assert!(row.starts_with("\"'=HYPERLINK(\"\"http://evil\"\")\";'@SUM(A1);"));
+        assert!(row.contains(";'-2+3;'\tPHARMA;"));
+    }
+
+    #[test]
+    fn test_csv_semicolon_separator() {
+        let csv = String::from_utf8(fiches_csv(&[fiche()], CsvSeparator::Semicolon)).unwrap();
+        let row = csv.split("\r\n").nth(1).unwrap();
+        assert!(row.starts_with("\"ACME; Fils\";"));
+        assert!(row.contains(";PHARMA, AUTO;"));
+    }
+}
@@ -0,0 +1,109 @@
+//! Génération des fichiers joints aux exports.
+//!
+//! Chaque format produit les octets du fichier à partir des fiches ; le
+//! handler d'export les transforme en `EmailAttachment`.
+
+mod csv;
+mod parts;
+mod pdf;
+mod vcard;
+mod xlsx;
+mod zip;
+
+pub use csv::fiches_csv;
+pub use parts::split_parts;
+pub use pdf::fiches_pdf;
+pub use vcard::{fiche_vcards, fiches_vcard};
+pub use xlsx::fiches_xlsx;
+pub use self::zip::photos_zip;
+
+use crate::domain::ContactFiche;
+use chrono::{TimeZone, Utc};
+use std::collections::HashSet;
+
+/// Résultat de génération d'un fichier d'export
+pub type ExportResult<T> = Result<T, ExportError>;
+
+/// Erreurs possibles lors de la génération d'un fichier d'export
+#[derive(Debug, thiserror::Error)]
+pub enum ExportError {
+    #[error("Erreur de génération du classeur Excel: {0}")]
+    Xlsx(#[from] rust_xlsxwriter::XlsxError),
+
+    #[error("Erreur de génération de l'archive des photos: {0}")]
+    Zip(#[from] ::zip::result::ZipError),
+
+    #[error("Photo {filename} illisible: {source}")]
+    UnreadablePhoto {
+        filename: String,
+        source: base64::DecodeError,
+    },
+}
+
+/// Date de création d'une fiche (ms) au format `jj/mm/aaaa hh:mm` (UTC)
+pub fn format_created_at(created_at: i64) -> String {
+    Utc.timestamp_millis_opt(created_at)
+        .single()
+        .map(|date| date.format("%d/%m/%Y %H:%M").to_string())
+        .unwrap_or_default()
+}
+
+/// Nom de fichier horodaté pour un export (`fiches_contacts_20240101_0930.csv`)
+pub fn export_filename(extension: &str) -> String {
+    format!("fiches_contacts_{}.{}", Utc::now().format("%Y%m%d_%H%M"), extension)
+}
+
+/// Nom de fichier sûr et unique dans un même export
+///
+/// Les caractères non alphanumériques sont remplacés par `_` ; un suffixe
+/// `_2`, `_3`, ... distingue les noms déjà utilisés (sans tenir compte de la casse).
+pub fn unique_filename(stem: &str, extension: &str, used: &mut HashSet<String>) -> String {
+    let mut safe = String::with_capacity(stem.len());
+    for c in stem.trim().chars() {
+        if c.is_alphanumeric() || c == '-' {
+            safe.push(c);
+        } else if !safe.ends_with('_') {
+            safe.push('_');
+        }
+    }
+    let safe = safe.trim_matches('_');
+    let safe = if safe.is_empty() { "contact" } else { safe };
+
+    let mut suffix = 1;
+    loop {
+        let name = if suffix == 1 {
+            format!("{}.{}", safe, extension)
+        } else {
+            format!("{}_{}.{}", safe, suffix, extension)
+        };
+        if used.insert(name.to_lowercase()) {
+            return name;
+        }
+        suffix += 1;
+    }
+}
+
+/// Base des noms de fichiers propres à une fiche (« société contact »)
+fn contact_stem(contact: &ContactFiche) -> String {
+    if contact.contact.trim().is_empty() {
+        contact.societe.clone()
+    } else {
+        format!("{} {}", contact.societe, contact.contact)
+    }
+}
+
+/// Noms des photos des fiches (`None` pour une fiche sans photo)
+///
+/// Les noms dépendent uniquement de l'ordre et du contenu des fiches : les
+/// pièces jointes, l'archive et la colonne « Photo » des exports concordent.
+pub fn photo_filenames(contacts: &[ContactFiche]) -> Vec<Option<String>> {
+    let mut used = HashSet::new();
+    contacts
+        .iter()
+        .map(|contact| {
+            contact
+                .has_photo()
+                .then(|| unique_filename(&contact_stem(contact), "jpg", &mut used))
+        })
+        .collect()
+}
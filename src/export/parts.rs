@@ -0,0 +1,80 @@
+//! Découpage d'un export en plusieurs emails.
+//!
+//! Resend et la plupart des relais SMTP limitent la taille totale d'un email.
+//! Au-delà du budget, les fiches sont réparties en parties consécutives,
+//! chacune envoyée dans son propre email avec ses fichiers et ses photos.
+
+use super::ExportResult;
+use std::ops::Range;
+
+/// Répartit `count` fiches en parties consécutives qui respectent `budget`
+///
+/// `size_of` retourne la taille encodée de l'email d'une plage de fiches.
+/// Une fiche qui dépasse seule le budget forme sa propre partie.
+pub fn split_parts<F>(
+    count: usize,
+    budget: usize,
+    mut size_of: F,
+) -> ExportResult<Vec<Range<usize>>>
+where
+    F: FnMut(Range<usize>) -> ExportResult<usize>,
+{
+    if count == 0 || size_of(0..count)? <= budget {
+        return Ok(std::iter::once(0..count).collect());
+    }
+
+    // Première répartition d'après la taille de chaque fiche exportée seule :
+    // l'en-tête et le corps de l'email étant comptés pour chacune, elle surestime
+    let mut ranges = Vec::new();
+    let (mut start, mut total) = (0, 0);
+    for i in 0..count {
+        let size = size_of(i..i + 1)?;
+        if i > start && total + size > budget {
+            ranges.push(start..i);
+            (start, total) = (i, 0);
+        }
+        total += size;
+    }
+    ranges.push(start..count);
+
+    // Chaque partie est vérifiée, et coupée en deux tant qu'elle dépasse
+    let mut pending: Vec<Range<usize>> = ranges.into_iter().rev().collect();
+    let mut parts = Vec::new();
+    while let Some(range) = pending.pop() {
+        if range.len() > 1 && size_of(range.clone())? > budget {
+            let middle = range.start + range.len() / 2;
+            pending.push(middle..range.end);
+            pending.push(range.start..middle);
+        } else {
+            parts.push(range);
+        }
+    }
+    Ok(parts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parts_follow_fiche_sizes() {
+        let weights = [50, 50, 50, 200, 10];
+        let size_of = |range: Range<usize>| Ok(20 + weights[range].iter().sum::<usize>());
+
+        assert_eq!(split_parts(5, 1000, size_of).unwrap().len(), 1);
+        // La quatrième fiche dépasse seule le budget : elle est envoyée à part
+        assert_eq!(split_parts(5, 150, size_of).unwrap(), vec![0..2, 2..3, 3..4, 4..5]);
+    }
+
+    #[test]
+    fn test_oversized_parts_are_halved() {
+        // Taille qui croît plus vite que la somme des fiches
+        let size_of = |range: Range<usize>| Ok(10 * range.len() * range.len());
+        let parts = split_parts(10, 100, size_of).unwrap();
+
+        assert_eq!(parts.first().unwrap().start, 0);
+        assert_eq!(parts.last().unwrap().end, 10);
+        assert!(parts.windows(2).all(|w| w[0].end == w[1].start));
+        assert!(parts.iter().all(|r| size_of(r.clone()).unwrap() <= 100));
+    }
+}
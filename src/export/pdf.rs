@@ -0,0 +1,606 @@
+//! Rapport PDF des fiches contacts.
+//!
+//! Le rapport commence par une page de garde (salon, nombre de fiches,
+//! répartition par statut et par secteur) suivie d'une page par fiche avec
+//! tous ses champs et la photo de la carte de visite. Le PDF est écrit
+//! directement : polices standard Helvetica et photos JPEG intégrées telles
+//! quelles, sans bibliothèque ni service externe.
+
+use super::format_created_at;
+use crate::domain::{split_sectors, ContactFiche, ContactStatus};
+use chrono::Utc;
+
+/// Format A4 en points
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 50.0;
+
+/// Couleur de la marque (#CC0033)
+const BRAND: &str = "0.8 0 0.2";
+
+/// Largeur des libellés des champs d'une fiche
+const LABEL_WIDTH: f32 = 120.0;
+
+/// Hauteur minimale laissée à une photo avant de passer à la page suivante
+const MIN_PHOTO_HEIGHT: f32 = 150.0;
+
+/// Chasses Helvetica des caractères ASCII 32 à 126 (millièmes de corps)
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722,
+    722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722, 667, 611, 722,
+    667, 944, 667, 667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556, 556, 278, 556,
+    556, 222, 222, 500, 222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500,
+    500, 334, 260, 334, 584,
+];
+
+/// Génère le rapport PDF des fiches
+pub fn fiches_pdf(salon: Option<&str>, contacts: &[ContactFiche]) -> Vec<u8> {
+    let mut layout = Layout::default();
+    cover_page(&mut layout, salon, contacts);
+    for contact in contacts {
+        fiche_page(&mut layout, contact);
+    }
+    write_document(layout.finish())
+}
+
+fn cover_page(layout: &mut Layout, salon: Option<&str>, contacts: &[ContactFiche]) {
+    layout.new_page();
+
+    // Bandeau de titre
+    let page = layout.page();
+    page.fill_rect(0.0, PAGE_HEIGHT - 130.0, PAGE_WIDTH, 130.0, BRAND);
+    page.text(Font::Bold, 26.0, MARGIN, PAGE_HEIGHT - 75.0, "1 1 1", "Rapport de prospection");
+    page.text(Font::Regular, 14.0, MARGIN, PAGE_HEIGHT - 102.0, "1 1 1", "SMP Moules");
+    layout.cursor = PAGE_HEIGHT - 180.0;
+
+    let salon = salon.map(str::trim).filter(|s| !s.is_empty());
+    layout.line(Font::Regular, 12.0, "Salon");
+    layout.paragraph(Font::Bold, 20.0, MARGIN, salon.unwrap_or("Salon non renseigné"));
+    layout.line(
+        Font::Regular,
+        10.0,
+        &format!("Rapport généré le {} (UTC)", Utc::now().format("%d/%m/%Y à %H:%M")),
+    );
+    layout.gap(20.0);
+
+    let with_photo = contacts.iter().filter(|c| c.has_photo()).count();
+    let with_email = contacts.iter().filter(|c| !c.email.trim().is_empty()).count();
+    layout.heading("Synthèse");
+    layout.field("Fiches", &contacts.len().to_string());
+    layout.field("Avec photo", &with_photo.to_string());
+    layout.field("Avec email", &with_email.to_string());
+    layout.gap(10.0);
+
+    layout.heading("Par statut");
+    for (label, count) in status_counts(contacts) {
+        layout.field(label, &count.to_string());
+    }
+    layout.gap(10.0);
+
+    let sectors = sector_counts(contacts);
+    if !sectors.is_empty() {
+        layout.heading("Par secteur");
+        for (sector, count) in &sectors {
+            layout.field(sector, &count.to_string());
+        }
+    }
+}
+
+fn fiche_page(layout: &mut Layout, contact: &ContactFiche) {
+    layout.new_page();
+
+    let title = if contact.societe.trim().is_empty() {
+        "Société non renseignée"
+    } else {
+        contact.societe.trim()
+    };
+    layout.paragraph(Font::Bold, 20.0, MARGIN, title);
+    layout.rule();
+    layout.gap(6.0);
+
+    let sectors: Vec<&str> = split_sectors(&contact.sectors).collect();
+    layout.field("Contact", &contact.contact);
+    layout.field("Email", &contact.email);
+    layout.field("Téléphone", &contact.telephone);
+    layout.field("Secteurs", &sectors.join(", "));
+    layout.field("Statut", status_label(contact.status.as_ref()));
+    layout.field("Date de création", &format_created_at(contact.created_at));
+    layout.field("Notes", &contact.notes);
+    layout.gap(12.0);
+
+    if !contact.has_photo() {
+        return;
+    }
+    match contact.decode_photo().ok().flatten().and_then(Jpeg::parse) {
+        Some(jpeg) => layout.photo(jpeg),
+        None => layout.line(Font::Regular, 10.0, "Photo non affichable (format non JPEG)"),
+    }
+}
+
+fn status_label(status: Option<&ContactStatus>) -> &'static str {
+    match status {
+        Some(ContactStatus::Sent) => "Envoyé",
+        Some(ContactStatus::Error) => "Erreur",
+        Some(ContactStatus::Pending) | None => "En attente",
+    }
+}
+
+/// Nombre de fiches par statut, dans un ordre fixe
+fn status_counts(contacts: &[ContactFiche]) -> Vec<(&'static str, usize)> {
+    ["En attente", "Envoyé", "Erreur"]
+        .into_iter()
+        .map(|label| {
+            let count = contacts
+                .iter()
+                .filter(|c| status_label(c.status.as_ref()) == label)
+                .count();
+            (label, count)
+        })
+        .collect()
+}
+
+/// Nombre de fiches par secteur (sans tenir compte de la casse), triés
+fn sector_counts(contacts: &[ContactFiche]) -> Vec<(String, usize)> {
+    let mut counts: Vec<(String, usize)> = Vec::new();
+    for contact in contacts {
+        let mut seen: Vec<&str> = Vec::new();
+        for sector in split_sectors(&contact.sectors) {
+            if seen.iter().any(|s| s.eq_ignore_ascii_case(sector)) {
+                continue;
+            }
+            seen.push(sector);
+            match counts.iter_mut().find(|(s, _)| s.eq_ignore_ascii_case(sector)) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((sector.to_string(), 1)),
+            }
+        }
+    }
+    counts.sort_by_key(|(s, _)| s.to_lowercase());
+    counts
+}
+
+// =============================================================================
+// MISE EN PAGE
+// =============================================================================
+
+#[derive(Clone, Copy)]
+enum Font {
+    Regular,
+    Bold,
+}
+
+impl Font {
+    fn resource(self) -> &'static str {
+        match self {
+            Self::Regular => "/F1",
+            Self::Bold => "/F2",
+        }
+    }
+}
+
+/// Photo JPEG intégrée sans réencodage (filtre DCTDecode)
+struct Jpeg {
+    data: Vec<u8>,
+    width: u16,
+    height: u16,
+    components: u8,
+}
+
+impl Jpeg {
+    /// Lit les dimensions dans l'en-tête SOF ; `None` si ce n'est pas un JPEG exploitable
+    fn parse(data: Vec<u8>) -> Option<Self> {
+        if !data.starts_with(&[0xFF, 0xD8]) {
+            return None;
+        }
+        let mut i = 2;
+        while i + 4 <= data.len() {
+            if data[i] != 0xFF {
+                return None;
+            }
+            let marker = data[i + 1];
+            if marker == 0xFF {
+                i += 1;
+                continue;
+            }
+            if marker == 0x01 || (0xD0..=0xD8).contains(&marker) {
+                i += 2;
+                continue;
+            }
+            let length = u16::from_be_bytes([data[i + 2], data[i + 3]]) as usize;
+            if (0xC0..=0xCF).contains(&marker) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+                let segment = data.get(i + 4..i + 2 + length)?;
+                if segment.len() < 6 {
+                    return None;
+                }
+                let height = u16::from_be_bytes([segment[1], segment[2]]);
+                let width = u16::from_be_bytes([segment[3], segment[4]]);
+                let components = segment[5];
+                if width == 0 || height == 0 || !matches!(components, 1 | 3 | 4) {
+                    return None;
+                }
+                return Some(Self {
+                    data,
+                    width,
+                    height,
+                    components,
+                });
+            }
+            i += 2 + length;
+        }
+        None
+    }
+}
+
+#[derive(Default)]
+struct Page {
+    content: Vec<u8>,
+    image: Option<Jpeg>,
+}
+
+impl Page {
+    fn text(&mut self, font: Font, size: f32, x: f32, y: f32, color: &str, text: &str) {
+        self.content.extend_from_slice(
+            format!("BT {} rg {} {} Tf {} {} Td (", color, font.resource(), size, x, y).as_bytes(),
+        );
+        self.content.extend(encode(text));
+        self.content.extend_from_slice(b") Tj ET\n");
+    }
+
+    fn fill_rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: &str) {
+        self.content.extend_from_slice(
+            format!("{} rg {} {} {} {} re f\n", color, x, y, width, height).as_bytes(),
+        );
+    }
+}
+
+/// Pages en cours de construction et position verticale courante
+#[derive(Default)]
+struct Layout {
+    pages: Vec<Page>,
+    cursor: f32,
+}
+
+impl Layout {
+    fn new_page(&mut self) {
+        self.pages.push(Page::default());
+        self.cursor = PAGE_HEIGHT - MARGIN;
+    }
+
+    fn page(&mut self) -> &mut Page {
+        self.pages.last_mut().expect("page courante")
+    }
+
+    /// Passe à une nouvelle page si la hauteur demandée ne tient plus
+    fn reserve(&mut self, height: f32) {
+        if self.cursor - height < MARGIN {
+            self.new_page();
+        }
+    }
+
+    fn gap(&mut self, height: f32) {
+        self.cursor -= height;
+    }
+
+    fn line(&mut self, font: Font, size: f32, text: &str) {
+        self.paragraph(font, size, MARGIN, text);
+    }
+
+    /// Texte replié sur la largeur disponible à partir de `x`
+    fn paragraph(&mut self, font: Font, size: f32, x: f32, text: &str) {
+        for line in wrap(text, size, PAGE_WIDTH - MARGIN - x) {
+            self.reserve(size * 1.4);
+            self.cursor -= size * 1.4;
+            let y = self.cursor;
+            self.page().text(font, size, x, y, "0.2 0.2 0.2", &line);
+        }
+    }
+
+    fn heading(&mut self, text: &str) {
+        self.reserve(40.0);
+        self.line(Font::Bold, 14.0, text);
+        self.gap(4.0);
+    }
+
+    /// Ligne « libellé : valeur », la valeur étant repliée à droite du libellé
+    fn field(&mut self, label: &str, value: &str) {
+        let value = if value.trim().is_empty() { "-" } else { value.trim() };
+        self.reserve(11.0 * 1.4);
+        let top = self.cursor;
+        let y = top - 11.0 * 1.4;
+        self.page().text(Font::Bold, 11.0, MARGIN, y, "0.4 0.4 0.4", label);
+        self.paragraph(Font::Regular, 11.0, MARGIN + LABEL_WIDTH, value);
+        self.gap(4.0);
+    }
+
+    fn rule(&mut self) {
+        self.cursor -= 6.0;
+        let y = self.cursor;
+        self.page().fill_rect(MARGIN, y, PAGE_WIDTH - 2.0 * MARGIN, 1.5, BRAND);
+    }
+
+    /// Photo mise à l'échelle dans l'espace restant (ou sur une nouvelle page)
+    fn photo(&mut self, jpeg: Jpeg) {
+        if self.cursor - MARGIN < MIN_PHOTO_HEIGHT || self.page().image.is_some() {
+            self.new_page();
+        }
+        let max_width = PAGE_WIDTH - 2.0 * MARGIN;
+        let max_height = self.cursor - MARGIN;
+        let scale = (max_width / jpeg.width as f32).min(max_height / jpeg.height as f32);
+        let (width, height) = (jpeg.width as f32 * scale, jpeg.height as f32 * scale);
+
+        self.cursor -= height;
+        let y = self.cursor;
+        let page = self.page();
+        page.content.extend_from_slice(
+            format!("q {} 0 0 {} {} {} cm /Im1 Do Q\n", width, height, MARGIN, y).as_bytes(),
+        );
+        page.image = Some(jpeg);
+    }
+
+    /// Ajoute la numérotation et retourne les pages
+    fn finish(mut self) -> Vec<Page> {
+        let total = self.pages.len();
+        for (index, page) in self.pages.iter_mut().enumerate() {
+            let label = format!("Page {} / {}", index + 1, total);
+            let x = PAGE_WIDTH - MARGIN - text_width(&label, 9.0);
+            page.text(Font::Regular, 9.0, x, MARGIN / 2.0, "0.5 0.5 0.5", &label);
+        }
+        self.pages
+    }
+}
+
+/// Largeur approximative d'un texte en points (chasses Helvetica)
+fn text_width(text: &str, size: f32) -> f32 {
+    let units: u32 = text
+        .chars()
+        .map(|c| match c {
+            ' '..='~' => HELVETICA_WIDTHS[c as usize - 32] as u32,
+            c if c.is_alphabetic() => 556,
+            _ => 500,
+        })
+        .sum();
+    units as f32 * size / 1000.0
+}
+
+/// Replie un texte (retours à la ligne compris) sur une largeur donnée
+fn wrap(text: &str, size: f32, max_width: f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let candidate = if line.is_empty() {
+                word.to_string()
+            } else {
+                format!("{} {}", line, word)
+            };
+            if text_width(&candidate, size) <= max_width {
+                line = candidate;
+                continue;
+            }
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+            // Mot plus long que la ligne : coupé au caractère
+            for c in word.chars() {
+                if !line.is_empty() && text_width(&format!("{}{}", line, c), size) > max_width {
+                    lines.push(std::mem::take(&mut line));
+                }
+                line.push(c);
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// Encode un texte en WinAnsi et échappe les délimiteurs de chaîne PDF
+///
+/// Les caractères absents de WinAnsi (emoji, ...) sont remplacés par `?`.
+fn encode(text: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(text.len());
+    for c in text.chars() {
+        let byte = match c {
+            '(' | ')' | '\\' => {
+                bytes.push(b'\\');
+                c as u8
+            }
+            '\t' => b' ',
+            c if c.is_control() => continue,
+            ' '..='~' | '\u{A0}'..='\u{FF}' => c as u32 as u8,
+            '€' => 0x80,
+            '‚' => 0x82,
+            '„' => 0x84,
+            '…' => 0x85,
+            'Œ' => 0x8C,
+            '‘' => 0x91,
+            '’' => 0x92,
+            '“' => 0x93,
+            '”' => 0x94,
+            '•' => 0x95,
+            '–' => 0x96,
+            '—' => 0x97,
+            '™' => 0x99,
+            'œ' => 0x9C,
+            'Ÿ' => 0x9F,
+            _ => b'?',
+        };
+        bytes.push(byte);
+    }
+    bytes
+}
+
+// =============================================================================
+// ÉCRITURE DU DOCUMENT
+// =============================================================================
+
+/// Identifiants des objets fixes du document
+const CATALOG_ID: usize = 1;
+const PAGES_ID: usize = 2;
+const FONT_REGULAR_ID: usize = 3;
+const FONT_BOLD_ID: usize = 4;
+
+/// Assemble les objets PDF, la table xref et le trailer
+fn write_document(pages: Vec<Page>) -> Vec<u8> {
+    // objects[i] contient l'objet d'identifiant i + 1
+    let mut objects: Vec<Vec<u8>> = vec![
+        format!("<< /Type /Catalog /Pages {} 0 R >>", PAGES_ID).into_bytes(),
+        Vec::new(),
+        font("Helvetica"),
+        font("Helvetica-Bold"),
+    ];
+
+    let mut kids = Vec::with_capacity(pages.len());
+    for page in pages {
+        let mut xobjects = String::new();
+        if let Some(jpeg) = page.image {
+            let (color_space, decode) = match jpeg.components {
+                1 => ("/DeviceGray", ""),
+                3 => ("/DeviceRGB", ""),
+                // Les JPEG CMJN (Adobe) sont stockés inversés
+                _ => ("/DeviceCMYK", " /Decode [1 0 1 0 1 0 1 0]"),
+            };
+            let dict = format!(
+                "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace {} \
+                 /BitsPerComponent 8 /Filter /DCTDecode{}",
+                jpeg.width, jpeg.height, color_space, decode
+            );
+            objects.push(stream(&dict, &jpeg.data));
+            xobjects = format!(" /XObject << /Im1 {} 0 R >>", objects.len());
+        }
+
+        objects.push(stream("", &page.content));
+        let content_id = objects.len();
+        objects.push(
+            format!(
+                "<< /Type /Page /Parent {} 0 R /MediaBox [0 0 {} {}] \
+                 /Resources << /Font << /F1 {} 0 R /F2 {} 0 R >>{} >> /Contents {} 0 R >>",
+                PAGES_ID, PAGE_WIDTH, PAGE_HEIGHT, FONT_REGULAR_ID, FONT_BOLD_ID, xobjects,
+                content_id
+            )
+            .into_bytes(),
+        );
+        kids.push(format!("{} 0 R", objects.len()));
+    }
+    objects[PAGES_ID - 1] = format!(
+        "<< /Type /Pages /Kids [{}] /Count {} >>",
+        kids.join(" "),
+        kids.len()
+    )
+    .into_bytes();
+
+    let mut pdf = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (index, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n", index + 1).as_bytes());
+        pdf.extend_from_slice(object);
+        pdf.extend_from_slice(b"\nendobj\n");
+    }
+
+    let xref = pdf.len();
+    pdf.extend_from_slice(
+        format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes(),
+    );
+    for offset in offsets {
+        pdf.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+    }
+    pdf.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} /Root {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            CATALOG_ID,
+            xref
+        )
+        .as_bytes(),
+    );
+    pdf
+}
+
+fn font(name: &str) -> Vec<u8> {
+    format!(
+        "<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>",
+        name
+    )
+    .into_bytes()
+}
+
+fn stream(dict: &str, data: &[u8]) -> Vec<u8> {
+    let mut object = format!("<< {} /Length {} >>\nstream\n", dict, data.len()).into_bytes();
+    object.extend_from_slice(data);
+    object.extend_from_slice(b"\nendstream");
+    object
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine;
+
+    /// En-tête JPEG minimal (SOI + SOF0 3 composantes, 40 x 20)
+    const JPEG: [u8; 21] = [
+        0xFF, 0xD8, 0xFF, 0xC0, 0x00, 0x11, 0x08, 0x00, 0x14, 0x00, 0x28, 0x03, 0x01, 0x22, 0x00,
+        0x02, 0x11, 0x01, 0x03, 0x11, 0x01,
+    ];
+
+    fn fiche(societe: &str, photo: Option<&[u8]>) -> ContactFiche {
+        ContactFiche {
+            notes: "Très intéressé (moules) ".repeat(40),
+            sectors: "PHARMA, auto".to_string(),
+            status: Some(ContactStatus::Sent),
+            photo_base64: photo.map(|p| base64::engine::general_purpose::STANDARD.encode(p)),
+            ..ContactFiche::sample(societe)
+        }
+    }
+
+    #[test]
+    fn test_report_structure() {
+        let contacts = [fiche("ACME", Some(&JPEG)), fiche("Globex", Some(b"PNG"))];
+        let pdf = fiches_pdf(Some("Salon Pharmapack"), &contacts);
+        let text = String::from_utf8_lossy(&pdf);
+
+        assert!(pdf.starts_with(b"%PDF-1.4"));
+        assert!(text.ends_with("%%EOF\n"));
+        // Page de garde + une page par fiche
+        assert!(text.contains("/Count 3"));
+        assert!(text.contains("(Salon Pharmapack)"));
+        assert!(text.contains("(Page 3 / 3)"));
+        // Seule la photo JPEG est intégrée
+        assert_eq!(text.matches("/Subtype /Image").count(), 1);
+        assert!(text.contains("/Width 40 /Height 20 /ColorSpace /DeviceRGB"));
+        assert!(text.contains("Photo non affichable"));
+
+        // La table xref pointe sur chaque objet (positions en octets bruts)
+        let xref = text.rfind("\nxref\n").unwrap();
+        let entries: Vec<usize> = text[xref..]
+            .lines()
+            .skip(4)
+            .take_while(|l| l.ends_with(" n "))
+            .map(|l| l[..10].parse().unwrap())
+            .collect();
+        let size: usize = text[xref..].lines().nth(2).unwrap()[2..].parse().unwrap();
+        assert_eq!(entries.len(), size - 1);
+        for (index, offset) in entries.iter().enumerate() {
+            assert!(pdf[*offset..].starts_with(format!("{} 0 obj", index + 1).as_bytes()));
+        }
+    }
+
+    #[test]
+    fn test_text_encoding_and_wrapping() {
+        assert_eq!(encode("Société (A\\B) 😀"), b"Soci\xE9t\xE9 \\(A\\\\B\\) ?".to_vec());
+        assert_eq!(encode("50 €"), b"50 \x80".to_vec());
+
+        let lines = wrap("un deux trois\nquatre", 10.0, text_width("un deux", 10.0));
+        assert_eq!(lines, vec!["un deux", "trois", "quatre"]);
+        assert!(wrap(&"x".repeat(200), 10.0, 100.0).iter().all(|l| text_width(l, 10.0) <= 100.0));
+    }
+
+    #[test]
+    fn test_sector_counts() {
+        let contacts = [fiche("A", None), fiche("B", None)];
+        assert_eq!(
+            sector_counts(&contacts),
+            vec![("auto".to_string(), 2), ("PHARMA".to_string(), 2)]
+        );
+    }
+}
@@ -0,0 +1,152 @@
+//! Export vCard 4.0 (RFC 6350) des fiches contacts.
+//!
+//! Permet d'ajouter directement les visiteurs du salon au carnet d'adresses,
+//! soit via un fichier unique contenant toutes les fiches, soit via un
+//! fichier par fiche.
+
+use super::{contact_stem, unique_filename};
+use crate::domain::{split_sectors, ContactFiche};
+use std::collections::HashSet;
+
+/// Longueur maximale d'une ligne avant repliement, en octets (hors CRLF)
+const MAX_LINE_OCTETS: usize = 75;
+
+/// Génère un fichier contenant les vCards de toutes les fiches
+pub fn fiches_vcard(contacts: &[ContactFiche]) -> Vec<u8> {
+    contacts.iter().map(vcard).collect::<String>().into_bytes()
+}
+
+/// Génère un fichier vCard par fiche, avec des noms de fichiers uniques
+pub fn fiche_vcards(contacts: &[ContactFiche]) -> Vec<(String, Vec<u8>)> {
+    let mut used = HashSet::new();
+    contacts
+        .iter()
+        .map(|contact| {
+            let filename = unique_filename(&contact_stem(contact), "vcf", &mut used);
+            (filename, vcard(contact).into_bytes())
+        })
+        .collect()
+}
+
+/// vCard d'une fiche
+fn vcard(contact: &ContactFiche) -> String {
+    let mut card = String::new();
+    push_line(&mut card, "BEGIN:VCARD");
+    push_line(&mut card, "VERSION:4.0");
+
+    // FN est obligatoire : à défaut de nom de contact, on utilise la société
+    let name = if contact.contact.trim().is_empty() {
+        &contact.societe
+    } else {
+        &contact.contact
+    };
+    push_line(&mut card, &format!("FN:{}", escape(name.trim())));
+    push_line(&mut card, &format!("ORG:{}", escape(contact.societe.trim())));
+
+    if !contact.email.trim().is_empty() {
+        push_line(&mut card, &format!("EMAIL;TYPE=work:{}", escape(contact.email.trim())));
+    }
+    if let Some(tel) = tel_uri(&contact.telephone) {
+        push_line(&mut card, &format!("TEL;TYPE=work;VALUE=uri:{}", tel));
+    }
+    if !contact.notes.trim().is_empty() {
+        push_line(&mut card, &format!("NOTE:{}", escape(contact.notes.trim())));
+    }
+
+    let categories: Vec<String> = split_sectors(&contact.sectors).map(escape).collect();
+    if !categories.is_empty() {
+        push_line(&mut card, &format!("CATEGORIES:{}", categories.join(",")));
+    }
+
+    if let Some(photo) = &contact.photo_base64 {
+        let photo: String = photo.chars().filter(|c| !c.is_whitespace()).collect();
+        if !photo.is_empty() {
+            push_line(&mut card, &format!("PHOTO:data:image/jpeg;base64,{}", photo));
+        }
+    }
+
+    push_line(&mut card, "END:VCARD");
+    card
+}
+
+/// Numéro au format URI `tel:` (chiffres et `+` initial uniquement)
+fn tel_uri(telephone: &str) -> Option<String> {
+    let trimmed = telephone.trim();
+    let digits: String = trimmed.chars().filter(|c| c.is_ascii_digit()).collect();
+    if digits.is_empty() {
+        return None;
+    }
+    let plus = if trimmed.starts_with('+') { "+" } else { "" };
+    Some(format!("tel:{}{}", plus, digits))
+}
+
+/// Échappe une valeur texte (`\`, `,`, `;` et retours à la ligne)
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ',' => escaped.push_str("\\,"),
+            ';' => escaped.push_str("\\;"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Ajoute une ligne terminée par CRLF, repliée tous les 75 octets
+/// sans couper de caractère UTF-8
+fn push_line(card: &mut String, line: &str) {
+    let mut octets = 0;
+    for c in line.chars() {
+        if octets + c.len_utf8() > MAX_LINE_OCTETS {
+            card.push_str("\r\n ");
+            // L'espace de continuation compte dans la ligne suivante
+            octets = 1;
+        }
+        card.push(c);
+        octets += c.len_utf8();
+    }
+    card.push_str("\r\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fiche() -> ContactFiche {
+        ContactFiche {
+            telephone: "+33 6 01 02 03 04".to_string(),
+            notes: "Rappeler en mars\nStand B12".to_string(),
+            sectors: "PHARMA, AUTO".to_string(),
+            photo_base64: Some("/9j/4AAQ".repeat(30)),
+            ..ContactFiche::sample("ACME; Fils")
+        }
+    }
+
+    #[test]
+    fn test_vcard_fields() {
+        let card = String::from_utf8(fiches_vcard(&[fiche()])).unwrap();
+        assert!(card.starts_with("BEGIN:VCARD\r\nVERSION:4.0\r\n"));
+        assert!(card.contains("FN:Jean Dupont\r\n"));
+        assert!(card.contains("ORG:ACME\\; Fils\r\n"));
+        assert!(card.contains("EMAIL;TYPE=work:jean@acme.fr\r\n"));
+        assert!(card.contains("TEL;TYPE=work;VALUE=uri:tel:+33601020304\r\n"));
+        assert!(card.contains("NOTE:Rappeler en mars\\nStand B12\r\n"));
+        assert!(card.contains("CATEGORIES:PHARMA,AUTO\r\n"));
+        assert!(card.contains("PHOTO:data:image/jpeg;base64,/9j/"));
+        assert!(card.ends_with("END:VCARD\r\n"));
+
+        // Toutes les lignes (photo comprise) sont repliées à 75 octets
+        assert!(card.split("\r\n").all(|line| line.len() <= MAX_LINE_OCTETS));
+    }
+
+    #[test]
+    fn test_one_file_per_contact_with_unique_names() {
+        let files = fiche_vcards(&[fiche(), fiche()]);
+        assert_eq!(files[0].0, "ACME_Fils_Jean_Dupont.vcf");
+        assert_eq!(files[1].0, "ACME_Fils_Jean_Dupont_2.vcf");
+    }
+}
@@ -0,0 +1,193 @@
+//! Export Excel (XLSX) des fiches contacts.
+//!
+//! Le classeur contient une feuille « Fiches » avec toutes les fiches, puis
+//! une feuille par secteur rencontré dans `ContactFiche.sectors`. Chaque
+//! feuille a un en-tête figé, un filtre automatique et une colonne date typée.
+
+use super::{photo_filenames, ExportResult};
+use crate::domain::{split_sectors, ContactFiche};
+use rust_xlsxwriter::{Color, ExcelDateTime, Format, Workbook, Worksheet};
+
+/// Nom de la feuille principale
+const ALL_SHEET: &str = "Fiches";
+
+/// Longueur maximale d'un nom de feuille Excel
+const MAX_SHEET_NAME: usize = 31;
+
+const HEADERS: [(&str, f64); 9] = [
+    ("Société", 28.0),
+    ("Contact", 24.0),
+    ("Email", 30.0),
+    ("Téléphone", 18.0),
+    ("Notes", 40.0),
+    ("Secteurs", 24.0),
+    ("Statut", 10.0),
+    ("Date de création", 18.0),
+    ("Photo", 30.0),
+];
+
+/// Colonne de la date de création
+const DATE_COLUMN: u16 = 7;
+
+/// Génère le classeur des fiches
+pub fn fiches_xlsx(contacts: &[ContactFiche]) -> ExportResult<Vec<u8>> {
+    let mut workbook = Workbook::new();
+    let header = Format::new()
+        .set_bold()
+        .set_font_color(Color::White)
+        .set_background_color(Color::RGB(0xCC0033));
+    let date = Format::new().set_num_format("dd/mm/yyyy hh:mm");
+
+    // Chaque fiche garde le même nom de photo dans toutes les feuilles
+    let rows: Vec<SheetRow> = contacts
+        .iter()
+        .zip(photo_filenames(contacts))
+        .map(|(contact, photo)| (contact, photo.unwrap_or_default()))
+        .collect();
+
+    let all: Vec<&SheetRow> = rows.iter().collect();
+    write_sheet(workbook.add_worksheet(), ALL_SHEET, &all, &header, &date)?;
+
+    let mut sheet_names = vec![ALL_SHEET.to_string()];
+    for sector in distinct_sectors(contacts) {
+        let fiches: Vec<&SheetRow> = rows
+            .iter()
+            .filter(|(c, _)| split_sectors(&c.sectors).any(|s| s.eq_ignore_ascii_case(&sector)))
+            .collect();
+
+        let name = unique_sheet_name(&sector, &sheet_names);
+        write_sheet(workbook.add_worksheet(), &name, &fiches, &header, &date)?;
+        sheet_names.push(name);
+    }
+
+    Ok(workbook.save_to_buffer()?)
+}
+
+/// Fiche et nom de sa photo
+type SheetRow<'a> = (&'a ContactFiche, String);
+
+fn write_sheet(
+    sheet: &mut Worksheet,
+    name: &str,
+    contacts: &[&SheetRow],
+    header: &Format,
+    date: &Format,
+) -> ExportResult<()> {
+    sheet.set_name(name)?;
+
+    for (col, (title, width)) in HEADERS.iter().enumerate() {
+        sheet.write_string_with_format(0, col as u16, *title, header)?;
+        sheet.set_column_width(col as u16, *width)?;
+    }
+
+    for (index, (contact, photo)) in contacts.iter().enumerate() {
+        let row = index as u32 + 1;
+        let status = contact.status.clone().unwrap_or_default();
+
+        // Toujours du texte : une saisie commençant par `=` n'est jamais une formule
+        sheet.write_string(row, 0, &contact.societe)?;
+        sheet.write_string(row, 1, &contact.contact)?;
+        sheet.write_string(row, 2, &contact.email)?;
+        sheet.write_string(row, 3, &contact.telephone)?;
+        sheet.write_string(row, 4, &contact.notes)?;
+        sheet.write_string(row, 5, &contact.sectors)?;
+        sheet.write_string(row, 6, status.as_str())?;
+        match ExcelDateTime::from_timestamp(contact.created_at.div_euclid(1000)) {
+            Ok(created_at) => sheet.write_datetime_with_format(row, DATE_COLUMN, created_at, date)?,
+            Err(_) => sheet.write_string(row, DATE_COLUMN, "")?,
+        };
+        sheet.write_string(row, 8, photo)?;
+    }
+
+    sheet.set_freeze_panes(1, 0)?;
+    sheet.autofilter(0, 0, contacts.len() as u32, HEADERS.len() as u16 - 1)?;
+    Ok(())
+}
+
+/// Secteurs distincts (sans tenir compte de la casse), triés
+fn distinct_sectors(contacts: &[ContactFiche]) -> Vec<String> {
+    let mut sectors: Vec<String> = Vec::new();
+    for sector in contacts.iter().flat_map(|c| split_sectors(&c.sectors)) {
+        if !sectors.iter().any(|s| s.eq_ignore_ascii_case(sector)) {
+            sectors.push(sector.to_string());
+        }
+    }
+    sectors.sort_by_key(|s| s.to_lowercase());
+    sectors
+}
+
+/// Nom de feuille valide pour Excel, distinct des noms déjà utilisés
+fn unique_sheet_name(sector: &str, used: &[String]) -> String {
+    let base: String = sector
+        .chars()
+        .map(|c| match c {
+            '[' | ']' | ':' | '*' | '?' | '/' | '\\' => '_',
+            c => c,
+        })
+        .collect();
+    let base = base.trim_matches('\'').trim();
+    let base = if base.is_empty() { "Secteur" } else { base };
+
+    let taken = |name: &str| used.iter().any(|u| u.to_lowercase() == name.to_lowercase());
+
+    let mut suffix = 1;
+    loop {
+        let tag = if suffix == 1 {
+            String::new()
+        } else {
+            format!(" ({})", suffix)
+        };
+        let name: String = base
+            .chars()
+            .take(MAX_SHEET_NAME - tag.chars().count())
+            .collect::<String>()
+            + &tag;
+        if !taken(&name) {
+            return name;
+        }
+        suffix += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fiche(societe: &str, sectors: &str) -> ContactFiche {
+        ContactFiche {
+            sectors: sectors.to_string(),
+            ..ContactFiche::sample(societe)
+        }
+    }
+
+    #[test]
+    fn test_workbook_is_a_zip_archive() {
+        let xlsx = fiches_xlsx(&[fiche("ACME", "PHARMA, AUTO"), fiche("Globex", "pharma")])
+            .unwrap();
+        assert!(xlsx.starts_with(b"PK"));
+    }
+
+    #[test]
+    fn test_formulas_are_written_as_text() {
+        let xlsx = fiches_xlsx(&[fiche("=HYPERLINK(\"http://evil\")", "+PHARMA")]).unwrap();
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(xlsx)).unwrap();
+        let mut sheet = String::new();
+        std::io::Read::read_to_string(
+            &mut archive.by_name("xl/worksheets/sheet1.xml").unwrap(),
+            &mut sheet,
+        )
+        .unwrap();
+        assert!(!sheet.contains("<f>"));
+    }
+
+    #[test]
+    fn test_sheet_names() {
+        let contacts = [fiche("A", "Pharma; AUTO"), fiche("B", "auto, Médical/Santé")];
+        assert_eq!(distinct_sectors(&contacts), vec!["AUTO", "Médical/Santé", "Pharma"]);
+
+        let used = vec!["Fiches".to_string()];
+        assert_eq!(unique_sheet_name("Médical/Santé", &used), "Médical_Santé");
+        assert_eq!(unique_sheet_name("fiches", &used), "fiches (2)");
+        assert_eq!(unique_sheet_name(&"x".repeat(40), &used).chars().count(), 31);
+    }
+}
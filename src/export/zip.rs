@@ -0,0 +1,123 @@
+//! Archive ZIP des photos de cartes de visite.
+//!
+//! Remplace les photos jointes une à une : l'archive contient une photo par
+//! fiche, nommée selon `photo_filenames`, et un fichier `index.csv` associant
+//! chaque photo à sa fiche.
+
+use super::csv::{write_row, UTF8_BOM};
+use super::{photo_filenames, ExportError, ExportResult};
+use crate::domain::{ContactFiche, CsvSeparator};
+use std::io::{Cursor, Write};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// Nom du fichier d'index dans l'archive
+const INDEX_FILENAME: &str = "index.csv";
+
+const INDEX_HEADERS: [&str; 5] = ["Photo", "Société", "Contact", "Email", "Téléphone"];
+
+/// Génère l'archive des photos, ou `None` si aucune fiche n'a de photo
+///
+/// Les JPEG étant déjà compressés, les fichiers sont stockés sans compression.
+/// Les dates des entrées sont fixes : les mêmes fiches donnent la même archive.
+/// Une photo illisible fait échouer l'export plutôt que de manquer à l'archive
+/// alors que la colonne « Photo » du CSV et du classeur la nomme.
+pub fn photos_zip(
+    contacts: &[ContactFiche],
+    separator: CsvSeparator,
+) -> ExportResult<Option<Vec<u8>>> {
+    let mut photos: Vec<(&ContactFiche, String, Vec<u8>)> = Vec::new();
+    for (contact, filename) in contacts.iter().zip(photo_filenames(contacts)) {
+        let Some(filename) = filename else { continue };
+        match contact.decode_photo() {
+            Ok(Some(data)) => photos.push((contact, filename, data)),
+            Ok(None) => {}
+            Err(source) => return Err(ExportError::UnreadablePhoto { filename, source }),
+        }
+    }
+    if photos.is_empty() {
+        return Ok(None);
+    }
+
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let mut index = String::from(UTF8_BOM);
+    write_row(&mut index, separator.as_char(), INDEX_HEADERS);
+
+    for (contact, filename, data) in &photos {
+        zip.start_file(filename.as_str(), options)?;
+        zip.write_all(data).map_err(::zip::result::ZipError::from)?;
+        write_row(
+            &mut index,
+            separator.as_char(),
+            [
+                filename.as_str(),
+                &contact.societe,
+                &contact.contact,
+                &contact.email,
+                &contact.telephone,
+            ],
+        );
+    }
+
+    zip.start_file(INDEX_FILENAME, options)?;
+    zip.write_all(index.as_bytes()).map_err(::zip::result::ZipError::from)?;
+
+    Ok(Some(zip.finish()?.into_inner()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use zip::ZipArchive;
+
+    fn fiche(societe: &str, photo: Option<&str>) -> ContactFiche {
+        ContactFiche {
+            contact: "Jean".to_string(),
+            telephone: String::new(),
+            photo_base64: photo.map(str::to_string),
+            ..ContactFiche::sample(societe)
+        }
+    }
+
+    #[test]
+    fn test_archive_has_unique_names_and_index() {
+        let contacts = [
+            fiche("ACME", Some("aGVsbG8=")),
+            fiche("Globex", None),
+            fiche("ACME", Some("d29ybGQ=")),
+        ];
+        let zip = photos_zip(&contacts, CsvSeparator::Semicolon).unwrap().unwrap();
+        assert_eq!(zip, photos_zip(&contacts, CsvSeparator::Semicolon).unwrap().unwrap());
+
+        let mut archive = ZipArchive::new(Cursor::new(zip)).unwrap();
+        let names: Vec<&str> = archive.file_names().collect();
+        assert_eq!(names, vec!["ACME_Jean.jpg", "ACME_Jean_2.jpg", "index.csv"]);
+
+        let mut photo = String::new();
+        archive.by_name("ACME_Jean_2.jpg").unwrap().read_to_string(&mut photo).unwrap();
+        assert_eq!(photo, "world");
+
+        let mut index = String::new();
+        archive.by_name("index.csv").unwrap().read_to_string(&mut index).unwrap();
+        let lines: Vec<&str> = index.trim_start_matches('\u{FEFF}').lines().collect();
+        assert_eq!(lines[0], "Photo;Société;Contact;Email;Téléphone");
+        assert_eq!(lines[2], "ACME_Jean_2.jpg;ACME;Jean;jean@acme.fr;");
+    }
+
+    #[test]
+    fn test_unreadable_photo_fails_the_archive() {
+        let contacts = [fiche("ACME", Some("aGVsbG8=")), fiche("Globex", Some("pas du base64!"))];
+        let err = photos_zip(&contacts, CsvSeparator::Comma).unwrap_err();
+        assert!(matches!(
+            err,
+            ExportError::UnreadablePhoto { filename, .. } if filename == "Globex_Jean.jpg"
+        ));
+    }
+
+    #[test]
+    fn test_no_archive_without_photos() {
+        assert!(photos_zip(&[fiche("ACME", None)], CsvSeparator::Comma).unwrap().is_none());
+    }
+}
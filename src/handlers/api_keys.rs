@@ -0,0 +1,178 @@
+//! Handlers d'administration des clés d'API des appareils.
+
+use actix_web::{web, HttpResponse};
+use serde::Serialize;
+use tracing::{error, info, instrument};
+use validator::Validate;
+
+use crate::domain::{ApiKey, ApiKeySecrets, IssueApiKeyRequest};
+use crate::storage::{ApiKeyRepository, StorageError};
+
+#[derive(Serialize)]
+pub struct ApiKeyResponse {
+    success: bool,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    api_key: Option<ApiKey>,
+    /// Clé complète et secret de signature, retournés uniquement à l'émission
+    #[serde(flatten)]
+    secrets: Option<ApiKeySecrets>,
+}
+
+impl ApiKeyResponse {
+    fn success(
+        message: impl Into<String>,
+        api_key: Option<ApiKey>,
+        secrets: Option<ApiKeySecrets>,
+    ) -> Self {
+        Self {
+            success: true,
+            message: message.into(),
+            api_key,
+            secrets,
+        }
+    }
+
+    fn error(message: impl Into<String>) -> Self {
+        Self {
+            success: false,
+            message: message.into(),
+            api_key: None,
+            secrets: None,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ApiKeyListResponse {
+    success: bool,
+    api_keys: Vec<ApiKey>,
+}
+
+/// POST /api/admin/api-keys
+///
+/// Émet une clé pour un appareil ; la clé complète et son secret de
+/// signature ne sont retournés qu'ici.
+#[instrument(skip(body, keys))]
+pub async fn issue_api_key(
+    body: web::Json<IssueApiKeyRequest>,
+    keys: web::Data<ApiKeyRepository>,
+) -> HttpResponse {
+    if let Err(errors) = body.validate() {
+        return HttpResponse::BadRequest().json(ApiKeyResponse::error(format!(
+            "Validation échouée: {:?}",
+            errors
+        )));
+    }
+
+    match keys.issue(body.into_inner()).await {
+        Ok((api_key, secrets)) => {
+            info!(key_id = %api_key.id, device_id = ?api_key.device_id, "Clé d'API émise");
+            HttpResponse::Created().json(ApiKeyResponse::success(
+                "Clé d'API émise",
+                Some(api_key),
+                Some(secrets),
+            ))
+        }
+        Err(e) => storage_error_response(e),
+    }
+}
+
+/// GET /api/admin/api-keys
+#[instrument(skip(keys))]
+pub async fn list_api_keys(keys: web::Data<ApiKeyRepository>) -> HttpResponse {
+    match keys.list().await {
+        Ok(api_keys) => HttpResponse::Ok().json(ApiKeyListResponse {
+            success: true,
+            api_keys,
+        }),
+        Err(e) => storage_error_response(e),
+    }
+}
+
+/// DELETE /api/admin/api-keys/{id}
+///
+/// Révoque une clé : elle est refusée dès la requête suivante.
+#[instrument(skip(keys))]
+pub async fn revoke_api_key(
+    path: web::Path<String>,
+    keys: web::Data<ApiKeyRepository>,
+) -> HttpResponse {
+    match keys.revoke(&path).await {
+        Ok(true) => {
+            info!(key_id = %path.as_str(), "Clé d'API révoquée");
+            HttpResponse::Ok().json(ApiKeyResponse::success("Clé d'API révoquée", None, None))
+        }
+        Ok(false) => HttpResponse::NotFound().json(ApiKeyResponse::error(format!(
+            "Clé {} introuvable ou déjà révoquée",
+            path.as_str()
+        ))),
+        Err(e) => storage_error_response(e),
+    }
+}
+
+fn storage_error_response(e: StorageError) -> HttpResponse {
+    error!(error = %e, "Erreur base de données");
+    HttpResponse::InternalServerError().json(ApiKeyResponse::error("Erreur base de données"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::handlers::api_routes;
+    use crate::storage::{AuditRepository, Database};
+    use actix_web::{test, App};
+    use std::sync::Arc;
+
+    #[actix_web::test]
+    async fn test_issued_key_is_scoped_then_revoked() {
+        let database = Database::open_in_memory().unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(Arc::new(AppConfig::for_tests())))
+                .app_data(web::Data::new(ApiKeyRepository::new(database.clone())))
+                .app_data(web::Data::new(AuditRepository::new(database)))
+                .configure(api_routes),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/admin/api-keys")
+            .insert_header(("X-API-Key", "secret"))
+            .set_json(serde_json::json!({
+                "label": "Tablette stand A",
+                "device_id": "tablet-1",
+                "scopes": ["export:send", "sync"]
+            }))
+            .to_request();
+        let issued: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let key = issued["key"].as_str().unwrap().to_string();
+        let id = issued["api_key"]["id"].as_str().unwrap().to_string();
+
+        // Clé valide mais sans le droit `admin`
+        let req = test::TestRequest::get()
+            .uri("/api/audit")
+            .insert_header(("X-API-Key", key.as_str()))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 403);
+
+        let req = test::TestRequest::delete()
+            .uri(&format!("/api/admin/api-keys/{}", id))
+            .insert_header(("X-API-Key", key.as_str()))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 403);
+
+        let req = test::TestRequest::delete()
+            .uri(&format!("/api/admin/api-keys/{}", id))
+            .insert_header(("X-API-Key", "secret"))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+        let req = test::TestRequest::get()
+            .uri("/api/audit")
+            .insert_header(("X-API-Key", key.as_str()))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 401);
+    }
+}
@@ -0,0 +1,76 @@
+//! Handler de consultation des compteurs d'audit.
+
+use actix_web::{web, HttpResponse};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use tracing::{error, instrument};
+
+use crate::storage::AuditRepository;
+
+#[derive(Serialize)]
+pub struct AuditResponse {
+    success: bool,
+    message: String,
+    counters: BTreeMap<String, i64>,
+}
+
+/// GET /api/audit
+///
+/// Compteurs cumulés, par exemple `photos_with_location` : nombre de photos
+/// reçues dont les coordonnées GPS ont été retirées.
+#[instrument(skip(audit))]
+pub async fn get_audit(audit: web::Data<AuditRepository>) -> HttpResponse {
+    match audit.counters().await {
+        Ok(counters) => HttpResponse::Ok().json(AuditResponse {
+            success: true,
+            message: "Compteurs d'audit".to_string(),
+            counters,
+        }),
+        Err(e) => {
+            error!(error = %e, "Erreur lecture des compteurs d'audit");
+            HttpResponse::InternalServerError().json(AuditResponse {
+                success: false,
+                message: "Erreur base de données".to_string(),
+                counters: BTreeMap::new(),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::handlers::api_routes;
+    use crate::photo::AUDIT_PHOTOS_WITH_LOCATION;
+    use crate::storage::Database;
+    use actix_web::{test, App};
+    use std::sync::Arc;
+
+    #[actix_web::test]
+    async fn test_audit_counters_require_api_key() {
+        let audit = AuditRepository::new(Database::open_in_memory().unwrap());
+        audit
+            .increment(vec![(AUDIT_PHOTOS_WITH_LOCATION, 2)])
+            .await
+            .unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(Arc::new(AppConfig::for_tests())))
+                .app_data(web::Data::new(audit))
+                .configure(api_routes),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/api/audit").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 401);
+
+        let req = test::TestRequest::get()
+            .uri("/api/audit")
+            .insert_header(("X-API-Key", "secret"))
+            .to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["counters"]["photos_with_location"], 2);
+    }
+}
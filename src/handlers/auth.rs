@@ -0,0 +1,161 @@
+//! Handlers de connexion des utilisateurs du back-office.
+//!
+//! Routes publiques : elles délivrent les jetons présentés ensuite aux
+//! routes `/api` dans `Authorization: Bearer`.
+
+use actix_web::{web, HttpResponse};
+use serde::Serialize;
+use std::sync::Arc;
+use tracing::{error, info, instrument, warn};
+use validator::Validate;
+
+use crate::config::AppConfig;
+use crate::domain::{LoginRequest, RefreshRequest, TokenPair, User};
+use crate::middleware::issue_access_token;
+use crate::storage::{StorageError, UserRepository};
+
+#[derive(Serialize)]
+pub struct TokenResponse {
+    success: bool,
+    message: String,
+    #[serde(flatten)]
+    tokens: Option<TokenPair>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user: Option<User>,
+}
+
+impl TokenResponse {
+    fn success(message: impl Into<String>, tokens: TokenPair, user: User) -> Self {
+        Self {
+            success: true,
+            message: message.into(),
+            tokens: Some(tokens),
+            user: Some(user),
+        }
+    }
+
+    fn logged_out() -> Self {
+        Self {
+            success: true,
+            message: "Déconnexion effectuée".to_string(),
+            tokens: None,
+            user: None,
+        }
+    }
+
+    fn error(message: impl Into<String>) -> Self {
+        Self {
+            success: false,
+            message: message.into(),
+            tokens: None,
+            user: None,
+        }
+    }
+}
+
+/// POST /auth/login
+#[instrument(skip(body, config, users), fields(username = %body.username))]
+pub async fn login(
+    body: web::Json<LoginRequest>,
+    config: web::Data<Arc<AppConfig>>,
+    users: web::Data<UserRepository>,
+) -> HttpResponse {
+    if let Err(errors) = body.validate() {
+        return HttpResponse::BadRequest().json(TokenResponse::error(format!(
+            "Validation échouée: {:?}",
+            errors
+        )));
+    }
+
+    let user = match users.verify_credentials(&body.username, &body.password).await {
+        Ok(Some(user)) => user,
+        Ok(None) => {
+            warn!("Échec de connexion");
+            return HttpResponse::Unauthorized()
+                .json(TokenResponse::error("Identifiants invalides"));
+        }
+        Err(e) => return server_error(e),
+    };
+
+    let refresh_token =
+        match users.issue_refresh_token(&user.id, config.security.refresh_token_ttl).await {
+            Ok(token) => token,
+            Err(e) => return server_error(e),
+        };
+    info!(user_id = %user.id, "Utilisateur connecté");
+    token_response("Connexion réussie", &config, user, refresh_token)
+}
+
+/// POST /auth/refresh
+///
+/// Échange le jeton de rafraîchissement contre une nouvelle paire de jetons ;
+/// l'ancien jeton ne peut plus servir.
+#[instrument(skip(body, config, users))]
+pub async fn refresh_token(
+    body: web::Json<RefreshRequest>,
+    config: web::Data<Arc<AppConfig>>,
+    users: web::Data<UserRepository>,
+) -> HttpResponse {
+    if let Err(errors) = body.validate() {
+        return HttpResponse::BadRequest().json(TokenResponse::error(format!(
+            "Validation échouée: {:?}",
+            errors
+        )));
+    }
+
+    match users
+        .rotate_refresh_token(&body.refresh_token, config.security.refresh_token_ttl)
+        .await
+    {
+        Ok(Some((user, refresh_token))) => {
+            token_response("Jetons renouvelés", &config, user, refresh_token)
+        }
+        Ok(None) => HttpResponse::Unauthorized()
+            .json(TokenResponse::error("Jeton de rafraîchissement invalide ou expiré")),
+        Err(e) => server_error(e),
+    }
+}
+
+/// POST /auth/logout
+///
+/// Révoque le jeton de rafraîchissement ; le jeton d'accès expire de lui-même.
+#[instrument(skip(body, users))]
+pub async fn logout(
+    body: web::Json<RefreshRequest>,
+    users: web::Data<UserRepository>,
+) -> HttpResponse {
+    match users.revoke_refresh_token(&body.refresh_token).await {
+        // Même réponse pour un jeton inconnu : la déconnexion est idempotente
+        Ok(_) => HttpResponse::Ok().json(TokenResponse::logged_out()),
+        Err(e) => server_error(e),
+    }
+}
+
+fn token_response(
+    message: &str,
+    config: &AppConfig,
+    user: User,
+    refresh_token: String,
+) -> HttpResponse {
+    match issue_access_token(&config.security, &user) {
+        Ok(access_token) => {
+            let tokens = TokenPair {
+                access_token,
+                token_type: "Bearer",
+                expires_in: config.security.access_token_ttl.as_secs(),
+                refresh_token,
+            };
+            HttpResponse::Ok().json(TokenResponse::success(message, tokens, user))
+        }
+        Err(e) => {
+            error!(error = %e, "Erreur de signature du jeton d'accès");
+            HttpResponse::InternalServerError()
+                .json(TokenResponse::error("Erreur de signature du jeton d'accès"))
+        }
+    }
+}
+
+fn server_error(e: StorageError) -> HttpResponse {
+    error!(error = %e, "Erreur base de données");
+    HttpResponse::InternalServerError().json(TokenResponse::error("Erreur base de données"))
+}
@@ -0,0 +1,455 @@
+//! Handlers CRUD pour les fiches contacts stockées côté serveur.
+
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, instrument};
+use validator::Validate;
+
+use super::photo_error_response;
+use crate::domain::{ContactFiche, StoredContact};
+use crate::middleware::Principal;
+use crate::photo::{PhotoProcessor, PhotoStorage};
+use crate::storage::{ContactFilter, ContactRepository, StorageError};
+
+/// Taille de page par défaut et maximale pour la liste
+pub(super) const DEFAULT_PAGE_SIZE: u32 = 50;
+pub(super) const MAX_PAGE_SIZE: u32 = 200;
+
+#[derive(Serialize)]
+pub struct ContactResponse {
+    success: bool,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    contact: Option<StoredContact>,
+}
+
+impl ContactResponse {
+    fn success(message: impl Into<String>, contact: Option<StoredContact>) -> Self {
+        Self {
+            success: true,
+            message: message.into(),
+            contact,
+        }
+    }
+
+    fn error(message: impl Into<String>) -> Self {
+        Self {
+            success: false,
+            message: message.into(),
+            contact: None,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ContactListResponse {
+    success: bool,
+    total: i64,
+    limit: u32,
+    offset: u32,
+    contacts: Vec<StoredContact>,
+}
+
+/// Paramètres de la liste des contacts
+#[derive(Debug, Deserialize)]
+pub struct ListContactsQuery {
+    /// Recherche sur société, contact ou email
+    q: Option<String>,
+    device_id: Option<String>,
+    limit: Option<u32>,
+    offset: Option<u32>,
+}
+
+/// Corps de la fusion de deux fiches
+#[derive(Debug, Deserialize)]
+pub struct MergeContactsRequest {
+    /// Fiche en double, absorbée puis supprimée
+    duplicate_id: String,
+}
+
+/// POST /api/contacts
+///
+/// La fiche créée avec une clé d'appareil est rattachée à cet appareil.
+#[instrument(skip(principal, body, contacts_repo, photos, storage))]
+pub async fn create_contact(
+    principal: Principal,
+    body: web::Json<ContactFiche>,
+    contacts_repo: web::Data<ContactRepository>,
+    photos: web::Data<PhotoProcessor>,
+    storage: web::Data<PhotoStorage>,
+) -> HttpResponse {
+    if let Err(errors) = body.validate() {
+        return HttpResponse::BadRequest().json(ContactResponse::error(format!(
+            "Validation échouée: {:?}",
+            errors
+        )));
+    }
+
+    let fiche = match store_photo(body.into_inner(), &photos, &storage).await {
+        Ok(fiche) => fiche,
+        Err(response) => return response,
+    };
+    let photo_key = fiche.photo_key.clone();
+
+    match contacts_repo.insert(fiche, principal.device_id).await {
+        Ok(contact) => {
+            info!(contact_id = %contact.id, "Fiche créée");
+            HttpResponse::Created().json(ContactResponse::success("Fiche créée", Some(contact)))
+        }
+        Err(e) => {
+            storage.discard(photo_key.into_iter().collect()).await;
+            storage_error_response(e)
+        }
+    }
+}
+
+/// GET /api/contacts
+///
+/// Une clé d'appareil ne liste que les fiches de son appareil.
+#[instrument(skip(principal, contacts_repo))]
+pub async fn list_contacts(
+    principal: Principal,
+    query: web::Query<ListContactsQuery>,
+    contacts_repo: web::Data<ContactRepository>,
+) -> HttpResponse {
+    let query = query.into_inner();
+    let device_id = match principal.resolve_device(query.device_id) {
+        Ok(device_id) => device_id,
+        Err(response) => return response,
+    };
+    let filter = ContactFilter {
+        search: query.q,
+        device_id,
+        limit: query
+            .limit
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE),
+        offset: query.offset.unwrap_or(0),
+    };
+    let (limit, offset) = (filter.limit, filter.offset);
+
+    match contacts_repo.list(filter).await {
+        Ok((contacts, total)) => HttpResponse::Ok().json(ContactListResponse {
+            success: true,
+            total,
+            limit,
+            offset,
+            contacts,
+        }),
+        Err(e) => storage_error_response(e),
+    }
+}
+
+/// GET /api/contacts/{id}
+#[instrument(skip(principal, contacts_repo))]
+pub async fn get_contact(
+    principal: Principal,
+    path: web::Path<String>,
+    contacts_repo: web::Data<ContactRepository>,
+) -> HttpResponse {
+    match owned_contact(&principal, &contacts_repo, &path).await {
+        Ok(contact) => {
+            HttpResponse::Ok().json(ContactResponse::success("Fiche trouvée", Some(contact)))
+        }
+        Err(response) => response,
+    }
+}
+
+/// PUT /api/contacts/{id}
+///
+/// Remplace les champs de la fiche ; la photo existante est conservée
+/// si aucune nouvelle photo n'est fournie.
+#[instrument(skip(principal, body, contacts_repo, photos, storage))]
+pub async fn update_contact(
+    principal: Principal,
+    path: web::Path<String>,
+    body: web::Json<ContactFiche>,
+    contacts_repo: web::Data<ContactRepository>,
+    photos: web::Data<PhotoProcessor>,
+    storage: web::Data<PhotoStorage>,
+) -> HttpResponse {
+    if let Err(errors) = body.validate() {
+        return HttpResponse::BadRequest().json(ContactResponse::error(format!(
+            "Validation échouée: {:?}",
+            errors
+        )));
+    }
+    if let Err(response) = owned_contact(&principal, &contacts_repo, &path).await {
+        return response;
+    }
+
+    let fiche = match store_photo(body.into_inner(), &photos, &storage).await {
+        Ok(fiche) => fiche,
+        Err(response) => return response,
+    };
+    let photo_key = fiche.photo_key.clone();
+
+    let result = contacts_repo.update(&path, fiche).await;
+    if !matches!(result, Ok(Some(_))) {
+        storage.discard(photo_key.into_iter().collect()).await;
+    }
+    match result {
+        Ok(Some(contact)) => {
+            info!(contact_id = %contact.id, "Fiche mise à jour");
+            HttpResponse::Ok().json(ContactResponse::success("Fiche mise à jour", Some(contact)))
+        }
+        Ok(None) => not_found(&path),
+        Err(e) => storage_error_response(e),
+    }
+}
+
+/// DELETE /api/contacts/{id}
+#[instrument(skip(principal, contacts_repo))]
+pub async fn delete_contact(
+    principal: Principal,
+    path: web::Path<String>,
+    contacts_repo: web::Data<ContactRepository>,
+) -> HttpResponse {
+    if let Err(response) = owned_contact(&principal, &contacts_repo, &path).await {
+        return response;
+    }
+    match contacts_repo.delete(&path).await {
+        Ok(true) => {
+            info!(contact_id = %path.as_str(), "Fiche supprimée");
+            HttpResponse::Ok().json(ContactResponse::success("Fiche supprimée", None))
+        }
+        Ok(false) => not_found(&path),
+        Err(e) => storage_error_response(e),
+    }
+}
+
+/// POST /api/contacts/{id}/merge
+///
+/// Fusionne une fiche en double dans la fiche `{id}` : les champs les plus
+/// complets et les photos des deux fiches sont conservés.
+#[instrument(skip(principal, body, contacts_repo))]
+pub async fn merge_contacts(
+    principal: Principal,
+    path: web::Path<String>,
+    body: web::Json<MergeContactsRequest>,
+    contacts_repo: web::Data<ContactRepository>,
+) -> HttpResponse {
+    for id in [path.as_str(), body.duplicate_id.as_str()] {
+        if let Err(response) = owned_contact(&principal, &contacts_repo, id).await {
+            return response;
+        }
+    }
+    match contacts_repo.merge(&path, &body.duplicate_id).await {
+        Ok(Some(contact)) => {
+            info!(
+                contact_id = %contact.id,
+                duplicate_id = %body.duplicate_id,
+                "Fiches fusionnées"
+            );
+            HttpResponse::Ok().json(ContactResponse::success("Fiches fusionnées", Some(contact)))
+        }
+        Ok(None) => HttpResponse::NotFound().json(ContactResponse::error(format!(
+            "Fiche {} ou {} introuvable",
+            path.as_str(),
+            body.duplicate_id
+        ))),
+        Err(e) => storage_error_response(e),
+    }
+}
+
+/// Traite la photo de la fiche et la dépose dans le stockage externe éventuel
+async fn store_photo(
+    fiche: ContactFiche,
+    photos: &PhotoProcessor,
+    storage: &PhotoStorage,
+) -> Result<ContactFiche, HttpResponse> {
+    let mut fiche = photos
+        .process_photos(fiche, |f| vec![f])
+        .await
+        .map_err(|e| photo_error_response(e, ContactResponse::error))?;
+
+    if let Err(e) = storage.upload([&mut fiche]).await {
+        error!(error = %e, "Erreur d'enregistrement de la photo");
+        return Err(
+            HttpResponse::InternalServerError().json(ContactResponse::error(
+                "Erreur d'enregistrement de la photo",
+            )),
+        );
+    }
+    Ok(fiche)
+}
+
+/// Fiche `id`, introuvable pour une clé d'un autre appareil que le sien
+async fn owned_contact(
+    principal: &Principal,
+    contacts_repo: &ContactRepository,
+    id: &str,
+) -> Result<StoredContact, HttpResponse> {
+    match contacts_repo.get(id).await {
+        Ok(Some(contact)) if principal.owns(contact.device_id.as_deref()) => Ok(contact),
+        Ok(_) => Err(not_found(id)),
+        Err(e) => Err(storage_error_response(e)),
+    }
+}
+
+fn not_found(id: &str) -> HttpResponse {
+    HttpResponse::NotFound().json(ContactResponse::error(format!("Fiche {} introuvable", id)))
+}
+
+/// Traduit une erreur de persistance en réponse HTTP
+fn storage_error_response(e: StorageError) -> HttpResponse {
+    match e {
+        StorageError::InvalidData(message) => HttpResponse::BadRequest().json(
+            ContactResponse::error(format!("Fiche invalide: {}", message)),
+        ),
+        e => {
+            error!(error = %e, "Erreur base de données");
+            HttpResponse::InternalServerError()
+                .json(ContactResponse::error("Erreur base de données"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::handlers::api_routes;
+    use crate::storage::{AuditRepository, Database};
+    use actix_web::{test, App};
+    use std::sync::Arc;
+
+    #[actix_web::test]
+    async fn test_create_then_correct_contact() {
+        let database = Database::open_in_memory().unwrap();
+        let config = AppConfig::for_tests();
+        let audit = AuditRepository::new(database.clone());
+        let photos = PhotoProcessor::new(config.photo.clone(), audit);
+        let storage = crate::photo::build_storage(&config.photo_store);
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(Arc::new(config)))
+                .app_data(web::Data::new(ContactRepository::new(database)))
+                .app_data(web::Data::new(photos))
+                .app_data(web::Data::new(storage))
+                .configure(api_routes),
+        )
+        .await;
+
+        let mut fiche = serde_json::json!({
+            "societe": "ACME",
+            "contact": "Jean",
+            "email": "jean@acme.fr",
+            "telephone": "0601020304",
+            "notes": "",
+            "sectors": "PHARMA",
+            "created_at": 1704067200000i64
+        });
+
+        let req = test::TestRequest::post()
+            .uri("/api/contacts")
+            .insert_header(("X-API-Key", "secret"))
+            .set_json(&fiche)
+            .to_request();
+        let created: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let id = created["contact"]["id"].as_str().unwrap().to_string();
+
+        // Un email invalide est refusé par les règles de ContactFiche
+        fiche["email"] = "pas-un-email".into();
+        let req = test::TestRequest::put()
+            .uri(&format!("/api/contacts/{}", id))
+            .insert_header(("X-API-Key", "secret"))
+            .set_json(&fiche)
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+
+        fiche["email"] = "jean.dupont@acme.fr".into();
+        let req = test::TestRequest::put()
+            .uri(&format!("/api/contacts/{}", id))
+            .insert_header(("X-API-Key", "secret"))
+            .set_json(&fiche)
+            .to_request();
+        let updated: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(updated["contact"]["email"], "jean.dupont@acme.fr");
+    }
+
+    #[actix_web::test]
+    async fn test_device_key_only_reaches_its_own_contacts() {
+        use crate::domain::{IssueApiKeyRequest, Scope};
+        use crate::storage::ApiKeyRepository;
+
+        let database = Database::open_in_memory().unwrap();
+        let config = AppConfig::for_tests();
+        let contacts = ContactRepository::new(database.clone());
+        let keys = ApiKeyRepository::new(database.clone());
+        let fiche: ContactFiche = serde_json::from_value(serde_json::json!({
+            "societe": "ACME",
+            "contact": "Jean",
+            "email": "jean@acme.fr",
+            "telephone": "0601020304",
+            "notes": "",
+            "sectors": "PHARMA",
+            "created_at": 1704067200000i64
+        }))
+        .unwrap();
+        let theirs = contacts
+            .insert(fiche.clone(), Some("tablet-1".into()))
+            .await
+            .unwrap();
+        let ours = contacts
+            .insert(fiche, Some("tablet-2".into()))
+            .await
+            .unwrap();
+        let (_, secrets) = keys
+            .issue(IssueApiKeyRequest {
+                label: "Tablette 2".to_string(),
+                device_id: Some("tablet-2".to_string()),
+                scopes: vec![Scope::ContactsRead, Scope::ContactsWrite],
+                expires_in_days: None,
+                recipients: None,
+            })
+            .await
+            .unwrap();
+
+        let audit = AuditRepository::new(database.clone());
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(PhotoProcessor::new(
+                    config.photo.clone(),
+                    audit,
+                )))
+                .app_data(web::Data::new(crate::photo::build_storage(
+                    &config.photo_store,
+                )))
+                .app_data(web::Data::new(Arc::new(config)))
+                .app_data(web::Data::new(contacts))
+                .app_data(web::Data::new(keys))
+                .configure(api_routes),
+        )
+        .await;
+        let call = |req: test::TestRequest| req.insert_header(("X-API-Key", secrets.key.as_str()));
+
+        // La liste est restreinte à l'appareil de la clé, quel que soit le filtre
+        let req = call(test::TestRequest::get().uri("/api/contacts")).to_request();
+        let list: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(list["total"], 1);
+        assert_eq!(list["contacts"][0]["id"], ours.id.as_str());
+        let req = call(test::TestRequest::get().uri("/api/contacts?device_id=tablet-1"));
+        assert_eq!(
+            test::call_service(&app, req.to_request()).await.status(),
+            403
+        );
+
+        // Les fiches d'un autre appareil sont introuvables
+        let uri = format!("/api/contacts/{}", theirs.id);
+        let req = call(test::TestRequest::get().uri(&uri)).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 404);
+        let req = call(test::TestRequest::delete().uri(&uri)).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 404);
+        let req = call(test::TestRequest::post().uri(&format!("/api/contacts/{}/merge", ours.id)))
+            .set_json(serde_json::json!({ "duplicate_id": theirs.id }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 404);
+
+        let req = test::TestRequest::get()
+            .uri(&uri)
+            .insert_header(("X-API-Key", "secret"))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 200);
+    }
+}
@@ -1,100 +1,865 @@
-//! Handler pour l'export des fiches contacts.
+//! Handlers pour l'export des fiches contacts.
 
+use actix_multipart::Multipart;
 use actix_web::{web, HttpRequest, HttpResponse};
 use std::sync::Arc;
-use tracing::{info, error, instrument};
+use tracing::{error, info, instrument, warn};
 use validator::Validate;
 
-use crate::config::AppConfig;
+use super::photo_error_response;
+use crate::config::{AppConfig, ExportConfig, RateRoute};
 use crate::domain::{
-    Email, EmailAttachment, ExportFichesRequest, ExportFichesResponse,
+    find_batch_duplicates, ContactFiche, DuplicateMatch, Email, EmailAttachment,
+    ExportFichesRequest, ExportFichesResponse, ExportPart, PhotoLinks,
 };
-use crate::email::{EmailProvider, EmailTemplates};
-use crate::middleware::verify_api_key;
+use crate::email::EmailTemplates;
+use crate::export::{self, ExportResult};
+use crate::middleware::{check_recipient, Principal};
+use crate::photo::{PhotoProcessor, PhotoStorage};
+use crate::storage::{ContactRepository, OutboxRepository, StorageError};
+use crate::upload::{self, UploadError};
 
 /// POST /api/export-fiches
-#[instrument(skip(req, body, config, email_provider), fields(contacts_count))]
+///
+/// Enregistre les fiches puis met l'email en file d'envoi : la réponse 202
+/// contient l'identifiant de l'envoi, effectué en arrière-plan.
+#[instrument(
+    skip(req, principal, body, config, contacts_repo, outbox, photos, storage),
+    fields(contacts_count)
+)]
+#[allow(clippy::too_many_arguments)] // un extracteur par dépendance
 pub async fn export_fiches(
     req: HttpRequest,
+    principal: Principal,
     body: web::Json<ExportFichesRequest>,
     config: web::Data<Arc<AppConfig>>,
-    email_provider: web::Data<Arc<dyn EmailProvider>>,
+    contacts_repo: web::Data<ContactRepository>,
+    outbox: web::Data<OutboxRepository>,
+    photos: web::Data<PhotoProcessor>,
+    storage: web::Data<PhotoStorage>,
 ) -> HttpResponse {
-    // 1. Vérifier l'authentification
-    if let Err(response) = verify_api_key(&req, &config) {
+    // 1. Valider la requête
+    let mut body = body.into_inner();
+    if let Err(response) = validate_request(&req, &principal, &config, &body) {
         return response;
     }
+    body.device_id = match principal.resolve_device(body.device_id.take()) {
+        Ok(device_id) => device_id,
+        Err(response) => return response,
+    };
+
+    // 2. Traiter les photos (format réel, orientation, taille)
+    body.contacts = match photos
+        .process_photos(body.contacts, |contacts| contacts.iter_mut().collect())
+        .await
+    {
+        Ok(contacts) => contacts,
+        Err(e) => return photo_error_response(e, ExportFichesResponse::error),
+    };
+
+    queue_export(body, &config, &contacts_repo, &outbox, &storage).await
+}
+
+/// POST /api/v2/export-fiches
+///
+/// Même traitement que `/api/export-fiches`, à partir d'un envoi
+/// `multipart/form-data` : fiches dans la partie JSON `metadata`, photos dans
+/// des parties binaires désignées par le `photo_id` des fiches.
+#[instrument(
+    skip(
+        req,
+        principal,
+        payload,
+        config,
+        contacts_repo,
+        outbox,
+        photos,
+        storage
+    ),
+    fields(contacts_count)
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn export_fiches_multipart(
+    req: HttpRequest,
+    principal: Principal,
+    payload: Multipart,
+    config: web::Data<Arc<AppConfig>>,
+    contacts_repo: web::Data<ContactRepository>,
+    outbox: web::Data<OutboxRepository>,
+    photos: web::Data<PhotoProcessor>,
+    storage: web::Data<PhotoStorage>,
+) -> HttpResponse {
+    // 1. Recevoir l'envoi, photos écrites dans des fichiers temporaires
+    let upload = match upload::read_export(payload, &config.upload).await {
+        Ok(upload) => upload,
+        Err(e) if e.is_too_large() => {
+            return HttpResponse::PayloadTooLarge()
+                .json(ExportFichesResponse::error(e.to_string()));
+        }
+        Err(e @ UploadError::Io(_)) => {
+            error!(error = %e, "Erreur de réception des photos");
+            return HttpResponse::InternalServerError().json(ExportFichesResponse::error(
+                "Erreur de réception des photos",
+            ));
+        }
+        Err(e) => {
+            return HttpResponse::BadRequest().json(ExportFichesResponse::error(e.to_string()));
+        }
+    };
 
     // 2. Valider la requête
+    let mut body = upload.request;
+    if let Err(response) = validate_request(&req, &principal, &config, &body) {
+        return response;
+    }
+    body.device_id = match principal.resolve_device(body.device_id.take()) {
+        Ok(device_id) => device_id,
+        Err(response) => return response,
+    };
+
+    // 3. Traiter les photos : éventuelles photos base64, puis photos reçues en fichiers
+    let processed = match photos
+        .process_photos(body.contacts, |contacts| contacts.iter_mut().collect())
+        .await
+    {
+        Ok(contacts) => photos.process_uploads(contacts, upload.photos).await,
+        Err(e) => Err(e),
+    };
+    body.contacts = match processed {
+        Ok(contacts) => contacts,
+        Err(e) => return photo_error_response(e, ExportFichesResponse::error),
+    };
+
+    queue_export(body, &config, &contacts_repo, &outbox, &storage).await
+}
+
+/// Vérifie la requête d'export avant tout traitement
+///
+/// Un destinataire choisi par l'appareil doit être autorisé et est soumis à
+/// la limite par domaine ; le destinataire par défaut ne l'est pas.
+fn validate_request(
+    req: &HttpRequest,
+    principal: &Principal,
+    config: &AppConfig,
+    body: &ExportFichesRequest,
+) -> Result<(), HttpResponse> {
     if let Err(errors) = body.validate() {
-        return HttpResponse::BadRequest().json(ExportFichesResponse::error(
-            format!("Validation échouée: {:?}", errors)
-        ));
+        return Err(
+            HttpResponse::BadRequest().json(ExportFichesResponse::error(format!(
+                "Validation échouée: {:?}",
+                errors
+            ))),
+        );
     }
 
-    let contacts = &body.contacts;
-    
-    if contacts.is_empty() {
-        return HttpResponse::BadRequest().json(ExportFichesResponse::error(
-            "Aucune fiche contact à exporter"
+    if body.contacts.is_empty() {
+        return Err(HttpResponse::BadRequest().json(ExportFichesResponse::error(
+            "Aucune fiche contact à exporter",
+        )));
+    }
+
+    if let Some(recipient) = &body.recipient_email {
+        principal
+            .authorize_recipient(&config.email.recipients, recipient)
+            .map_err(|e| {
+                HttpResponse::UnprocessableEntity()
+                    .json(ExportFichesResponse::error(e.to_string()).with_error(e.kind()))
+            })?;
+        check_recipient(req, RateRoute::Export, recipient)?;
+    }
+
+    tracing::Span::current().record("contacts_count", body.contacts.len());
+    Ok(())
+}
+
+/// Enregistre les fiches (photos déjà traitées) et met l'export en file d'envoi
+async fn queue_export(
+    mut body: ExportFichesRequest,
+    config: &AppConfig,
+    contacts_repo: &ContactRepository,
+    outbox: &OutboxRepository,
+    storage: &PhotoStorage,
+) -> HttpResponse {
+    // Photos déposées dans le stockage externe éventuel : la base n'en garde que la clé
+    if let Err(e) = storage.upload(&mut body.contacts).await {
+        error!(error = %e, "Erreur d'enregistrement des photos");
+        return HttpResponse::InternalServerError().json(ExportFichesResponse::error(
+            "Erreur d'enregistrement des photos",
         ));
     }
+    let contacts = &body.contacts;
+
+    // 3. Détecter les doublons probables (dans le lot et parmi les fiches déjà reçues)
+    let mut duplicates = find_batch_duplicates(contacts);
+    match contacts_repo.find_duplicates(contacts.clone()).await {
+        Ok(existing) => duplicates.extend(existing),
+        Err(e) => warn!(error = %e, "Recherche de doublons impossible"),
+    }
+    duplicates.sort_by_key(|d| d.index);
 
-    tracing::Span::current().record("contacts_count", contacts.len());
+    if !duplicates.is_empty() {
+        info!(duplicates = duplicates.len(), "Doublons probables détectés");
+    }
 
-    // 3. Déterminer le destinataire
+    // 4. Déterminer le destinataire
     let recipient = body
         .recipient_email
         .clone()
         .unwrap_or_else(|| config.email.default_recipient.clone());
 
-    // 4. Construire le sujet
-    let subject = body.subject.clone().unwrap_or_else(|| {
-        format!("📋 Export {} fiches contacts - SMP Moules", contacts.len())
-    });
+    // 5. Construire le sujet
+    let subject = body
+        .subject
+        .clone()
+        .unwrap_or_else(|| format!("📋 Export {} fiches contacts - SMP Moules", contacts.len()));
 
-    // 5. Générer le contenu HTML
-    let html_body = EmailTemplates::export_fiches_html(contacts);
+    let salon = body.salon.clone().or_else(|| config.export.salon.clone());
 
-    // 6. Construire les pièces jointes
-    let attachments: Vec<EmailAttachment> = contacts
-        .iter()
-        .filter_map(|c| {
-            c.photo_base64.as_ref().map(|base64| {
-                EmailAttachment::jpeg(c.safe_photo_filename(), base64.clone())
-            })
-        })
-        .collect();
+    // Liens temporaires vers les photos stockées, à la place des pièces jointes
+    let photo_links = match body.photo_links.unwrap_or(config.export.photo_links) {
+        true => storage.links(contacts),
+        false => None,
+    };
 
-    let attachment_count = attachments.len();
+    // 6. Construire les emails (contenu HTML et pièces jointes), en plusieurs
+    //    parties si la taille maximale d'un email est dépassée, avant d'enregistrer
+    //    quoi que ce soit : un export impossible à générer ne laisse pas de fiches
+    let emails = match export_emails(
+        &body,
+        &duplicates,
+        &recipient,
+        &subject,
+        salon.as_deref(),
+        photo_links.as_ref(),
+        &config.export,
+    ) {
+        Ok(emails) => emails,
+        Err(e) => {
+            error!(error = %e, "Erreur de génération des fichiers d'export");
+            let keys = contacts
+                .iter()
+                .filter_map(|c| c.photo_key.clone())
+                .collect();
+            storage.discard(keys).await;
+            return HttpResponse::InternalServerError()
+                .json(ExportFichesResponse::error(e.to_string()));
+        }
+    };
 
-    // 7. Construire l'email
-    let email = Email {
-        to: recipient.clone(),
-        subject,
-        html_body,
-        attachments,
+    // 7. Enregistrer les fiches (le serveur est le système de référence)
+    let stored = match contacts_repo
+        .insert_many(contacts.clone(), body.device_id.clone())
+        .await
+    {
+        Ok(stored) => stored,
+        Err(e) => {
+            let keys = contacts
+                .iter()
+                .filter_map(|c| c.photo_key.clone())
+                .collect();
+            storage.discard(keys).await;
+            if let StorageError::InvalidData(message) = e {
+                return HttpResponse::BadRequest().json(ExportFichesResponse::error(format!(
+                    "Fiche invalide: {}",
+                    message
+                )));
+            }
+            error!(error = %e, "Erreur enregistrement des fiches");
+            return HttpResponse::InternalServerError().json(ExportFichesResponse::error(
+                "Erreur d'enregistrement des fiches",
+            ));
+        }
     };
+    let contact_ids: Vec<String> = stored.into_iter().map(|c| c.id).collect();
 
-    // 8. Envoyer via le provider
-    match email_provider.send(&email).await {
-        Ok(email_id) => {
+    let budget = config.export.max_email_bytes;
+    let attachment_count: usize = emails.iter().map(|(_, e)| e.attachments.len()).sum();
+    for (part, email) in &emails {
+        let size = email.encoded_size();
+        if size > budget {
+            // Une fiche seule peut dépasser le budget : elle est envoyée quand même
+            warn!(part = %part.label(), size, budget, "Email d'export trop volumineux");
+        }
+    }
+    let emails: Vec<(Email, Vec<String>)> = emails
+        .into_iter()
+        .map(|(part, email)| (email, contact_ids[part.range].to_vec()))
+        .collect();
+
+    // 8. Mettre en file d'envoi (une entrée par partie)
+    match outbox
+        .enqueue_all(emails, body.device_id.clone(), salon)
+        .await
+    {
+        Ok(job_ids) => {
             info!(
-                email_id = %email_id,
+                job_ids = ?job_ids,
                 to = %recipient,
                 contacts = contacts.len(),
                 attachments = attachment_count,
-                "Export envoyé avec succès"
+                "Export mis en file d'envoi"
             );
 
-            HttpResponse::Ok().json(ExportFichesResponse::success(contacts.len(), &recipient))
+            HttpResponse::Accepted().json(
+                ExportFichesResponse::queued(contacts.len(), &recipient, job_ids)
+                    .with_contact_ids(contact_ids)
+                    .with_duplicates(duplicates),
+            )
         }
         Err(e) => {
-            error!(error = %e, "Erreur envoi export");
+            error!(error = %e, "Erreur de mise en file de l'export");
+
+            HttpResponse::InternalServerError().json(
+                ExportFichesResponse::error("Erreur de mise en file de l'export")
+                    .with_contact_ids(contact_ids)
+                    .with_duplicates(duplicates),
+            )
+        }
+    }
+}
+
+/// Emails de l'export, un par partie
+///
+/// Si l'email de toutes les fiches dépasse `max_email_bytes` une fois encodé,
+/// les fiches sont réparties en parties numérotées (« 1/3 », « 2/3 », ...),
+/// chacune avec son tableau, ses fichiers et ses photos. Avec `photo_links`,
+/// les photos ne sont pas jointes mais liées depuis le tableau.
+fn export_emails(
+    body: &ExportFichesRequest,
+    duplicates: &[DuplicateMatch],
+    recipient: &str,
+    subject: &str,
+    salon: Option<&str>,
+    photo_links: Option<&PhotoLinks>,
+    defaults: &ExportConfig,
+) -> ExportResult<Vec<(ExportPart, Email)>> {
+    let contacts = &body.contacts;
+    let build = |part: &ExportPart| -> ExportResult<Email> {
+        Ok(Email {
+            to: recipient.to_string(),
+            subject: match part.total {
+                1 => subject.to_string(),
+                _ => format!("{} ({})", subject, part.label()),
+            },
+            html_body: EmailTemplates::export_fiches_html(
+                contacts,
+                duplicates,
+                Some(part),
+                photo_links,
+            ),
+            attachments: export_attachments(
+                body,
+                &contacts[part.range.clone()],
+                salon,
+                photo_links.is_none(),
+                defaults,
+            )?,
+        })
+    };
 
-            HttpResponse::InternalServerError().json(ExportFichesResponse::error(
-                format!("Erreur d'envoi: {}", e)
+    // Une partie est mesurée avec le plus long libellé « x/y » qu'elle peut porter
+    let ranges = export::split_parts(contacts.len(), defaults.max_email_bytes, |range| {
+        let total = if range.len() == contacts.len() {
+            1
+        } else {
+            contacts.len()
+        };
+        Ok(build(&ExportPart {
+            number: total,
+            total,
+            range,
+        })?
+        .encoded_size())
+    })?;
+
+    let total = ranges.len();
+    ranges
+        .into_iter()
+        .enumerate()
+        .map(|(i, range)| {
+            let part = ExportPart {
+                number: i + 1,
+                total,
+                range,
+            };
+            let email = build(&part)?;
+            Ok((part, email))
+        })
+        .collect()
+}
+
+/// Photos des cartes de visite (si `with_photos`) et fichiers d'export des
+/// fiches `contacts`, demandés par la requête ou activés par défaut
+fn export_attachments(
+    body: &ExportFichesRequest,
+    contacts: &[ContactFiche],
+    salon: Option<&str>,
+    with_photos: bool,
+    defaults: &ExportConfig,
+) -> ExportResult<Vec<EmailAttachment>> {
+    let mut attachments = Vec::new();
+    let separator = body.csv_separator.unwrap_or(defaults.csv_separator);
+
+    if with_photos && body.zip_photos.unwrap_or(defaults.zip_photos) {
+        if let Some(zip) = export::photos_zip(contacts, separator)? {
+            attachments.push(EmailAttachment::from_bytes(
+                export::export_filename("zip"),
+                "application/zip",
+                &zip,
+            ));
+        }
+    } else if with_photos {
+        let photos = contacts.iter().zip(export::photo_filenames(contacts));
+        attachments.extend(photos.filter_map(|(contact, filename)| {
+            Some(EmailAttachment::jpeg(
+                filename?,
+                contact.photo_base64.clone()?,
             ))
+        }));
+    }
+
+    if body.include_csv.unwrap_or(defaults.include_csv) {
+        attachments.push(EmailAttachment::from_bytes(
+            export::export_filename("csv"),
+            "text/csv; charset=utf-8",
+            &export::fiches_csv(contacts, separator),
+        ));
+    }
+
+    if body.include_xlsx.unwrap_or(defaults.include_xlsx) {
+        attachments.push(EmailAttachment::from_bytes(
+            export::export_filename("xlsx"),
+            "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            &export::fiches_xlsx(contacts)?,
+        ));
+    }
+
+    if body.include_vcard.unwrap_or(defaults.include_vcard) {
+        const VCARD: &str = "text/vcard; charset=utf-8";
+        if body.vcard_per_contact.unwrap_or(defaults.vcard_per_contact) {
+            attachments.extend(
+                export::fiche_vcards(contacts)
+                    .into_iter()
+                    .map(|(filename, vcard)| EmailAttachment::from_bytes(filename, VCARD, &vcard)),
+            );
+        } else {
+            attachments.push(EmailAttachment::from_bytes(
+                export::export_filename("vcf"),
+                VCARD,
+                &export::fiches_vcard(contacts),
+            ));
+        }
+    }
+
+    if body.include_pdf.unwrap_or(defaults.include_pdf) {
+        attachments.push(EmailAttachment::from_bytes(
+            export::export_filename("pdf"),
+            "application/pdf",
+            &export::fiches_pdf(salon, contacts),
+        ));
+    }
+
+    Ok(attachments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::api_routes;
+    use crate::storage::{AuditRepository, Database};
+    use actix_web::{test, App};
+
+    #[actix_web::test]
+    async fn test_export_persists_fiches_and_queues_email() {
+        let database = Database::open_in_memory().unwrap();
+        let outbox = OutboxRepository::new(database.clone());
+        let config = AppConfig::for_tests();
+        let audit = AuditRepository::new(database.clone());
+        let photos = PhotoProcessor::new(config.photo.clone(), audit);
+        let storage = crate::photo::build_storage(&config.photo_store);
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(Arc::new(config)))
+                .app_data(web::Data::new(ContactRepository::new(database)))
+                .app_data(web::Data::new(outbox.clone()))
+                .app_data(web::Data::new(photos))
+                .app_data(web::Data::new(storage))
+                .configure(api_routes),
+        )
+        .await;
+
+        // Photo PNG, recompressée en JPEG par le serveur
+        let mut png = Vec::new();
+        image::RgbImage::new(8, 8)
+            .write_with_encoder(image::codecs::png::PngEncoder::new(&mut png))
+            .unwrap();
+        let photo = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, png);
+
+        let req = test::TestRequest::post()
+            .uri("/api/export-fiches")
+            .insert_header(("X-API-Key", "secret"))
+            .set_json(serde_json::json!({
+                "contacts": [{
+                    "societe": "ACME",
+                    "contact": "Jean",
+                    "email": "jean@acme.fr",
+                    "telephone": "0601020304",
+                    "notes": "",
+                    "sectors": "PHARMA",
+                    "created_at": 1704067200000i64,
+                    "photo_base64": photo
+                }],
+                "device_id": "tablet-1",
+                "include_csv": true,
+                "csv_separator": "semicolon",
+                "include_xlsx": true,
+                "include_pdf": true,
+                "salon": "Pharmapack Paris"
+            }))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 202);
+
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["success"], true);
+        assert_eq!(body["contact_ids"].as_array().unwrap().len(), 1);
+
+        let job_id = body["job_id"].as_str().unwrap();
+        let job = outbox.get(job_id).await.unwrap().unwrap();
+        assert_eq!(job.state, crate::domain::ExportJobState::Queued);
+        assert_eq!(job.device_id.as_deref(), Some("tablet-1"));
+        assert_eq!(job.salon.as_deref(), Some("Pharmapack Paris"));
+
+        // Les photos (archive ZIP par défaut) et les fichiers demandés sont joints
+        let queued = outbox.claim_due(1).await.unwrap();
+        let attachments = &queued[0].email.attachments;
+        assert_eq!(attachments.len(), 4);
+        assert_eq!(attachments[0].content_type, "application/zip");
+        assert!(attachments[1].filename.ends_with(".csv"));
+        assert_eq!(attachments[1].content_type, "text/csv; charset=utf-8");
+        assert!(attachments[2].filename.ends_with(".xlsx"));
+        assert_eq!(attachments[3].content_type, "application/pdf");
+    }
+
+    #[actix_web::test]
+    async fn test_refused_recipient_is_tagged_and_stores_nothing() {
+        let database = Database::open_in_memory().unwrap();
+        let outbox = OutboxRepository::new(database.clone());
+        let contacts = ContactRepository::new(database.clone());
+        let config = AppConfig::for_tests();
+        let photos = PhotoProcessor::new(config.photo.clone(), AuditRepository::new(database));
+        let storage = crate::photo::build_storage(&config.photo_store);
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(Arc::new(config)))
+                .app_data(web::Data::new(contacts))
+                .app_data(web::Data::new(outbox.clone()))
+                .app_data(web::Data::new(photos))
+                .app_data(web::Data::new(storage))
+                .configure(api_routes),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/export-fiches")
+            .insert_header(("X-API-Key", "secret"))
+            .set_json(serde_json::json!({
+                "contacts": [{
+                    "societe": "ACME", "contact": "Jean", "email": "jean@acme.fr",
+                    "telephone": "", "notes": "", "sectors": "",
+                    "created_at": 1704067200000i64
+                }],
+                "recipient_email": "x@gmail.com"
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 422);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["success"], false);
+        assert_eq!(body["error"], "invalid_recipient");
+        assert!(outbox.claim_due(1).await.unwrap().is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_multipart_export_with_binary_photo() {
+        let database = Database::open_in_memory().unwrap();
+        let outbox = OutboxRepository::new(database.clone());
+        let config = AppConfig::for_tests();
+        let audit = AuditRepository::new(database.clone());
+        let photos = PhotoProcessor::new(config.photo.clone(), audit);
+        let storage = crate::photo::build_storage(&config.photo_store);
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(Arc::new(config)))
+                .app_data(web::Data::new(ContactRepository::new(database)))
+                .app_data(web::Data::new(outbox.clone()))
+                .app_data(web::Data::new(photos))
+                .app_data(web::Data::new(storage))
+                .configure(api_routes),
+        )
+        .await;
+
+        let mut png = Vec::new();
+        image::RgbImage::new(8, 8)
+            .write_with_encoder(image::codecs::png::PngEncoder::new(&mut png))
+            .unwrap();
+        let metadata = serde_json::json!({
+            "contacts": [{
+                "societe": "ACME",
+                "contact": "Jean",
+                "email": "jean@acme.fr",
+                "telephone": "",
+                "notes": "",
+                "sectors": "",
+                "created_at": 0,
+                "photo_id": "carte-1"
+            }],
+            "zip_photos": false
+        });
+
+        let mut body = Vec::new();
+        body.extend_from_slice(b"--fiches\r\n");
+        body.extend_from_slice(b"Content-Disposition: form-data; name=\"metadata\"\r\n");
+        body.extend_from_slice(b"Content-Type: application/json\r\n\r\n");
+        body.extend_from_slice(metadata.to_string().as_bytes());
+        body.extend_from_slice(b"\r\n--fiches\r\nContent-Disposition: form-data; ");
+        body.extend_from_slice(b"name=\"carte-1\"; filename=\"carte.png\"\r\n");
+        body.extend_from_slice(b"Content-Type: image/png\r\n\r\n");
+        body.extend_from_slice(&png);
+        body.extend_from_slice(b"\r\n--fiches--\r\n");
+
+        let req = test::TestRequest::post()
+            .uri("/api/v2/export-fiches")
+            .insert_header(("X-API-Key", "secret"))
+            .insert_header(("Content-Type", "multipart/form-data; boundary=fiches"))
+            .set_payload(body)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 202);
+
+        // La photo binaire est recompressée en JPEG et jointe, nommée d'après la fiche
+        let queued = outbox.claim_due(1).await.unwrap();
+        let attachments = &queued[0].email.attachments;
+        assert_eq!(attachments.len(), 1);
+        assert_eq!(attachments[0].content_type, "image/jpeg");
+        assert_eq!(attachments[0].filename, "ACME_Jean.jpg");
+    }
+
+    #[actix_web::test]
+    async fn test_large_export_is_split_into_numbered_parts() {
+        let photo =
+            base64::Engine::encode(&base64::engine::general_purpose::STANDARD, [7u8; 20_000]);
+        let contacts: Vec<serde_json::Value> = ["ACME", "Globex", "Initech"]
+            .iter()
+            .map(|societe| {
+                serde_json::json!({
+                    "societe": societe,
+                    "contact": "Jean",
+                    "email": "jean@acme.fr",
+                    "telephone": "",
+                    "notes": "",
+                    "sectors": "",
+                    "created_at": 0,
+                    "photo_base64": photo
+                })
+            })
+            .collect();
+        let body: ExportFichesRequest = serde_json::from_value(serde_json::json!({
+            "contacts": contacts,
+            "include_csv": true
+        }))
+        .unwrap();
+
+        let mut defaults = ExportConfig::default();
+        let whole = export_emails(
+            &body,
+            &[],
+            "a@smp-moules.com",
+            "Export",
+            None,
+            None,
+            &defaults,
+        )
+        .unwrap();
+        assert_eq!(whole.len(), 1);
+        assert_eq!(whole[0].1.subject, "Export");
+
+        defaults.max_email_bytes = whole[0].1.encoded_size() / 2;
+        let parts = export_emails(
+            &body,
+            &[],
+            "a@smp-moules.com",
+            "Export",
+            None,
+            None,
+            &defaults,
+        )
+        .unwrap();
+        assert!(parts.len() > 1);
+        assert_eq!(parts[0].1.subject, format!("Export (1/{})", parts.len()));
+        assert_eq!(parts.iter().map(|(p, _)| p.range.len()).sum::<usize>(), 3);
+        for (_, email) in &parts {
+            assert!(email.encoded_size() <= defaults.max_email_bytes);
+            // Chaque partie a son archive de photos et son CSV
+            assert_eq!(email.attachments.len(), 2);
+        }
+
+        // Photos en lien : un seul email, sans photos jointes
+        let links = PhotoLinks {
+            urls: vec![Some("https://cdn.example.com/photo.jpg".to_string()); 3],
+            expires_at: chrono::Utc::now(),
+        };
+        let linked = export_emails(
+            &body,
+            &[],
+            "a@smp-moules.com",
+            "Export",
+            None,
+            Some(&links),
+            &defaults,
+        )
+        .unwrap();
+        assert_eq!(linked.len(), 1);
+        assert_eq!(linked[0].1.attachments.len(), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_parts_fit_a_budget_close_to_their_size() {
+        let contacts: Vec<serde_json::Value> = ["ACME", "Globex", "Initech", "Umbrella"]
+            .iter()
+            .map(|societe| {
+                serde_json::json!({
+                    "societe": societe, "contact": "Jean", "email": "jean@acme.fr",
+                    "telephone": "", "notes": "", "sectors": "", "created_at": 0
+                })
+            })
+            .collect();
+        let body: ExportFichesRequest = serde_json::from_value(serde_json::json!({
+            "contacts": contacts,
+            "include_csv": true
+        }))
+        .unwrap();
+        let mut defaults = ExportConfig::default();
+        let whole = export_emails(
+            &body,
+            &[],
+            "a@smp-moules.com",
+            "Export",
+            None,
+            None,
+            &defaults,
+        )
+        .unwrap();
+        let whole = whole[0].1.encoded_size();
+        defaults.max_email_bytes = 0;
+        let single = export_emails(
+            &body,
+            &[],
+            "a@smp-moules.com",
+            "Export",
+            None,
+            None,
+            &defaults,
+        )
+        .unwrap()
+        .iter()
+        .map(|(_, email)| email.encoded_size())
+        .max()
+        .unwrap();
+
+        // Le libellé « Partie x/y » des emails envoyés est compté dans le budget
+        for budget in (single..whole).step_by(3) {
+            defaults.max_email_bytes = budget;
+            let parts = export_emails(
+                &body,
+                &[],
+                "a@smp-moules.com",
+                "Export",
+                None,
+                None,
+                &defaults,
+            )
+            .unwrap();
+            assert!(parts.len() > 1);
+            for (part, email) in &parts {
+                assert!(
+                    email.encoded_size() <= budget,
+                    "partie {} > {}",
+                    part.label(),
+                    budget
+                );
+            }
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_split_parts_attach_their_own_photos() {
+        use base64::Engine;
+        let photos: Vec<String> = (1..=3u8)
+            .map(|n| base64::engine::general_purpose::STANDARD.encode([n; 20_000]))
+            .collect();
+        let contacts: Vec<serde_json::Value> = ["ACME", "Globex", "Initech"]
+            .iter()
+            .zip(&photos)
+            .map(|(societe, photo)| {
+                serde_json::json!({
+                    "societe": societe,
+                    "contact": "Jean",
+                    "email": "jean@acme.fr",
+                    "telephone": "",
+                    "notes": "",
+                    "sectors": "",
+                    "created_at": 0,
+                    "photo_base64": photo
+                })
+            })
+            .collect();
+        let body: ExportFichesRequest = serde_json::from_value(serde_json::json!({
+            "contacts": contacts,
+            "zip_photos": false
+        }))
+        .unwrap();
+
+        let mut defaults = ExportConfig::default();
+        let whole = export_emails(
+            &body,
+            &[],
+            "a@smp-moules.com",
+            "Export",
+            None,
+            None,
+            &defaults,
+        )
+        .unwrap();
+        defaults.max_email_bytes = whole[0].1.encoded_size() / 2;
+        let parts = export_emails(
+            &body,
+            &[],
+            "a@smp-moules.com",
+            "Export",
+            None,
+            None,
+            &defaults,
+        )
+        .unwrap();
+        assert!(parts.len() > 1);
+
+        // Chaque partie joint les photos de ses propres fiches, dans l'ordre
+        for (part, email) in &parts {
+            let attached: Vec<&str> = email
+                .attachments
+                .iter()
+                .filter(|a| a.content_type == "image/jpeg")
+                .map(|a| a.content_base64.as_str())
+                .collect();
+            let expected: Vec<&str> = photos[part.range.clone()]
+                .iter()
+                .map(String::as_str)
+                .collect();
+            assert_eq!(attached, expected);
         }
     }
 }
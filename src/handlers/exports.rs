@@ -0,0 +1,288 @@
+//! Handlers de suivi des envois d'export.
+//!
+//! Les exports étant envoyés en arrière-plan, l'application interroge ces
+//! endpoints pour connaître le sort de chaque envoi et mettre à jour le
+//! statut de ses fiches. Le rapport PDF d'un envoi peut aussi être téléchargé.
+
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{error, instrument};
+
+use super::contacts::{DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE};
+use crate::config::AppConfig;
+use crate::domain::ExportJob;
+use crate::export;
+use crate::middleware::Principal;
+use crate::photo::PhotoStorage;
+use crate::storage::{ContactRepository, ExportJobFilter, OutboxRepository};
+
+#[derive(Serialize)]
+pub struct ExportJobResponse {
+    success: bool,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    job: Option<ExportJob>,
+}
+
+impl ExportJobResponse {
+    fn error(message: impl Into<String>) -> Self {
+        Self {
+            success: false,
+            message: message.into(),
+            job: None,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ExportJobListResponse {
+    success: bool,
+    total: i64,
+    limit: u32,
+    offset: u32,
+    jobs: Vec<ExportJob>,
+}
+
+/// Paramètres de la liste des envois
+#[derive(Debug, Deserialize)]
+pub struct ListExportsQuery {
+    device_id: Option<String>,
+    /// Envois créés à partir de cette date (ms)
+    since: Option<i64>,
+    /// Envois créés avant cette date (ms)
+    until: Option<i64>,
+    limit: Option<u32>,
+    offset: Option<u32>,
+}
+
+/// GET /api/exports/{id}
+#[instrument(skip(principal, outbox))]
+pub async fn get_export(
+    principal: Principal,
+    path: web::Path<String>,
+    outbox: web::Data<OutboxRepository>,
+) -> HttpResponse {
+    match owned_job(&principal, &outbox, &path).await {
+        Ok(job) => HttpResponse::Ok().json(ExportJobResponse {
+            success: true,
+            message: "Envoi trouvé".to_string(),
+            job: Some(job),
+        }),
+        Err(response) => response,
+    }
+}
+
+/// GET /api/exports
+///
+/// Une clé d'appareil ne liste que les envois de son appareil.
+#[instrument(skip(principal, outbox))]
+pub async fn list_exports(
+    principal: Principal,
+    query: web::Query<ListExportsQuery>,
+    outbox: web::Data<OutboxRepository>,
+) -> HttpResponse {
+    let query = query.into_inner();
+    let device_id = match principal.resolve_device(query.device_id) {
+        Ok(device_id) => device_id,
+        Err(response) => return response,
+    };
+    let filter = ExportJobFilter {
+        device_id,
+        since: query.since,
+        until: query.until,
+        limit: query
+            .limit
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE),
+        offset: query.offset.unwrap_or(0),
+    };
+    let (limit, offset) = (filter.limit, filter.offset);
+
+    match outbox.list(filter).await {
+        Ok((jobs, total)) => HttpResponse::Ok().json(ExportJobListResponse {
+            success: true,
+            total,
+            limit,
+            offset,
+            jobs,
+        }),
+        Err(e) => {
+            error!(error = %e, "Erreur lecture des envois");
+            HttpResponse::InternalServerError()
+                .json(ExportJobResponse::error("Erreur base de données"))
+        }
+    }
+}
+
+/// GET /api/exports/{id}/report.pdf
+///
+/// Régénère le rapport PDF à partir des fiches enregistrées de l'envoi.
+#[instrument(skip(principal, config, outbox, contacts_repo, storage))]
+pub async fn export_report(
+    principal: Principal,
+    path: web::Path<String>,
+    config: web::Data<Arc<AppConfig>>,
+    outbox: web::Data<OutboxRepository>,
+    contacts_repo: web::Data<ContactRepository>,
+    storage: web::Data<PhotoStorage>,
+) -> HttpResponse {
+    let job = match owned_job(&principal, &outbox, &path).await {
+        Ok(job) => job,
+        Err(response) => return response,
+    };
+
+    let mut fiches = match contacts_repo.fiches_with_photo(job.contact_ids).await {
+        Ok(fiches) => fiches,
+        Err(e) => {
+            error!(error = %e, "Erreur lecture des fiches de l'envoi");
+            return HttpResponse::InternalServerError()
+                .json(ExportJobResponse::error("Erreur base de données"));
+        }
+    };
+    if let Err(e) = storage.load(&mut fiches).await {
+        error!(error = %e, "Erreur lecture des photos de l'envoi");
+        return HttpResponse::InternalServerError()
+            .json(ExportJobResponse::error("Erreur lecture des photos"));
+    }
+
+    let salon = job.salon.or_else(|| config.export.salon.clone());
+    HttpResponse::Ok()
+        .content_type("application/pdf")
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(export::export_filename("pdf"))],
+        })
+        .body(export::fiches_pdf(salon.as_deref(), &fiches))
+}
+
+/// Envoi `id`, introuvable pour une clé d'un autre appareil que le sien
+async fn owned_job(
+    principal: &Principal,
+    outbox: &OutboxRepository,
+    id: &str,
+) -> Result<ExportJob, HttpResponse> {
+    match outbox.get(id).await {
+        Ok(Some(job)) if principal.owns(job.device_id.as_deref()) => Ok(job),
+        Ok(_) => Err(
+            HttpResponse::NotFound().json(ExportJobResponse::error(format!(
+                "Envoi {} introuvable",
+                id
+            ))),
+        ),
+        Err(e) => {
+            error!(error = %e, "Erreur lecture de l'envoi");
+            Err(HttpResponse::InternalServerError()
+                .json(ExportJobResponse::error("Erreur base de données")))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Email, IssueApiKeyRequest, Scope};
+    use crate::handlers::api_routes;
+    use crate::storage::{ApiKeyRepository, Database};
+    use actix_web::{test, App};
+
+    #[actix_web::test]
+    async fn test_export_status_and_list() {
+        let database = Database::open_in_memory().unwrap();
+        let outbox = OutboxRepository::new(database.clone());
+        let keys = ApiKeyRepository::new(database.clone());
+        let email = Email {
+            to: "commercial@smp-moules.com".to_string(),
+            subject: "Export".to_string(),
+            html_body: String::new(),
+            attachments: vec![],
+        };
+        let id = outbox
+            .enqueue(
+                email,
+                Some("tablet-1".into()),
+                vec!["c1".into()],
+                Some("Pharmapack".into()),
+            )
+            .await
+            .unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(Arc::new(AppConfig::for_tests())))
+                .app_data(web::Data::new(outbox))
+                .app_data(web::Data::new(keys.clone()))
+                .app_data(web::Data::new(ContactRepository::new(database)))
+                .app_data(web::Data::new(crate::photo::build_storage(
+                    &AppConfig::for_tests().photo_store,
+                )))
+                .configure(api_routes),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/exports/{}", id))
+            .insert_header(("X-API-Key", "secret"))
+            .to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["job"]["state"], "queued");
+        assert_eq!(resp["job"]["attempts"], 0);
+        assert_eq!(resp["job"]["contact_ids"][0], "c1");
+        assert_eq!(resp["job"]["salon"], "Pharmapack");
+
+        // Le rapport est régénéré même si les fiches ont disparu depuis
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/exports/{}/report.pdf", id))
+            .insert_header(("X-API-Key", "secret"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(
+            resp.headers().get("content-type").unwrap(),
+            "application/pdf"
+        );
+        assert!(test::read_body(resp).await.starts_with(b"%PDF-"));
+
+        let req = test::TestRequest::get()
+            .uri("/api/exports?device_id=tablet-2")
+            .insert_header(("X-API-Key", "secret"))
+            .to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["total"], 0);
+
+        let req = test::TestRequest::get()
+            .uri("/api/exports/inconnu")
+            .insert_header(("X-API-Key", "secret"))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 404);
+
+        // Une clé de tablet-2 ne voit pas les envois de tablet-1
+        let (_, secrets) = keys
+            .issue(IssueApiKeyRequest {
+                label: "Tablette 2".to_string(),
+                device_id: Some("tablet-2".to_string()),
+                scopes: vec![Scope::ExportSend],
+                expires_in_days: None,
+                recipients: None,
+            })
+            .await
+            .unwrap();
+        for uri in [
+            format!("/api/exports/{}", id),
+            format!("/api/exports/{}/report.pdf", id),
+        ] {
+            let req = test::TestRequest::get()
+                .uri(&uri)
+                .insert_header(("X-API-Key", secrets.key.as_str()))
+                .to_request();
+            assert_eq!(test::call_service(&app, req).await.status(), 404);
+        }
+        let req = test::TestRequest::get()
+            .uri("/api/exports")
+            .insert_header(("X-API-Key", secrets.key.as_str()))
+            .to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["total"], 0);
+    }
+}
@@ -6,24 +6,32 @@ use std::sync::Arc;
 use tracing::{info, error, instrument};
 use validator::Validate;
 
-use crate::config::AppConfig;
+use crate::config::{AppConfig, RateRoute};
 use crate::domain::{Email, HistoryEmailRequest};
 use crate::email::{EmailProvider, EmailTemplates};
-use crate::middleware::verify_api_key;
+use crate::middleware::{check_recipient, Principal};
 
 #[derive(Serialize)]
 pub struct HistoryEmailResponse {
     success: bool,
     message: String,
     contacts_sent: usize,
+    /// Provider email ayant effectivement délivré l'historique
+    #[serde(skip_serializing_if = "Option::is_none")]
+    provider: Option<String>,
+    /// Code de l'erreur, pour les refus que l'application traite elle-même
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'static str>,
 }
 
 impl HistoryEmailResponse {
-    fn success(count: usize) -> Self {
+    fn success(count: usize, provider: &str) -> Self {
         Self {
             success: true,
             message: format!("Historique de {} contact(s) envoyé avec succès", count),
             contacts_sent: count,
+            provider: Some(provider.to_string()),
+            error: None,
         }
     }
 
@@ -32,26 +40,32 @@ impl HistoryEmailResponse {
             success: false,
             message: message.into(),
             contacts_sent: 0,
+            provider: None,
+            error: None,
         }
     }
+
+    fn with_error(mut self, code: &'static str) -> Self {
+        self.error = Some(code);
+        self
+    }
 }
 
 /// POST /api/send-history-email
 ///
 /// Envoie l'historique des contacts par email.
-#[instrument(skip(req, body, config, email_provider), fields(contacts_count, recipient))]
+#[instrument(
+    skip(req, principal, body, config, email_provider),
+    fields(contacts_count, recipient)
+)]
 pub async fn send_history_email(
     req: HttpRequest,
+    principal: Principal,
     body: web::Json<HistoryEmailRequest>,
     config: web::Data<Arc<AppConfig>>,
     email_provider: web::Data<Arc<dyn EmailProvider>>,
 ) -> HttpResponse {
-    // 1. Vérifier l'authentification
-    if let Err(response) = verify_api_key(&req, &config) {
-        return response;
-    }
-
-    // 2. Valider la requête
+    // 1. Valider la requête
     if let Err(errors) = body.validate() {
         return HttpResponse::BadRequest().json(HistoryEmailResponse::error(
             format!("Validation échouée: {:?}", errors)
@@ -70,6 +84,16 @@ pub async fn send_history_email(
     tracing::Span::current().record("contacts_count", contacts.len());
     tracing::Span::current().record("recipient", recipient.as_str());
 
+    // 2. Vérifier que le destinataire est autorisé, puis limiter les envois
+    //    vers un même domaine
+    if let Err(e) = principal.authorize_recipient(&config.email.recipients, recipient) {
+        return HttpResponse::UnprocessableEntity()
+            .json(HistoryEmailResponse::error(e.to_string()).with_error(e.kind()));
+    }
+    if let Err(response) = check_recipient(&req, RateRoute::History, recipient) {
+        return response;
+    }
+
     // 3. Construire le sujet
     let subject = format!(
         "📊 Historique {} contacts - SMP Moules ({})",
@@ -89,15 +113,16 @@ pub async fn send_history_email(
     };
 
     match email_provider.send(&email).await {
-        Ok(email_id) => {
+        Ok(delivery) => {
             info!(
-                email_id = %email_id,
+                email_id = %delivery.message_id,
+                provider = delivery.provider,
                 to = %recipient,
                 contacts = contacts.len(),
                 "Historique envoyé avec succès"
             );
 
-            HttpResponse::Ok().json(HistoryEmailResponse::success(contacts.len()))
+            HttpResponse::Ok().json(HistoryEmailResponse::success(contacts.len(), delivery.provider))
         }
         Err(e) => {
             error!(error = %e, to = %recipient, "Erreur envoi historique");
@@ -108,3 +133,120 @@ pub async fn send_history_email(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Quota, RouteLimits};
+    use crate::domain::RecipientPolicy;
+    use crate::email::mock::MockEmailProvider;
+    use crate::handlers::api_routes;
+    use crate::middleware::RateLimiter;
+    use crate::storage::{ApiKeyRepository, Database};
+    use actix_web::{test, App};
+    use std::time::Duration;
+
+    fn history(recipient: &str) -> serde_json::Value {
+        serde_json::json!({
+            "recipient_email": recipient,
+            "contacts": [{
+                "societe": "Acme", "contact": "Jean", "email": "jean@acme.fr",
+                "telephone": "", "notes": "", "sectors": "", "status": "",
+                "created_at": "2025-01-01"
+            }],
+            "total_contacts": 1,
+            "export_date": "01/01/2025"
+        })
+    }
+
+    #[actix_web::test]
+    async fn test_recipient_must_match_global_or_key_policy() {
+        let keys = ApiKeyRepository::new(Database::open_in_memory().unwrap());
+        let provider = Arc::new(MockEmailProvider::new(true));
+        let email_provider: Arc<dyn EmailProvider> = provider.clone();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(Arc::new(AppConfig::for_tests())))
+                .app_data(web::Data::new(email_provider))
+                .app_data(web::Data::new(keys.clone()))
+                .configure(api_routes),
+        )
+        .await;
+        let send = |key: &str, recipient: &str| {
+            test::TestRequest::post()
+                .uri("/api/send-history-email")
+                .insert_header(("X-API-Key", key))
+                .set_json(history(recipient))
+                .to_request()
+        };
+
+        // Politique globale : domaine example.com et partenaire@gmail.com
+        let resp = test::call_service(&app, send("secret", "x@gmail.com")).await;
+        assert_eq!(resp.status(), 422);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert!(body["message"].as_str().unwrap().starts_with("Destinataire invalide"));
+        assert_eq!(body["error"], "invalid_recipient");
+        let resp = test::call_service(&app, send("secret", "partenaire@gmail.com")).await;
+        assert_eq!(resp.status(), 200);
+
+        // La politique d'une clé remplace la politique globale
+        let req = test::TestRequest::post()
+            .uri("/api/admin/api-keys")
+            .insert_header(("X-API-Key", "secret"))
+            .set_json(serde_json::json!({
+                "label": "Tablette client",
+                "scopes": ["history:send"],
+                "recipients": { "domains": ["client.fr"] }
+            }))
+            .to_request();
+        let issued: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(issued["api_key"]["recipients"]["domains"][0], "client.fr");
+        let key = issued["key"].as_str().unwrap();
+        assert_eq!(test::call_service(&app, send(key, "jean@client.fr")).await.status(), 200);
+        assert_eq!(test::call_service(&app, send(key, "default@example.com")).await.status(), 422);
+
+        assert_eq!(provider.get_send_count(), 2);
+    }
+
+    #[actix_web::test]
+    async fn test_history_is_rate_limited_per_recipient_domain() {
+        let mut config = AppConfig::for_tests();
+        config.email.recipients = RecipientPolicy::new(["*"], [""; 0]);
+        config.rate_limit.history = RouteLimits {
+            per_domain: Some(Quota::new(1, Duration::from_secs(3600))),
+            ..RouteLimits::default()
+        };
+        let provider = Arc::new(MockEmailProvider::new(true));
+        let email_provider: Arc<dyn EmailProvider> = provider.clone();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(Arc::new(config)))
+                .app_data(web::Data::new(email_provider))
+                .app_data(web::Data::new(RateLimiter::new()))
+                .configure(api_routes),
+        )
+        .await;
+        let send = |recipient: &str| {
+            test::TestRequest::post()
+                .uri("/api/send-history-email")
+                .insert_header(("X-API-Key", "secret"))
+                .set_json(history(recipient))
+                .to_request()
+        };
+
+        assert_eq!(test::call_service(&app, send("a@spam.example")).await.status(), 200);
+        let resp = test::call_service(&app, send("b@SPAM.example")).await;
+        assert_eq!(resp.status(), 429);
+        assert_eq!(resp.headers().get("Retry-After").unwrap(), "3600");
+        assert_eq!(test::call_service(&app, send("c@autre.example")).await.status(), 200);
+        assert_eq!(provider.get_send_count(), 2);
+
+        let req = test::TestRequest::get()
+            .uri("/api/admin/rate-limits")
+            .insert_header(("X-API-Key", "secret"))
+            .to_request();
+        let stats: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(stats["limits"]["history"]["domain"]["limited"], 1);
+        assert_eq!(stats["limits"]["history"]["domain"]["tracked"], 2);
+    }
+}
@@ -1,12 +1,155 @@
 //! Handlers HTTP pour les endpoints API.
 //!
 //! Les handlers sont minces et délèguent la logique métier
-//! aux services appropriés.
+//! aux services appropriés. L'authentification est portée par les
+//! middlewares déclarés dans [`api_routes`].
 
+mod api_keys;
+mod audit;
+mod auth;
+mod contacts;
 mod export_fiches;
+mod exports;
 mod health;
 mod history;
+mod photos;
+mod rate_limits;
+mod sync;
+mod users;
 
-pub use export_fiches::export_fiches;
+use actix_web::{web, HttpResponse};
+use serde::Serialize;
+
+use crate::config::RateRoute;
+use crate::domain::Scope;
+use crate::middleware::{Authenticate, RateLimit, RequireScope};
+use crate::photo::PhotoError;
+
+pub use api_keys::{issue_api_key, list_api_keys, revoke_api_key};
+pub use audit::get_audit;
+pub use auth::{login, logout, refresh_token};
+pub use contacts::{
+    create_contact, delete_contact, get_contact, list_contacts, merge_contacts, update_contact,
+};
+pub use export_fiches::{export_fiches, export_fiches_multipart};
+pub use exports::{export_report, get_export, list_exports};
 pub use health::health_check;
 pub use history::send_history_email;
+pub use photos::get_photo;
+pub use rate_limits::get_rate_limits;
+pub use sync::sync_contacts;
+pub use users::{create_user, disable_user, list_users};
+
+/// Routes publiques de connexion du back-office `/auth`
+///
+/// Elles délivrent les jetons présentés ensuite aux routes [`api_routes`] ;
+/// sans appelant authentifié, seule la limite par adresse IP s'applique.
+pub fn auth_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/auth")
+            .wrap(RateLimit(RateRoute::Auth))
+            .route("/login", web::post().to(login))
+            .route("/refresh", web::post().to(refresh_token))
+            .route("/logout", web::post().to(logout)),
+    );
+}
+
+/// Routes authentifiées `/api` : chaque groupe déclare le droit qu'il exige
+///
+/// Une route publique se déclare hors de ce groupe (voir `main.rs`). Le
+/// dernier middleware déclaré s'exécute en premier : les limites de débit
+/// déclarées avant [`Authenticate`] voient l'appelant authentifié ; celle
+/// déclarée après limite par adresse IP, requêtes refusées (401) comprises.
+pub fn api_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api")
+            .wrap(RateLimit(RateRoute::Api))
+            .wrap(Authenticate)
+            .wrap(RateLimit(RateRoute::Api))
+            .service(
+                web::resource("/export-fiches")
+                    .wrap(RateLimit(RateRoute::Export))
+                    .wrap(RequireScope::new(Scope::ExportSend))
+                    .route(web::post().to(export_fiches)),
+            )
+            .service(
+                web::resource("/v2/export-fiches")
+                    .wrap(RateLimit(RateRoute::Export))
+                    .wrap(RequireScope::new(Scope::ExportSend))
+                    .route(web::post().to(export_fiches_multipart)),
+            )
+            .service(
+                web::scope("/exports")
+                    .wrap(RequireScope::new(Scope::ExportSend))
+                    .route("", web::get().to(list_exports))
+                    .route("/{id}", web::get().to(get_export))
+                    .route("/{id}/report.pdf", web::get().to(export_report)),
+            )
+            .service(
+                web::resource("/send-history-email")
+                    .wrap(RateLimit(RateRoute::History))
+                    .wrap(RequireScope::new(Scope::HistorySend))
+                    .route(web::post().to(send_history_email)),
+            )
+            .service(
+                web::resource("/sync")
+                    .wrap(RequireScope::new(Scope::Sync))
+                    .route(web::post().to(sync_contacts)),
+            )
+            .service(
+                web::scope("/contacts")
+                    .wrap(
+                        RequireScope::new(Scope::ContactsWrite)
+                            .reads(Scope::ContactsRead),
+                    )
+                    .service(
+                        web::resource("")
+                            .route(web::get().to(list_contacts))
+                            .route(web::post().to(create_contact)),
+                    )
+                    .service(
+                        web::resource("/{id}")
+                            .route(web::get().to(get_contact))
+                            .route(web::put().to(update_contact))
+                            .route(web::delete().to(delete_contact)),
+                    )
+                    .route("/{id}/merge", web::post().to(merge_contacts)),
+            )
+            .service(
+                web::resource("/audit")
+                    .wrap(RequireScope::new(Scope::Admin))
+                    .route(web::get().to(get_audit)),
+            )
+            .service(
+                web::scope("/admin")
+                    .wrap(RequireScope::new(Scope::Admin))
+                    .service(
+                        web::resource("/api-keys")
+                            .route(web::get().to(list_api_keys))
+                            .route(web::post().to(issue_api_key)),
+                    )
+                    .route("/api-keys/{id}", web::delete().to(revoke_api_key))
+                    .service(
+                        web::resource("/users")
+                            .route(web::get().to(list_users))
+                            .route(web::post().to(create_user)),
+                    )
+                    .route("/users/{id}", web::delete().to(disable_user))
+                    .route("/rate-limits", web::get().to(get_rate_limits)),
+            ),
+    );
+}
+
+/// Réponse d'erreur du traitement des photos : 400 si une photo est en cause
+///
+/// `error` construit le corps d'erreur propre à l'endpoint.
+fn photo_error_response<T: Serialize>(
+    e: PhotoError,
+    error: impl Fn(String) -> T,
+) -> HttpResponse {
+    if e.is_invalid_photo() {
+        return HttpResponse::BadRequest().json(error(format!("Photo invalide: {}", e)));
+    }
+    tracing::error!(error = %e, "Erreur du traitement des photos");
+    HttpResponse::InternalServerError().json(error("Erreur du traitement des photos".to_string()))
+}
@@ -0,0 +1,108 @@
+//! Handler des liens temporaires vers les photos stockées.
+//!
+//! Les stockages sans URL présignée (système de fichiers) servent leurs
+//! photos par ce lien, signé par le serveur et limité dans le temps. Il est
+//! destiné aux destinataires des emails d'export : aucune clé d'API n'est
+//! demandée.
+
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use tracing::{error, instrument};
+
+use crate::photo::{PhotoStorage, StoreError, PHOTO_CONTENT_TYPE};
+
+#[derive(Serialize)]
+pub struct PhotoResponse {
+    success: bool,
+    message: String,
+}
+
+impl PhotoResponse {
+    fn error(message: impl Into<String>) -> Self {
+        Self {
+            success: false,
+            message: message.into(),
+        }
+    }
+}
+
+/// Paramètres d'un lien signé
+#[derive(Debug, Deserialize)]
+pub struct PhotoLinkQuery {
+    /// Fin de validité du lien (timestamp Unix, secondes)
+    expires: i64,
+    signature: String,
+}
+
+/// GET /photos/{uuid}.jpg
+#[instrument(skip(query, storage))]
+pub async fn get_photo(
+    path: web::Path<String>,
+    query: web::Query<PhotoLinkQuery>,
+    storage: web::Data<PhotoStorage>,
+) -> HttpResponse {
+    match storage.open_link(&path, query.expires, &query.signature).await {
+        Ok(Some(data)) => HttpResponse::Ok()
+            .content_type(PHOTO_CONTENT_TYPE)
+            .insert_header(("Cache-Control", "private, max-age=3600"))
+            .body(data),
+        Ok(None) => HttpResponse::Forbidden()
+            .json(PhotoResponse::error("Lien invalide ou expiré")),
+        Err(StoreError::NotFound(_) | StoreError::InvalidKey(_)) => HttpResponse::NotFound()
+            .json(PhotoResponse::error(format!("Photo {} introuvable", path.as_str()))),
+        Err(e) => {
+            error!(error = %e, "Erreur lecture de la photo");
+            HttpResponse::InternalServerError()
+                .json(PhotoResponse::error("Erreur lecture de la photo"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{AppConfig, PhotoStoreBackend};
+    use crate::domain::ContactFiche;
+    use actix_web::{test, App};
+
+    #[actix_web::test]
+    async fn test_signed_link_serves_photo_until_tampered() {
+        let root = tempfile::tempdir().unwrap();
+        let mut config = AppConfig::for_tests().photo_store;
+        config.backend = PhotoStoreBackend::Filesystem { root: root.path().to_path_buf() };
+        config.public_base_url = Some("https://smp.example.com".to_string());
+        let storage = crate::photo::build_storage(&config);
+
+        let mut fiche: ContactFiche = serde_json::from_value(serde_json::json!({
+            "societe": "ACME",
+            "contact": "Jean",
+            "email": "jean@acme.fr",
+            "telephone": "",
+            "notes": "",
+            "sectors": "",
+            "created_at": 0,
+            "photo_base64": "anBlZw=="
+        }))
+        .unwrap();
+        storage.upload([&mut fiche]).await.unwrap();
+        let links = storage.links(std::slice::from_ref(&fiche)).unwrap();
+        let url = links.urls[0].as_deref().unwrap();
+        let uri = url.strip_prefix("https://smp.example.com").unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(storage))
+                .route("/{key:photos/.+}", web::get().to(get_photo)),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri(uri).to_request();
+        let response = test::call_service(&app, req).await;
+        assert_eq!(response.status(), 200);
+        assert_eq!(test::read_body(response).await, "jpeg");
+
+        let tampered = uri.replace("expires=", "expires=1");
+        let req = test::TestRequest::get().uri(&tampered).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 403);
+    }
+}
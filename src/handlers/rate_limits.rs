@@ -0,0 +1,29 @@
+//! Handler de consultation des compteurs des limites de débit.
+
+use actix_web::{web, HttpResponse};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use tracing::instrument;
+
+use crate::config::RateRoute;
+use crate::middleware::{LimitKind, RateLimitStats, RateLimiter};
+
+#[derive(Serialize)]
+pub struct RateLimitsResponse {
+    success: bool,
+    message: String,
+    limits: BTreeMap<RateRoute, BTreeMap<LimitKind, RateLimitStats>>,
+}
+
+/// GET /api/admin/rate-limits
+///
+/// Requêtes acceptées et refusées (429) depuis le démarrage, par groupe de
+/// routes et par critère (`key`, `ip`, `domain`).
+#[instrument(skip(limiter))]
+pub async fn get_rate_limits(limiter: web::Data<RateLimiter>) -> HttpResponse {
+    HttpResponse::Ok().json(RateLimitsResponse {
+        success: true,
+        message: "Compteurs des limites de débit".to_string(),
+        limits: limiter.stats(),
+    })
+}
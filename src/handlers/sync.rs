@@ -0,0 +1,210 @@
+//! Handler de synchronisation des appareils.
+
+use actix_web::{web, HttpResponse};
+use tracing::{error, info, instrument};
+use uuid::Uuid;
+use validator::Validate;
+
+use super::photo_error_response;
+use crate::domain::{SyncRequest, SyncResponse};
+use crate::middleware::Principal;
+use crate::photo::{PhotoProcessor, PhotoStorage};
+use crate::storage::{ContactRepository, StorageError};
+
+/// Nombre maximal de modifications serveur renvoyées par appel
+const MAX_CHANGES_PER_SYNC: u32 = 500;
+
+/// POST /api/sync
+///
+/// Applique les modifications locales de l'appareil et renvoie les
+/// modifications serveur postérieures à son curseur.
+#[instrument(
+    skip(principal, body, contacts_repo, photos, storage),
+    fields(device_id, changes_count)
+)]
+pub async fn sync_contacts(
+    principal: Principal,
+    body: web::Json<SyncRequest>,
+    contacts_repo: web::Data<ContactRepository>,
+    photos: web::Data<PhotoProcessor>,
+    storage: web::Data<PhotoStorage>,
+) -> HttpResponse {
+    // 1. Valider la requête et chaque modification
+    if let Err(errors) = body.validate() {
+        return HttpResponse::BadRequest().json(SyncResponse::error(
+            format!("Validation échouée: {:?}", errors)
+        ));
+    }
+
+    for change in &body.changes {
+        if Uuid::parse_str(&change.id).is_err() {
+            return HttpResponse::BadRequest().json(SyncResponse::error(
+                format!("Identifiant de fiche invalide: {}", change.id)
+            ));
+        }
+        if let Some(Err(errors)) = change.fiche.as_ref().map(|f| f.validate()) {
+            return HttpResponse::BadRequest().json(SyncResponse::error(
+                format!("Fiche {} invalide: {:?}", change.id, errors)
+            ));
+        }
+    }
+
+    let mut body = body.into_inner();
+    if let Err(response) = principal.resolve_device(Some(body.device_id.clone())) {
+        return response;
+    }
+    tracing::Span::current().record("device_id", body.device_id.as_str());
+    tracing::Span::current().record("changes_count", body.changes.len());
+
+    // 2. Traiter les photos des fiches créées ou modifiées
+    body.changes = match photos
+        .process_photos(body.changes, |changes| {
+            changes.iter_mut().filter_map(|c| c.fiche.as_mut()).collect()
+        })
+        .await
+    {
+        Ok(changes) => changes,
+        Err(e) => return photo_error_response(e, SyncResponse::error),
+    };
+
+    // 3. Déposer les photos dans le stockage externe éventuel
+    let fiches = body.changes.iter_mut().filter_map(|c| c.fiche.as_mut());
+    if let Err(e) = storage.upload(fiches).await {
+        error!(error = %e, "Erreur d'enregistrement des photos");
+        return HttpResponse::InternalServerError()
+            .json(SyncResponse::error("Erreur d'enregistrement des photos"));
+    }
+    let photo_keys: Vec<(String, String)> = body
+        .changes
+        .iter()
+        .filter_map(|c| Some((c.id.clone(), c.fiche.as_ref()?.photo_key.clone()?)))
+        .collect();
+
+    // 4. Appliquer et récupérer les modifications serveur
+    let result = contacts_repo
+        .sync(
+            body.device_id,
+            principal.device_id.clone(),
+            body.cursor,
+            body.changes,
+            MAX_CHANGES_PER_SYNC,
+        )
+        .await;
+
+    // Photos des modifications refusées : jamais enregistrées
+    let rejected: Vec<String> = match &result {
+        Ok(outcome) => photo_keys
+            .into_iter()
+            .filter(|(id, _)| outcome.conflicts.iter().any(|c| &c.id == id))
+            .map(|(_, key)| key)
+            .collect(),
+        Err(_) => photo_keys.into_iter().map(|(_, key)| key).collect(),
+    };
+    storage.discard(rejected).await;
+
+    match result {
+        Ok(outcome) => {
+            info!(
+                applied = outcome.applied.len(),
+                conflicts = outcome.conflicts.len(),
+                server_changes = outcome.changes.len(),
+                cursor = outcome.cursor,
+                "Synchronisation effectuée"
+            );
+
+            HttpResponse::Ok().json(SyncResponse {
+                success: true,
+                message: format!(
+                    "{} modification(s) appliquée(s), {} conflit(s)",
+                    outcome.applied.len(),
+                    outcome.conflicts.len()
+                ),
+                cursor: outcome.cursor,
+                has_more: outcome.has_more,
+                applied: outcome.applied,
+                conflicts: outcome.conflicts,
+                changes: outcome.changes,
+            })
+        }
+        Err(StorageError::InvalidData(message)) => HttpResponse::BadRequest()
+            .json(SyncResponse::error(format!("Modification invalide: {}", message))),
+        Err(e) => {
+            error!(error = %e, "Erreur de synchronisation");
+            HttpResponse::InternalServerError()
+                .json(SyncResponse::error("Erreur de synchronisation"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::domain::{ContactFiche, IssueApiKeyRequest, Scope};
+    use crate::handlers::api_routes;
+    use crate::storage::{ApiKeyRepository, AuditRepository, Database};
+    use actix_web::{test, App};
+    use std::sync::Arc;
+
+    #[actix_web::test]
+    async fn test_device_key_cannot_sync_another_devices_contacts() {
+        let database = Database::open_in_memory().unwrap();
+        let config = AppConfig::for_tests();
+        let contacts = ContactRepository::new(database.clone());
+        let keys = ApiKeyRepository::new(database.clone());
+        let theirs = contacts
+            .insert(ContactFiche::sample("ACME"), Some("tablet-a".into()))
+            .await
+            .unwrap();
+        let (_, secrets) = keys
+            .issue(IssueApiKeyRequest {
+                label: "Tablette B".to_string(),
+                device_id: Some("tablet-b".to_string()),
+                scopes: vec![Scope::Sync],
+                expires_in_days: None,
+                recipients: None,
+            })
+            .await
+            .unwrap();
+
+        let audit = AuditRepository::new(database);
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(PhotoProcessor::new(config.photo.clone(), audit)))
+                .app_data(web::Data::new(crate::photo::build_storage(&config.photo_store)))
+                .app_data(web::Data::new(Arc::new(config)))
+                .app_data(web::Data::new(contacts.clone()))
+                .app_data(web::Data::new(keys))
+                .configure(api_routes),
+        )
+        .await;
+        let sync = |changes: serde_json::Value| {
+            test::TestRequest::post()
+                .uri("/api/sync")
+                .insert_header(("X-API-Key", secrets.key.as_str()))
+                .set_json(serde_json::json!({ "device_id": "tablet-b", "changes": changes }))
+                .to_request()
+        };
+
+        // La tablette B ne reçoit pas la fiche de la tablette A
+        let req = sync(serde_json::json!([]));
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["changes"].as_array().unwrap().len(), 0);
+
+        // Ni modification ni suppression : conflit, sans dévoiler la fiche
+        let fiche = serde_json::to_value(ContactFiche::sample("Pirate")).unwrap();
+        let changes = serde_json::json!([
+            { "id": theirs.id, "base_version": 1, "fiche": fiche },
+            { "id": theirs.id, "base_version": 1, "deleted": true }
+        ]);
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, sync(changes)).await;
+        assert!(resp["applied"].as_array().unwrap().is_empty());
+        let conflicts = resp["conflicts"].as_array().unwrap();
+        assert_eq!(conflicts.len(), 2);
+        assert!(conflicts.iter().all(|c| c["server"].is_null()));
+
+        let stored = contacts.get(&theirs.id).await.unwrap().unwrap();
+        assert_eq!(stored.fiche.societe, "ACME");
+        assert_eq!(stored.version, 1);
+    }
+}
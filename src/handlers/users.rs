@@ -0,0 +1,195 @@
+//! Handlers d'administration des comptes du back-office.
+
+use actix_web::{web, HttpResponse};
+use serde::Serialize;
+use tracing::{error, info, instrument};
+use validator::Validate;
+
+use crate::domain::{CreateUserRequest, User};
+use crate::storage::{StorageError, UserRepository};
+
+#[derive(Serialize)]
+pub struct UserResponse {
+    success: bool,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user: Option<User>,
+}
+
+impl UserResponse {
+    fn success(message: impl Into<String>, user: Option<User>) -> Self {
+        Self {
+            success: true,
+            message: message.into(),
+            user,
+        }
+    }
+
+    fn error(message: impl Into<String>) -> Self {
+        Self {
+            success: false,
+            message: message.into(),
+            user: None,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct UserListResponse {
+    success: bool,
+    users: Vec<User>,
+}
+
+/// POST /api/admin/users
+#[instrument(skip(body, users), fields(username = %body.username))]
+pub async fn create_user(
+    body: web::Json<CreateUserRequest>,
+    users: web::Data<UserRepository>,
+) -> HttpResponse {
+    if let Err(errors) = body.validate() {
+        return HttpResponse::BadRequest().json(UserResponse::error(format!(
+            "Validation échouée: {:?}",
+            errors
+        )));
+    }
+
+    let username = body.username.clone();
+    match users.create(body.into_inner()).await {
+        Ok(Some(user)) => {
+            info!(user_id = %user.id, role = user.role.as_str(), "Compte créé");
+            HttpResponse::Created().json(UserResponse::success("Compte créé", Some(user)))
+        }
+        Ok(None) => HttpResponse::Conflict().json(UserResponse::error(format!(
+            "L'identifiant {} est déjà utilisé",
+            username
+        ))),
+        Err(e) => storage_error_response(e),
+    }
+}
+
+/// GET /api/admin/users
+#[instrument(skip(users))]
+pub async fn list_users(users: web::Data<UserRepository>) -> HttpResponse {
+    match users.list().await {
+        Ok(users) => HttpResponse::Ok().json(UserListResponse {
+            success: true,
+            users,
+        }),
+        Err(e) => storage_error_response(e),
+    }
+}
+
+/// DELETE /api/admin/users/{id}
+///
+/// Désactive un compte : il ne peut plus se connecter ni renouveler ses
+/// jetons ; son jeton d'accès en cours expire de lui-même.
+#[instrument(skip(users))]
+pub async fn disable_user(
+    path: web::Path<String>,
+    users: web::Data<UserRepository>,
+) -> HttpResponse {
+    match users.disable(&path).await {
+        Ok(true) => {
+            info!(user_id = %path.as_str(), "Compte désactivé");
+            HttpResponse::Ok().json(UserResponse::success("Compte désactivé", None))
+        }
+        Ok(false) => HttpResponse::NotFound().json(UserResponse::error(format!(
+            "Compte {} introuvable ou déjà désactivé",
+            path.as_str()
+        ))),
+        Err(e) => storage_error_response(e),
+    }
+}
+
+fn storage_error_response(e: StorageError) -> HttpResponse {
+    error!(error = %e, "Erreur base de données");
+    HttpResponse::InternalServerError().json(UserResponse::error("Erreur base de données"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::handlers::{api_routes, auth_routes};
+    use crate::storage::{ApiKeyRepository, AuditRepository, ContactRepository, Database};
+    use actix_web::{test, App};
+    use std::sync::Arc;
+
+    #[actix_web::test]
+    async fn test_user_logs_in_with_role_scopes_and_refreshes() {
+        let database = Database::open_in_memory().unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(Arc::new(AppConfig::for_tests())))
+                .app_data(web::Data::new(ApiKeyRepository::new(database.clone())))
+                .app_data(web::Data::new(UserRepository::new(database.clone())))
+                .app_data(web::Data::new(ContactRepository::new(database.clone())))
+                .app_data(web::Data::new(AuditRepository::new(database)))
+                .configure(auth_routes)
+                .configure(api_routes),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/admin/users")
+            .insert_header(("X-API-Key", "secret"))
+            .set_json(serde_json::json!({
+                "username": "claire",
+                "password": "mot-de-passe-solide",
+                "role": "viewer"
+            }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 201);
+
+        let req = test::TestRequest::post()
+            .uri("/auth/login")
+            .set_json(serde_json::json!({"username": "claire", "password": "faux"}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 401);
+
+        let req = test::TestRequest::post()
+            .uri("/auth/login")
+            .set_json(serde_json::json!({
+                "username": "claire",
+                "password": "mot-de-passe-solide"
+            }))
+            .to_request();
+        let tokens: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let access = format!("Bearer {}", tokens["access_token"].as_str().unwrap());
+        let refresh = tokens["refresh_token"].as_str().unwrap().to_string();
+
+        // Le rôle `viewer` ouvre la consultation, pas l'administration
+        let req = test::TestRequest::get()
+            .uri("/api/contacts")
+            .insert_header(("Authorization", access.as_str()))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+        let req = test::TestRequest::get()
+            .uri("/api/admin/users")
+            .insert_header(("Authorization", access.as_str()))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 403);
+
+        let req = test::TestRequest::get()
+            .uri("/api/contacts")
+            .insert_header(("Authorization", "Bearer pas-un-jeton"))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 401);
+
+        let req = test::TestRequest::post()
+            .uri("/auth/refresh")
+            .set_json(serde_json::json!({"refresh_token": refresh}))
+            .to_request();
+        let renewed: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert!(renewed["access_token"].is_string());
+        assert_ne!(renewed["refresh_token"].as_str().unwrap(), refresh);
+
+        // Le jeton de rafraîchissement est à usage unique
+        let req = test::TestRequest::post()
+            .uri("/auth/refresh")
+            .set_json(serde_json::json!({"refresh_token": refresh}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 401);
+    }
+}
@@ -31,7 +31,7 @@
 //! │                   Email Provider (trait)                     │
 //! │                           │                                  │
 //! │              ┌────────────┴────────────┐                     │
-//! │              │     ResendProvider      │                     │
+//! │              │ ResendProvider │ Smtp   │                     │
 //! │              └─────────────────────────┘                     │
 //! └──────────────────────────────────────────────────────────────┘
 //! ```
@@ -39,8 +39,12 @@
 mod config;
 mod domain;
 mod email;
+mod export;
 mod handlers;
 mod middleware;
+mod photo;
+mod storage;
+mod upload;
 
 use actix_web::{web, App, HttpServer, middleware as actix_middleware};
 use std::sync::Arc;
@@ -48,7 +52,12 @@ use tracing::{info, Level};
 use tracing_subscriber::FmtSubscriber;
 
 use crate::config::AppConfig;
-use crate::email::{EmailProvider, ResendProvider};
+use crate::email::{EmailProvider, OutboxWorker};
+use crate::photo::PhotoProcessor;
+use crate::storage::{
+    ApiKeyRepository, AuditRepository, ContactRepository, Database, OutboxRepository,
+    UserRepository,
+};
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
@@ -62,19 +71,47 @@ async fn main() -> std::io::Result<()> {
     info!(
         host = %config.server.host,
         port = %config.server.port,
+        mode = ?config.security.mode,
         "Démarrage du serveur SMP Backend"
     );
+    if config.security.api_key == config::DEV_API_KEY {
+        tracing::warn!("API_KEY absente : clé de développement utilisée");
+    }
 
-    // 3. Créer le provider email
-    let email_provider: Arc<dyn EmailProvider> = Arc::new(ResendProvider::new(&config.email));
+    // 3. Ouvrir la base de données (migrations incluses)
+    let database = Database::open(&config.database.url).expect("Erreur d'ouverture de la base");
+    let contacts_repo = ContactRepository::new(database.clone());
+    let audit = AuditRepository::new(database.clone());
+    let api_keys = ApiKeyRepository::new(database.clone());
+    let users = UserRepository::new(database.clone());
+    let photos = PhotoProcessor::new(config.photo.clone(), audit.clone());
+    let outbox = OutboxRepository::new(database);
+
+    // Stockage des photos (base, système de fichiers ou bucket S3)
+    let photo_storage = photo::build_storage(&config.photo_store);
+    photo_storage.clone().spawn_cleanup(contacts_repo.clone());
+
+    // 4. Créer le provider email
+    let email_provider: Arc<dyn EmailProvider> =
+        email::build_provider(&config.email).expect("Erreur de configuration du provider email");
 
     info!(
         provider = %email_provider.provider_name(),
         "Provider email initialisé"
     );
 
-    // 4. Démarrer le serveur
+    // 5. Démarrer le worker de la file d'envoi
+    OutboxWorker::new(outbox.clone(), email_provider.clone(), config.outbox.clone()).spawn();
+
+    // 6. Démarrer le serveur
     let server_config = config.clone();
+    // Cache partagé par tous les workers HTTP : un nonce vu par l'un est refusé par les autres
+    let nonces = web::Data::new(middleware::NonceCache::new(
+        config.security.nonce_cache_size,
+        config.security.signature_max_skew,
+    ));
+    // Idem pour les seaux des limites de débit
+    let rate_limiter = web::Data::new(middleware::RateLimiter::new());
     
     HttpServer::new(move || {
         App::new()
@@ -85,14 +122,24 @@ async fn main() -> std::io::Result<()> {
             // State partagé
             .app_data(web::Data::new(config.clone()))
             .app_data(web::Data::new(email_provider.clone()))
+            .app_data(web::Data::new(contacts_repo.clone()))
+            .app_data(web::Data::new(outbox.clone()))
+            .app_data(web::Data::new(audit.clone()))
+            .app_data(web::Data::new(api_keys.clone()))
+            .app_data(web::Data::new(users.clone()))
+            .app_data(nonces.clone())
+            .app_data(rate_limiter.clone())
+            .app_data(web::Data::new(photos.clone()))
+            .app_data(web::Data::new(photo_storage.clone()))
             
             // Configuration JSON
             .app_data(web::JsonConfig::default().limit(10 * 1024 * 1024)) // 10MB limit
             
-            // Routes
+            // Routes publiques
             .route("/health", web::get().to(handlers::health_check))
-            .route("/api/export-fiches", web::post().to(handlers::export_fiches))
-            .route("/api/send-history-email", web::post().to(handlers::send_history_email))
+            .route("/{key:photos/.+}", web::get().to(handlers::get_photo))
+            .configure(handlers::auth_routes)
+            .configure(handlers::api_routes)
     })
     .bind((server_config.server.host.as_str(), server_config.server.port))?
     .run()
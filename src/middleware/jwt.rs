@@ -0,0 +1,95 @@
+//! Jetons d'accès du back-office (JWT signés HS256).
+//!
+//! Les jetons d'accès sont courts et sans état : un compte désactivé garde
+//! l'accès jusqu'à leur expiration, mais ne peut plus les renouveler.
+
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+
+use super::AuthError;
+use crate::config::SecurityConfig;
+use crate::domain::{Role, User};
+
+/// Contenu d'un jeton d'accès
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccessClaims {
+    /// Identifiant du compte
+    pub sub: String,
+    pub role: Role,
+    /// Émission et expiration (timestamp Unix, secondes)
+    pub iat: i64,
+    pub exp: i64,
+}
+
+/// Émet un jeton d'accès pour un compte
+pub fn issue_access_token(
+    config: &SecurityConfig,
+    user: &User,
+) -> Result<String, jsonwebtoken::errors::Error> {
+    let now = chrono::Utc::now().timestamp();
+    let claims = AccessClaims {
+        sub: user.id.clone(),
+        role: user.role,
+        iat: now,
+        exp: now + config.access_token_ttl.as_secs() as i64,
+    };
+    jsonwebtoken::encode(
+        &Header::new(Algorithm::HS256),
+        &claims,
+        &EncodingKey::from_secret(config.jwt_secret.as_bytes()),
+    )
+}
+
+/// Vérifie la signature et l'expiration d'un jeton d'accès
+pub(super) fn verify_access_token(
+    config: &SecurityConfig,
+    token: &str,
+) -> Result<AccessClaims, AuthError> {
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.leeway = 5;
+    jsonwebtoken::decode::<AccessClaims>(
+        token,
+        &DecodingKey::from_secret(config.jwt_secret.as_bytes()),
+        &validation,
+    )
+    .map(|data| data.claims)
+    .map_err(|e| match e.kind() {
+        ErrorKind::ExpiredSignature => AuthError::ExpiredToken,
+        _ => AuthError::InvalidToken,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+
+    #[test]
+    fn test_access_token_roundtrip_and_tampering() {
+        let mut config = AppConfig::for_tests().security;
+        let user = User {
+            id: "u-1".to_string(),
+            username: "claire".to_string(),
+            role: Role::Manager,
+            created_at: 0,
+            disabled_at: None,
+        };
+
+        let token = issue_access_token(&config, &user).unwrap();
+        let claims = verify_access_token(&config, &token).unwrap();
+        assert_eq!((claims.sub.as_str(), claims.role), ("u-1", Role::Manager));
+
+        config.jwt_secret = "autre-secret".to_string();
+        assert!(matches!(verify_access_token(&config, &token), Err(AuthError::InvalidToken)));
+
+        let expired = AccessClaims { iat: 0, exp: 60, ..claims };
+        let token = jsonwebtoken::encode(
+            &Header::new(Algorithm::HS256),
+            &expired,
+            &EncodingKey::from_secret(config.jwt_secret.as_bytes()),
+        )
+        .unwrap();
+        assert!(matches!(verify_access_token(&config, &token), Err(AuthError::ExpiredToken)));
+    }
+}
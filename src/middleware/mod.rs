@@ -1,24 +1,838 @@
 //! Middleware et guards pour la sécurité.
+//!
+//! Deux modes d'authentification :
+//! - clé statique dans `X-API-Key` ;
+//! - requête signée (optionnel) : l'appareil envoie l'identifiant de sa clé
+//!   (`X-Key-Id`), un horodatage Unix en secondes (`X-Timestamp`), un nonce
+//!   à usage unique (`X-Nonce`), l'empreinte SHA-256 hexadécimale du corps
+//!   (`X-Content-SHA256`) et la signature HMAC-SHA256 hexadécimale
+//!   (`X-Signature`) de `METHODE\nCHEMIN?REQUETE\nHORODATAGE\nNONCE\nEMPREINTE`,
+//!   calculée avec le secret de signature de la clé. Une requête rejouée ou
+//!   trop ancienne est refusée, même interceptée sur un réseau non sûr.
+//!
+//! Les utilisateurs du back-office présentent quant à eux un jeton d'accès
+//! (`Authorization: Bearer`), dont le rôle détermine les droits.
+//!
+//! L'authentification est portée par des middlewares déclarés sur les groupes
+//! de routes : [`Authenticate`] sur `/api`, puis [`RequireScope`] pour le droit
+//! de chaque groupe. Les routes publiques (`/health`, liens des photos) sont
+//! simplement déclarées hors de `/api`. Les limites de débit ([`RateLimit`])
+//! se déclarent de la même façon, à l'intérieur de [`Authenticate`].
+
+use actix_web::body::EitherBody;
+use actix_web::dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::error::{InternalError, PayloadError};
+use actix_web::http::Method;
+use actix_web::{web, FromRequest, HttpMessage, HttpRequest, HttpResponse};
+use futures_util::future::LocalBoxFuture;
+use futures_util::{Stream, StreamExt};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{HashSet, VecDeque};
+use std::future::{ready, Ready};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use subtle::ConstantTimeEq;
 
-use actix_web::{HttpRequest, HttpResponse};
 use crate::config::AppConfig;
-use crate::domain::ExportFichesResponse;
+use crate::domain::{RecipientPolicy, Scope};
+use crate::email::EmailError;
+use crate::storage::{ApiKeyRepository, StorageError};
+
+mod jwt;
+mod rate_limit;
+
+pub use jwt::issue_access_token;
+pub use rate_limit::{check_recipient, LimitKind, RateLimit, RateLimitStats, RateLimiter};
+
+pub const KEY_ID_HEADER: &str = "X-Key-Id";
+pub const TIMESTAMP_HEADER: &str = "X-Timestamp";
+pub const NONCE_HEADER: &str = "X-Nonce";
+pub const CONTENT_SHA256_HEADER: &str = "X-Content-SHA256";
+pub const SIGNATURE_HEADER: &str = "X-Signature";
+
+/// Marge ajoutée aux photos pour les champs d'un corps signé
+const SIGNED_BODY_OVERHEAD: usize = 10 * 1024 * 1024;
+
+/// Échec d'authentification ou d'autorisation
+#[derive(Debug, thiserror::Error)]
+pub enum AuthError {
+    #[error("Clé API manquante")]
+    MissingKey,
+
+    #[error("Clé API invalide")]
+    InvalidKey,
+
+    #[error("Jeton d'accès invalide")]
+    InvalidToken,
+
+    #[error("Jeton d'accès expiré")]
+    ExpiredToken,
+
+    #[error("Droit {} requis", .0.as_str())]
+    MissingScope(Scope),
+
+    #[error("Clé d'API réservée à l'appareil {0}")]
+    DeviceMismatch(String),
+
+    #[error("En-tête {0} manquant ou invalide")]
+    IncompleteSignature(&'static str),
+
+    #[error("Signature invalide")]
+    InvalidSignature,
+
+    #[error("Horodatage hors de la fenêtre acceptée")]
+    StaleTimestamp,
+
+    #[error("Nonce déjà utilisé")]
+    ReplayedNonce,
+
+    #[error("Trop de requêtes signées en cours de validité, réessayez plus tard")]
+    NonceCacheFull,
+
+    #[error("Le corps ne correspond pas à son empreinte")]
+    BodyHashMismatch,
+
+    #[error("Corps de requête trop volumineux")]
+    BodyTooLarge,
 
-/// Vérifie la clé API dans les headers
-pub fn verify_api_key(req: &HttpRequest, config: &AppConfig) -> Result<(), HttpResponse> {
+    #[error("Erreur base de données")]
+    Storage(#[from] StorageError),
+}
+
+/// Réponse commune à tous les refus d'accès
+#[derive(Debug, Serialize)]
+pub struct AuthErrorResponse {
+    pub success: bool,
+    pub message: String,
+    /// Code stable, exploitable par l'application
+    pub error: &'static str,
+    /// Droit manquant
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<Scope>,
+}
+
+impl AuthError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::MissingKey => "missing_api_key",
+            Self::InvalidKey => "invalid_api_key",
+            Self::InvalidToken => "invalid_token",
+            Self::ExpiredToken => "expired_token",
+            Self::MissingScope(_) => "missing_scope",
+            Self::DeviceMismatch(_) => "device_mismatch",
+            Self::IncompleteSignature(_) | Self::InvalidSignature => "invalid_signature",
+            Self::StaleTimestamp => "stale_timestamp",
+            Self::ReplayedNonce => "replayed_nonce",
+            Self::NonceCacheFull => "nonce_cache_full",
+            Self::BodyHashMismatch => "body_hash_mismatch",
+            Self::BodyTooLarge => "payload_too_large",
+            Self::Storage(_) => "internal_error",
+        }
+    }
+
+    /// Réponse HTTP (401, 403, 413, 500 ou 503)
+    pub fn response(&self) -> HttpResponse {
+        let mut response = match self {
+            Self::MissingScope(_) | Self::DeviceMismatch(_) => HttpResponse::Forbidden(),
+            Self::BodyTooLarge => HttpResponse::PayloadTooLarge(),
+            Self::NonceCacheFull => HttpResponse::ServiceUnavailable(),
+            Self::Storage(_) => HttpResponse::InternalServerError(),
+            _ => HttpResponse::Unauthorized(),
+        };
+        response.json(AuthErrorResponse {
+            success: false,
+            message: self.to_string(),
+            error: self.code(),
+            scope: match self {
+                Self::MissingScope(scope) => Some(*scope),
+                _ => None,
+            },
+        })
+    }
+}
+
+/// Appelant authentifié, placé dans les extensions de la requête par
+/// [`Authenticate`] et extrait par les handlers qui en ont besoin
+#[derive(Debug, Clone)]
+pub struct Principal {
+    /// Clé d'API utilisée (`None` pour la clé d'administration `API_KEY`)
+    pub key_id: Option<String>,
+    /// Appareil auquel la clé est remise
+    pub device_id: Option<String>,
+    /// Utilisateur du back-office (jeton d'accès)
+    pub user_id: Option<String>,
+    pub scopes: Vec<Scope>,
+    /// Destinataires propres à la clé, à la place de la politique globale
+    pub recipients: Option<RecipientPolicy>,
+}
+
+impl Principal {
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        self.scopes.contains(&scope)
+    }
+
+    /// Appareil pour lequel agit la requête
+    ///
+    /// Une clé remise à un appareil ne peut agir que pour lui : l'appareil
+    /// demandé doit être le sien, ou absent.
+    pub fn resolve_device(
+        &self,
+        requested: Option<String>,
+    ) -> Result<Option<String>, HttpResponse> {
+        match (&self.device_id, requested) {
+            (Some(own), Some(requested)) if *own != requested => {
+                tracing::warn!(
+                    key_id = ?self.key_id,
+                    device_id = %own,
+                    requested = %requested,
+                    "Clé utilisée pour un autre appareil"
+                );
+                Err(AuthError::DeviceMismatch(own.clone()).response())
+            }
+            (Some(own), _) => Ok(Some(own.clone())),
+            (None, requested) => Ok(requested),
+        }
+    }
+
+    /// La ressource d'un appareil est accessible à l'appelant : une clé
+    /// remise à un appareil ne voit que les ressources de cet appareil
+    pub fn owns(&self, device_id: Option<&str>) -> bool {
+        match &self.device_id {
+            Some(own) => device_id == Some(own.as_str()),
+            None => true,
+        }
+    }
+
+    /// Refuse un destinataire hors de la politique de la clé, ou à défaut
+    /// de la politique globale, avant tout envoi
+    pub fn authorize_recipient(
+        &self,
+        global: &RecipientPolicy,
+        recipient: &str,
+    ) -> Result<(), EmailError> {
+        if self.recipients.as_ref().unwrap_or(global).allows(recipient) {
+            return Ok(());
+        }
+        tracing::warn!(
+            key_id = ?self.key_id,
+            user_id = ?self.user_id,
+            recipient = %recipient,
+            "Destinataire refusé"
+        );
+        Err(EmailError::InvalidRecipient(format!(
+            "{} n'est pas un destinataire autorisé",
+            recipient
+        )))
+    }
+}
+
+impl FromRequest for Principal {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    /// Une route hors de [`Authenticate`] n'a pas d'appelant : refusée
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(req.extensions().get::<Principal>().cloned().ok_or_else(|| {
+            let e = AuthError::MissingKey;
+            InternalError::from_response(e.to_string(), e.response()).into()
+        }))
+    }
+}
+
+/// Authentifie les requêtes d'un groupe de routes
+///
+/// La clé d'administration (`API_KEY`) a tous les droits ; les autres clés
+/// sont cherchées dans le registre des clés d'API, s'il est enregistré dans
+/// l'application.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Authenticate;
+
+/// Exige un droit sur un groupe de routes déjà authentifié
+///
+/// Les lectures (`GET`, `HEAD`) peuvent se contenter d'un droit distinct.
+#[derive(Debug, Clone, Copy)]
+pub struct RequireScope {
+    scope: Scope,
+    read_scope: Option<Scope>,
+}
+
+impl RequireScope {
+    pub fn new(scope: Scope) -> Self {
+        Self {
+            scope,
+            read_scope: None,
+        }
+    }
+
+    /// Droit suffisant pour les lectures
+    pub fn reads(self, scope: Scope) -> Self {
+        Self {
+            read_scope: Some(scope),
+            ..self
+        }
+    }
+
+    fn scope_for(&self, method: &Method) -> Scope {
+        match self.read_scope {
+            Some(scope) if method == Method::GET || method == Method::HEAD => scope,
+            _ => self.scope,
+        }
+    }
+}
+
+/// Étape de contrôle d'accès portée par [`AuthMiddleware`]
+#[derive(Debug, Clone, Copy)]
+enum Guard {
+    Authenticate,
+    RequireScope(RequireScope),
+}
+
+pub struct AuthMiddleware<S> {
+    service: Rc<S>,
+    guard: Guard,
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Authenticate
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Transform = AuthMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AuthMiddleware {
+            service: Rc::new(service),
+            guard: Guard::Authenticate,
+        }))
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RequireScope
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Transform = AuthMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AuthMiddleware {
+            service: Rc::new(service),
+            guard: Guard::RequireScope(*self),
+        }))
+    }
+}
+
+impl<S, B> Service<ServiceRequest> for AuthMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let guard = self.guard;
+
+        Box::pin(async move {
+            let outcome = match guard {
+                Guard::Authenticate => match authenticate(req.request()).await {
+                    Ok(principal) => {
+                        req.extensions_mut().insert(principal);
+                        if req.headers().contains_key(SIGNATURE_HEADER) {
+                            verify_body_hash(&mut req).await
+                        } else {
+                            Ok(())
+                        }
+                    }
+                    Err(e) => Err(e),
+                },
+                Guard::RequireScope(required) => {
+                    let scope = required.scope_for(req.method());
+                    match req.extensions().get::<Principal>() {
+                        Some(principal) if principal.has_scope(scope) => Ok(()),
+                        Some(principal) => {
+                            tracing::warn!(
+                                key_id = ?principal.key_id,
+                                user_id = ?principal.user_id,
+                                scope = scope.as_str(),
+                                "Droit manquant"
+                            );
+                            Err(AuthError::MissingScope(scope))
+                        }
+                        None => Err(AuthError::MissingKey),
+                    }
+                }
+            };
+
+            match outcome {
+                Ok(()) => service
+                    .call(req)
+                    .await
+                    .map(ServiceResponse::map_into_left_body),
+                Err(e) => {
+                    if matches!(e, AuthError::BodyHashMismatch | AuthError::BodyTooLarge) {
+                        tracing::warn!(error = e.code(), "Requête signée refusée");
+                    }
+                    let response = e.response().map_into_right_body();
+                    Ok(req.into_response(response))
+                }
+            }
+        })
+    }
+}
+
+/// Identifie l'appelant d'une requête (clé statique, requête signée ou
+/// jeton d'accès)
+async fn authenticate(req: &HttpRequest) -> Result<Principal, AuthError> {
+    let config = req
+        .app_data::<web::Data<Arc<AppConfig>>>()
+        .expect("configuration enregistrée dans l'application");
+    let bearer = req
+        .headers()
+        .get(actix_web::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    let result = if req.headers().contains_key(SIGNATURE_HEADER) {
+        authenticate_signed(req, config).await
+    } else if let Some(token) = bearer {
+        jwt::verify_access_token(&config.security, token.trim()).map(|claims| Principal {
+            key_id: None,
+            device_id: None,
+            user_id: Some(claims.sub),
+            scopes: claims.role.scopes(),
+            recipients: None,
+        })
+    } else {
+        authenticate_key(req, config).await
+    };
+
+    if let Err(e) = &result {
+        match e {
+            AuthError::Storage(err) => {
+                tracing::error!(error = %err, "Erreur lecture des clés d'API")
+            }
+            // Un jeton expiré est renouvelé par le back-office : rien d'anormal ;
+            // un cache des nonces plein est signalé par le cache lui-même
+            AuthError::ExpiredToken | AuthError::NonceCacheFull => {}
+            _ => tracing::warn!(
+                remote_addr = ?req.connection_info().peer_addr(),
+                error = e.code(),
+                "Tentative d'accès avec clé API invalide"
+            ),
+        }
+    }
+    result
+}
+
+async fn authenticate_key(req: &HttpRequest, config: &AppConfig) -> Result<Principal, AuthError> {
     let api_key = req
         .headers()
         .get("X-API-Key")
         .and_then(|v| v.to_str().ok())
         .unwrap_or("");
+    if api_key.is_empty() {
+        return Err(AuthError::MissingKey);
+    }
+
+    if keys_match(api_key, &config.security.api_key) {
+        return Ok(Principal {
+            key_id: None,
+            device_id: None,
+            user_id: None,
+            scopes: Scope::ALL.to_vec(),
+            recipients: None,
+        });
+    }
+
+    let Some(keys) = req.app_data::<web::Data<ApiKeyRepository>>() else {
+        return Err(AuthError::InvalidKey);
+    };
+    let key = keys
+        .authenticate(api_key)
+        .await?
+        .ok_or(AuthError::InvalidKey)?;
+    Ok(Principal {
+        key_id: Some(key.id),
+        device_id: key.device_id,
+        user_id: None,
+        scopes: key.scopes,
+        recipients: key.recipients,
+    })
+}
+
+/// Authentifie une requête signée
+///
+/// L'empreinte du corps est contrôlée ensuite par [`verify_body_hash`] ; le
+/// nonce n'est retenu qu'une fois la signature vérifiée, pour qu'un tiers ne
+/// puisse pas remplir le cache.
+async fn authenticate_signed(
+    req: &HttpRequest,
+    config: &AppConfig,
+) -> Result<Principal, AuthError> {
+    let header = |name: &'static str| {
+        req.headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .filter(|v| !v.is_empty())
+            .ok_or(AuthError::IncompleteSignature(name))
+    };
+    let key_id = header(KEY_ID_HEADER)?;
+    let nonce = header(NONCE_HEADER)?;
+    let content_sha256 = header(CONTENT_SHA256_HEADER)?;
+    let signature = hex::decode(header(SIGNATURE_HEADER)?)
+        .map_err(|_| AuthError::IncompleteSignature(SIGNATURE_HEADER))?;
+    let timestamp: i64 = header(TIMESTAMP_HEADER)?
+        .parse()
+        .map_err(|_| AuthError::IncompleteSignature(TIMESTAMP_HEADER))?;
+    if nonce.len() > 128 {
+        return Err(AuthError::IncompleteSignature(NONCE_HEADER));
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    // Écart sans débordement : l'horodatage reçu n'est pas encore authentifié
+    if now.abs_diff(timestamp) > config.security.signature_max_skew.as_secs() {
+        return Err(AuthError::StaleTimestamp);
+    }
+
+    let (Some(keys), Some(nonces)) = (
+        req.app_data::<web::Data<ApiKeyRepository>>(),
+        req.app_data::<web::Data<NonceCache>>(),
+    ) else {
+        return Err(AuthError::InvalidSignature);
+    };
+    let (key, signing_secret) = keys
+        .signing_key(key_id)
+        .await?
+        .ok_or(AuthError::InvalidSignature)?;
+
+    let path = match req.query_string() {
+        "" => req.path().to_string(),
+        query => format!("{}?{}", req.path(), query),
+    };
+    let canonical = format!(
+        "{}\n{}\n{}\n{}\n{}",
+        req.method(),
+        path,
+        timestamp,
+        nonce,
+        content_sha256.to_ascii_lowercase()
+    );
+    let mut mac = Hmac::<Sha256>::new_from_slice(signing_secret.as_bytes())
+        .expect("HMAC accepte toute taille de clé");
+    mac.update(canonical.as_bytes());
+    mac.verify_slice(&signature)
+        .map_err(|_| AuthError::InvalidSignature)?;
+
+    nonces.insert(&key.id, nonce, now)?;
+    Ok(Principal {
+        key_id: Some(key.id),
+        device_id: key.device_id,
+        user_id: None,
+        scopes: key.scopes,
+        recipients: key.recipients,
+    })
+}
+
+/// Vérifie que le corps d'une requête signée correspond à `X-Content-SHA256`
+///
+/// Appelé une fois la signature vérifiée : le corps est lu en entier puis
+/// rendu au handler. La signature, calculée sur l'empreinte déclarée, couvre
+/// ainsi le corps réellement reçu.
+async fn verify_body_hash(req: &mut ServiceRequest) -> Result<(), AuthError> {
+    let limit = req
+        .app_data::<web::Data<Arc<AppConfig>>>()
+        .map(|config| config.upload.max_total_bytes)
+        .unwrap_or_default()
+        + SIGNED_BODY_OVERHEAD;
+    let declared = req
+        .headers()
+        .get(CONTENT_SHA256_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| hex::decode(v).ok())
+        .ok_or(AuthError::IncompleteSignature(CONTENT_SHA256_HEADER))?;
+
+    let mut payload = req.take_payload();
+    let mut body = web::BytesMut::new();
+    while let Some(chunk) = payload.next().await {
+        // Un corps illisible ne peut pas correspondre à son empreinte
+        let chunk = chunk.map_err(|_| AuthError::BodyHashMismatch)?;
+        if body.len() + chunk.len() > limit {
+            return Err(AuthError::BodyTooLarge);
+        }
+        body.extend_from_slice(&chunk);
+    }
+    if declared[..] != Sha256::digest(&body)[..] {
+        return Err(AuthError::BodyHashMismatch);
+    }
+
+    let body = body.freeze();
+    let stream: Pin<Box<dyn Stream<Item = Result<web::Bytes, PayloadError>>>> =
+        Box::pin(futures_util::stream::once(async move { Ok(body) }));
+    req.set_payload(Payload::from(stream));
+    Ok(())
+}
+
+/// Nonces déjà vus, pour refuser les requêtes rejouées
+///
+/// Un nonce est retenu deux fois la fenêtre d'horodatage : au-delà, la
+/// requête qui le porte est de toute façon refusée comme trop ancienne. Le
+/// cache est borné : tant qu'il est plein de nonces encore retenus, les
+/// nouvelles requêtes signées sont refusées plutôt que d'en oublier un.
+pub struct NonceCache {
+    capacity: usize,
+    retention_secs: i64,
+    inner: Mutex<NonceCacheInner>,
+}
+
+#[derive(Default)]
+struct NonceCacheInner {
+    seen: HashSet<String>,
+    /// (fin de rétention, nonce), par ordre de réception
+    order: VecDeque<(i64, String)>,
+}
+
+impl NonceCache {
+    pub fn new(capacity: usize, max_skew: std::time::Duration) -> Self {
+        Self {
+            capacity,
+            retention_secs: 2 * max_skew.as_secs() as i64,
+            inner: Mutex::new(NonceCacheInner::default()),
+        }
+    }
+
+    /// Retient un nonce ; refuse un nonce déjà utilisé par la clé, ou tout
+    /// nouveau nonce quand le cache est plein
+    pub fn insert(&self, key_id: &str, nonce: &str, now: i64) -> Result<(), AuthError> {
+        let entry = format!("{}:{}", key_id, nonce);
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
 
-    if api_key == config.security.api_key {
+        while inner.order.front().is_some_and(|(until, _)| *until < now) {
+            if let Some((_, old)) = inner.order.pop_front() {
+                inner.seen.remove(&old);
+            }
+        }
+        if inner.seen.contains(&entry) {
+            return Err(AuthError::ReplayedNonce);
+        }
+        if inner.order.len() >= self.capacity {
+            tracing::warn!(
+                capacity = self.capacity,
+                "Cache des nonces plein, requête refusée"
+            );
+            return Err(AuthError::NonceCacheFull);
+        }
+        inner.seen.insert(entry.clone());
+        inner.order.push_back((now + self.retention_secs, entry));
         Ok(())
-    } else {
-        tracing::warn!(
-            remote_addr = ?req.connection_info().peer_addr(),
-            "Tentative d'accès avec clé API invalide"
+    }
+}
+
+/// Comparaison en temps constant
+///
+/// Les empreintes ont une longueur fixe : la durée ne révèle ni la longueur
+/// de la clé attendue ni le nombre de caractères corrects.
+fn keys_match(presented: &str, expected: &str) -> bool {
+    Sha256::digest(presented.as_bytes())
+        .ct_eq(&Sha256::digest(expected.as_bytes()))
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{ApiKey, ApiKeySecrets, IssueApiKeyRequest};
+    use crate::storage::Database;
+    use actix_web::test::{self as actix_test, TestRequest};
+    use actix_web::App;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_keys_match() {
+        assert!(keys_match("secret", "secret"));
+        assert!(!keys_match("secreT", "secret"));
+        assert!(!keys_match("secret-plus-longue", "secret"));
+    }
+
+    #[test]
+    fn test_full_nonce_cache_refuses_instead_of_forgetting() {
+        let cache = NonceCache::new(2, std::time::Duration::from_secs(300));
+        assert!(cache.insert("k", "a", 1000).is_ok());
+        assert!(cache.insert("k", "b", 1000).is_ok());
+
+        // Plein de nonces encore retenus : aucun n'est oublié
+        assert!(matches!(
+            cache.insert("k", "c", 1001),
+            Err(AuthError::NonceCacheFull)
+        ));
+        assert!(matches!(
+            cache.insert("k", "a", 1001),
+            Err(AuthError::ReplayedNonce)
+        ));
+
+        // Une fois leur rétention écoulée, la place est libérée
+        assert!(cache.insert("k", "c", 1601).is_ok());
+    }
+
+    async fn probe(principal: Principal) -> HttpResponse {
+        HttpResponse::Ok().body(principal.device_id.unwrap_or_default())
+    }
+
+    /// Une route publique et deux groupes authentifiés, comme dans `main.rs`
+    fn routes(cfg: &mut web::ServiceConfig) {
+        cfg.route("/health", web::get().to(HttpResponse::Ok))
+            .service(
+                web::scope("/api")
+                    .wrap(Authenticate)
+                    .service(
+                        web::resource("/sync")
+                            .wrap(RequireScope::new(Scope::Sync))
+                            .route(web::post().to(probe)),
+                    )
+                    .service(
+                        web::resource("/contacts")
+                            .wrap(
+                                RequireScope::new(Scope::ContactsWrite).reads(Scope::ContactsRead),
+                            )
+                            .route(web::get().to(probe))
+                            .route(web::post().to(probe)),
+                    ),
+            );
+    }
+
+    async fn issue(keys: &ApiKeyRepository, scopes: Vec<Scope>) -> (ApiKey, ApiKeySecrets) {
+        keys.issue(IssueApiKeyRequest {
+            label: "Tablette".to_string(),
+            device_id: Some("tablet-1".to_string()),
+            scopes,
+            expires_in_days: None,
+            recipients: None,
+        })
+        .await
+        .unwrap()
+    }
+
+    #[actix_web::test]
+    async fn test_scoped_routes_require_principal_and_scope() {
+        let keys = ApiKeyRepository::new(Database::open_in_memory().unwrap());
+        let (_, secrets) = issue(&keys, vec![Scope::ContactsRead]).await;
+        let app = actix_test::init_service(
+            App::new()
+                .app_data(web::Data::new(Arc::new(AppConfig::for_tests())))
+                .app_data(web::Data::new(keys))
+                .configure(routes),
+        )
+        .await;
+        let call = |method: Method, uri: &str, key: Option<&str>| {
+            let mut req = TestRequest::default().method(method).uri(uri);
+            if let Some(key) = key {
+                req = req.insert_header(("X-API-Key", key.to_string()));
+            }
+            req.to_request()
+        };
+
+        let health = actix_test::call_service(&app, call(Method::GET, "/health", None)).await;
+        assert_eq!(health.status(), 200);
+
+        let missing = actix_test::call_service(&app, call(Method::POST, "/api/sync", None)).await;
+        assert_eq!(missing.status(), 401);
+        let body: serde_json::Value = actix_test::read_body_json(missing).await;
+        assert_eq!(body["success"], false);
+        assert_eq!(body["error"], "missing_api_key");
+
+        let admin = call(Method::POST, "/api/sync", Some("secret"));
+        assert_eq!(actix_test::call_service(&app, admin).await.status(), 200);
+
+        let read = call(Method::GET, "/api/contacts", Some(&secrets.key));
+        let response = actix_test::call_service(&app, read).await;
+        assert_eq!(actix_test::read_body(response).await, "tablet-1");
+
+        let write = call(Method::POST, "/api/contacts", Some(&secrets.key));
+        let response = actix_test::call_service(&app, write).await;
+        assert_eq!(response.status(), 403);
+        let body: serde_json::Value = actix_test::read_body_json(response).await;
+        assert_eq!(body["scope"], "contacts:write");
+    }
+
+    #[actix_web::test]
+    async fn test_signed_request_rejects_replay_tampering_and_stale_timestamp() {
+        let config = AppConfig::for_tests();
+        let keys = ApiKeyRepository::new(Database::open_in_memory().unwrap());
+        let (key, secrets) = issue(&keys, vec![Scope::Sync]).await;
+        let nonces = NonceCache::new(10, config.security.signature_max_skew);
+        let app = actix_test::init_service(
+            App::new()
+                .app_data(web::Data::new(Arc::new(config)))
+                .app_data(web::Data::new(keys))
+                .app_data(web::Data::new(nonces))
+                .configure(routes),
+        )
+        .await;
+
+        let signed = |body: &'static str, sent: &'static str, timestamp: i64, nonce: &str| {
+            let content_sha256 = hex::encode(Sha256::digest(body));
+            let mut mac =
+                Hmac::<Sha256>::new_from_slice(secrets.signing_secret.as_bytes()).unwrap();
+            mac.update(
+                format!(
+                    "POST\n/api/sync\n{}\n{}\n{}",
+                    timestamp, nonce, content_sha256
+                )
+                .as_bytes(),
+            );
+            TestRequest::post()
+                .uri("/api/sync")
+                .insert_header((KEY_ID_HEADER, key.id.as_str()))
+                .insert_header((TIMESTAMP_HEADER, timestamp.to_string()))
+                .insert_header((NONCE_HEADER, nonce.to_string()))
+                .insert_header((CONTENT_SHA256_HEADER, content_sha256))
+                .insert_header((SIGNATURE_HEADER, hex::encode(mac.finalize().into_bytes())))
+                .set_payload(sent)
+                .to_request()
+        };
+        let now = chrono::Utc::now().timestamp();
+
+        let response = actix_test::call_service(&app, signed("{}", "{}", now, "n-1")).await;
+        assert_eq!(response.status(), 200);
+        assert_eq!(actix_test::read_body(response).await, "tablet-1");
+
+        let replayed = actix_test::call_service(&app, signed("{}", "{}", now, "n-1")).await;
+        assert_eq!(replayed.status(), 401);
+        let body: serde_json::Value = actix_test::read_body_json(replayed).await;
+        assert_eq!(body["error"], "replayed_nonce");
+
+        let tampered = actix_test::call_service(&app, signed("{}", "{\"a\":1}", now, "n-2")).await;
+        let body: serde_json::Value = actix_test::read_body_json(tampered).await;
+        assert_eq!(body["error"], "body_hash_mismatch");
+
+        // Signature contrôlée avant le corps : un inconnu n'obtient jamais sa lecture
+        let mut forged = signed("{}", "{\"a\":1}", now, "n-4");
+        forged.headers_mut().insert(
+            actix_web::http::header::HeaderName::from_static("x-signature"),
+            actix_web::http::header::HeaderValue::from_static("00"),
         );
-        Err(HttpResponse::Unauthorized().json(ExportFichesResponse::error("Clé API invalide")))
+        let forged = actix_test::call_service(&app, forged).await;
+        let body: serde_json::Value = actix_test::read_body_json(forged).await;
+        assert_eq!(body["error"], "invalid_signature");
+
+        for timestamp in [now - 3600, i64::MIN, i64::MAX] {
+            let stale = actix_test::call_service(&app, signed("{}", "{}", timestamp, "n-3")).await;
+            let body: serde_json::Value = actix_test::read_body_json(stale).await;
+            assert_eq!(body["error"], "stale_timestamp");
+        }
     }
 }
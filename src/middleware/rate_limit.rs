@@ -0,0 +1,487 @@
+//! Limites de débit (seaux à jetons en mémoire).
+//!
+//! Chaque groupe de routes a ses propres limites, par clé (ou utilisateur),
+//! par adresse IP et, pour les envois d'email, par domaine destinataire. Une
+//! requête n'est acceptée que si tous ses seaux ont un jeton ; elle n'en
+//! consomme qu'à cette condition. Au-delà : 429 avec `Retry-After`.
+//!
+//! La limite par adresse IP est contrôlée une seule fois par groupe de
+//! routes, par le premier [`RateLimit`] traversé : déclaré aussi autour de
+//! [`super::Authenticate`], il compte les requêtes refusées (401).
+
+use actix_web::body::EitherBody;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{HeaderValue, RETRY_AFTER};
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse};
+use futures_util::future::LocalBoxFuture;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::future::{ready, Ready};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::Principal;
+use crate::config::{AppConfig, Quota, RateRoute};
+
+/// Au-delà, les seaux pleins (inactifs) sont oubliés, puis les moins
+/// récemment utilisés
+const MAX_BUCKETS: usize = 100_000;
+
+/// Critère de limitation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LimitKind {
+    Key,
+    Ip,
+    Domain,
+}
+
+/// Requête refusée : un seau est vide
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimited {
+    pub kind: LimitKind,
+    pub retry_after: Duration,
+}
+
+#[derive(Serialize)]
+struct RateLimitedResponse {
+    success: bool,
+    message: String,
+    error: &'static str,
+    limit: LimitKind,
+    retry_after: u64,
+}
+
+impl RateLimited {
+    /// Délai annoncé, arrondi à la seconde supérieure
+    fn retry_after_secs(&self) -> u64 {
+        self.retry_after.as_secs_f64().ceil().max(1.0) as u64
+    }
+
+    /// Réponse 429 avec `Retry-After`
+    pub fn response(&self) -> HttpResponse {
+        let secs = self.retry_after_secs();
+        HttpResponse::TooManyRequests()
+            .insert_header((RETRY_AFTER, HeaderValue::from(secs)))
+            .json(RateLimitedResponse {
+                success: false,
+                message: format!("Trop de requêtes, réessayer dans {} s", secs),
+                error: "rate_limited",
+                limit: self.kind,
+                retry_after: secs,
+            })
+    }
+}
+
+/// Compteurs d'un critère d'un groupe de routes
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RateLimitStats {
+    pub allowed: u64,
+    pub limited: u64,
+    /// Seaux en mémoire (clés, adresses ou domaines suivis)
+    pub tracked: usize,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    quota: Quota,
+}
+
+impl Bucket {
+    fn new(quota: Quota, now: Instant) -> Self {
+        Self {
+            tokens: f64::from(quota.requests),
+            updated: now,
+            quota,
+        }
+    }
+
+    /// Jetons rendus par seconde
+    fn rate(&self) -> f64 {
+        f64::from(self.quota.requests) / self.quota.period.as_secs_f64()
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate()).min(f64::from(self.quota.requests));
+        self.updated = now;
+    }
+
+    /// Attente avant le prochain jeton, nulle s'il en reste un
+    fn wait(&self) -> Duration {
+        if self.tokens >= 1.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((1.0 - self.tokens) / self.rate())
+        }
+    }
+
+    /// Seau qui serait plein à `now`, sans le remplir : `updated` reste la
+    /// date de sa dernière utilisation
+    fn is_full_at(&self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens + elapsed * self.rate() >= f64::from(self.quota.requests)
+    }
+}
+
+type BucketKey = (RateRoute, LimitKind, String);
+
+#[derive(Default)]
+struct LimiterInner {
+    buckets: HashMap<BucketKey, Bucket>,
+    stats: BTreeMap<(RateRoute, LimitKind), RateLimitStats>,
+}
+
+impl LimiterInner {
+    /// Ramène le nombre de seaux sous `capacity`
+    ///
+    /// Les seaux pleins ne limitent personne et partent d'abord ; si cela ne
+    /// suffit pas (adresses forgées en nombre), les moins récemment utilisés
+    /// partent ensuite, jusqu'à libérer un dixième de la capacité.
+    fn evict(&mut self, capacity: usize, now: Instant) {
+        if self.buckets.len() < capacity {
+            return;
+        }
+        self.buckets.retain(|_, bucket| !bucket.is_full_at(now));
+        let target = capacity - capacity / 10;
+        if self.buckets.len() <= target {
+            return;
+        }
+
+        let excess = self.buckets.len() - target;
+        let mut by_age: Vec<(Instant, BucketKey)> =
+            self.buckets.iter().map(|(key, bucket)| (bucket.updated, key.clone())).collect();
+        by_age.sort_unstable_by_key(|(updated, _)| *updated);
+        for (_, key) in by_age.into_iter().take(excess) {
+            self.buckets.remove(&key);
+        }
+    }
+}
+
+/// Seaux de toutes les limites, partagés par les workers
+#[derive(Default)]
+pub struct RateLimiter {
+    inner: Mutex<LimiterInner>,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Prend un jeton dans chaque seau, ou aucun si l'un d'eux est vide
+    pub fn acquire(
+        &self,
+        route: RateRoute,
+        checks: &[(LimitKind, &str, Quota)],
+    ) -> Result<(), RateLimited> {
+        self.acquire_at(route, checks, Instant::now())
+    }
+
+    fn acquire_at(
+        &self,
+        route: RateRoute,
+        checks: &[(LimitKind, &str, Quota)],
+        now: Instant,
+    ) -> Result<(), RateLimited> {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.evict(MAX_BUCKETS, now);
+
+        let mut refused: Option<RateLimited> = None;
+        for (kind, id, quota) in checks {
+            let bucket = inner
+                .buckets
+                .entry((route, *kind, id.to_string()))
+                .or_insert_with(|| Bucket::new(*quota, now));
+            // Une limite modifiée s'applique dès la requête suivante
+            bucket.quota = *quota;
+            bucket.refill(now);
+            let wait = bucket.wait();
+            if !wait.is_zero() && refused.is_none_or(|r| wait > r.retry_after) {
+                refused = Some(RateLimited {
+                    kind: *kind,
+                    retry_after: wait,
+                });
+            }
+        }
+
+        if let Some(refused) = refused {
+            inner.stats.entry((route, refused.kind)).or_default().limited += 1;
+            return Err(refused);
+        }
+        for (kind, id, _) in checks {
+            if let Some(bucket) = inner.buckets.get_mut(&(route, *kind, id.to_string())) {
+                bucket.tokens -= 1.0;
+            }
+            inner.stats.entry((route, *kind)).or_default().allowed += 1;
+        }
+        Ok(())
+    }
+
+    /// Compteurs par groupe de routes puis par critère
+    pub fn stats(&self) -> BTreeMap<RateRoute, BTreeMap<LimitKind, RateLimitStats>> {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let mut stats: BTreeMap<RateRoute, BTreeMap<LimitKind, RateLimitStats>> = BTreeMap::new();
+        for ((route, kind), counters) in &inner.stats {
+            stats.entry(*route).or_default().insert(*kind, counters.clone());
+        }
+        for (route, kind, _) in inner.buckets.keys() {
+            stats.entry(*route).or_default().entry(*kind).or_default().tracked += 1;
+        }
+        stats
+    }
+}
+
+/// Limite de débit d'un groupe de routes
+///
+/// Déclarée à l'intérieur de [`super::Authenticate`], elle limite par clé ;
+/// autour, ou sur des routes publiques, seulement par adresse IP. Sans
+/// [`RateLimiter`] enregistré dans l'application, les requêtes passent.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimit(pub RateRoute);
+
+pub struct RateLimitMiddleware<S> {
+    service: Rc<S>,
+    route: RateRoute,
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Transform = RateLimitMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimitMiddleware {
+            service: Rc::new(service),
+            route: self.0,
+        }))
+    }
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let outcome = check_caller(req.request(), self.route);
+
+        Box::pin(async move {
+            match outcome {
+                Ok(()) => service.call(req).await.map(ServiceResponse::map_into_left_body),
+                Err(limited) => {
+                    let response = limited.response().map_into_right_body();
+                    Ok(req.into_response(response))
+                }
+            }
+        })
+    }
+}
+
+/// Groupes de routes dont la limite par adresse IP a déjà été contrôlée
+struct IpChecked(Vec<RateRoute>);
+
+/// Limites par clé et par adresse IP d'une requête
+fn check_caller(req: &HttpRequest, route: RateRoute) -> Result<(), RateLimited> {
+    let (Some(limiter), Some(config)) = (
+        req.app_data::<web::Data<RateLimiter>>(),
+        req.app_data::<web::Data<Arc<AppConfig>>>(),
+    ) else {
+        return Ok(());
+    };
+    let limits = config.rate_limit.limits(route);
+    let caller = req.extensions().get::<Principal>().map(caller_id);
+    let ip = client_ip(req, config.rate_limit.trust_forwarded_for);
+
+    let ip_checked = req
+        .extensions()
+        .get::<IpChecked>()
+        .is_some_and(|checked| checked.0.contains(&route));
+
+    let mut checks = Vec::with_capacity(2);
+    if let (Some(quota), Some(caller)) = (limits.per_key, caller.as_deref()) {
+        checks.push((LimitKind::Key, caller, quota));
+    }
+    if let (Some(quota), false) = (limits.per_ip, ip_checked) {
+        checks.push((LimitKind::Ip, ip.as_str(), quota));
+        req.extensions_mut().get_or_insert(IpChecked(Vec::new())).0.push(route);
+    }
+    limiter.acquire(route, &checks).inspect_err(|limited| {
+        tracing::warn!(
+            route = route.as_str(),
+            limit = ?limited.kind,
+            caller = ?caller,
+            ip = %ip,
+            "Limite de débit atteinte"
+        );
+    })
+}
+
+/// Limite par domaine de l'adresse destinataire, avant un envoi d'email
+///
+/// À appeler depuis le handler, une fois le corps lu ; retourne la réponse
+/// 429 à renvoyer telle quelle.
+pub fn check_recipient(
+    req: &HttpRequest,
+    route: RateRoute,
+    recipient: &str,
+) -> Result<(), HttpResponse> {
+    let (Some(limiter), Some(config)) = (
+        req.app_data::<web::Data<RateLimiter>>(),
+        req.app_data::<web::Data<Arc<AppConfig>>>(),
+    ) else {
+        return Ok(());
+    };
+    let Some(quota) = config.rate_limit.limits(route).per_domain else {
+        return Ok(());
+    };
+    let domain = recipient
+        .rsplit_once('@')
+        .map_or(recipient, |(_, domain)| domain)
+        .trim()
+        .to_lowercase();
+
+    limiter
+        .acquire(route, &[(LimitKind::Domain, domain.as_str(), quota)])
+        .map_err(|limited| {
+            tracing::warn!(route = route.as_str(), domain = %domain, "Limite de débit atteinte");
+            limited.response()
+        })
+}
+
+/// Identifiant de l'appelant pour la limite par clé
+fn caller_id(principal: &Principal) -> String {
+    match (&principal.key_id, &principal.user_id) {
+        (Some(key_id), _) => key_id.clone(),
+        (None, Some(user_id)) => format!("user:{}", user_id),
+        (None, None) => "admin".to_string(),
+    }
+}
+
+/// Adresse IP du client
+///
+/// Derrière un proxy de confiance, la dernière entrée de `X-Forwarded-For`
+/// est celle qu'il a ajoutée ; les précédentes viennent du client et peuvent
+/// être forgées.
+fn client_ip(req: &HttpRequest, trust_forwarded_for: bool) -> String {
+    let forwarded = trust_forwarded_for
+        .then(|| req.headers().get("X-Forwarded-For"))
+        .flatten()
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.rsplit(',').next())
+        .map(str::trim)
+        .filter(|v| !v.is_empty());
+    match forwarded {
+        Some(ip) => ip.to_string(),
+        None => req
+            .peer_addr()
+            .map_or_else(|| "inconnue".to_string(), |addr| addr.ip().to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_refills_and_refuses_without_consuming() {
+        let limiter = RateLimiter::new();
+        let start = Instant::now();
+        let key = Quota::new(2, Duration::from_secs(60));
+        let ip = Quota::new(1, Duration::from_secs(10));
+        let checks = [(LimitKind::Key, "k1", key), (LimitKind::Ip, "10.0.0.1", ip)];
+
+        assert!(limiter.acquire_at(RateRoute::Api, &checks, start).is_ok());
+        let refused = limiter.acquire_at(RateRoute::Api, &checks, start).unwrap_err();
+        assert_eq!(refused.kind, LimitKind::Ip);
+        assert_eq!(refused.retry_after_secs(), 10);
+
+        // Le refus par IP n'a pas consommé de jeton de la clé
+        let later = start + Duration::from_secs(10);
+        assert!(limiter.acquire_at(RateRoute::Api, &checks, later).is_ok());
+        let refused = limiter.acquire_at(RateRoute::Api, &checks, later).unwrap_err();
+        assert_eq!(refused.kind, LimitKind::Key);
+
+        // Les groupes de routes ont des seaux distincts
+        assert!(limiter.acquire_at(RateRoute::History, &checks, later).is_ok());
+
+        let stats = limiter.stats();
+        let api = &stats[&RateRoute::Api];
+        assert_eq!((api[&LimitKind::Key].allowed, api[&LimitKind::Key].limited), (2, 1));
+        assert_eq!((api[&LimitKind::Ip].limited, api[&LimitKind::Ip].tracked), (1, 1));
+    }
+
+    #[test]
+    fn test_eviction_drops_least_recently_used_buckets() {
+        let mut inner = LimiterInner::default();
+        let start = Instant::now();
+        let quota = Quota::new(1, Duration::from_secs(3600));
+        for n in 0..10u64 {
+            let mut bucket = Bucket::new(quota, start + Duration::from_secs(n));
+            bucket.tokens = 0.0;
+            inner.buckets.insert((RateRoute::Auth, LimitKind::Ip, n.to_string()), bucket);
+        }
+
+        // Aucun seau n'est plein : les plus anciens partent
+        inner.evict(10, start + Duration::from_secs(10));
+        let mut kept: Vec<u64> =
+            inner.buckets.keys().map(|(_, _, ip)| ip.parse().unwrap()).collect();
+        kept.sort();
+        assert_eq!(kept, vec![1, 2, 3, 4, 5, 6, 7, 8, 9]);
+    }
+
+    #[actix_web::test]
+    async fn test_rejected_keys_are_limited_per_ip() {
+        use crate::config::RouteLimits;
+        use crate::handlers::api_routes;
+        use actix_web::{test, App};
+
+        let mut config = AppConfig::for_tests();
+        config.rate_limit.api = RouteLimits {
+            per_ip: Some(Quota::new(2, Duration::from_secs(60))),
+            ..RouteLimits::default()
+        };
+        let audit = web::Data::new(crate::storage::AuditRepository::new(
+            crate::storage::Database::open_in_memory().unwrap(),
+        ));
+        let limiter = web::Data::new(RateLimiter::new());
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(Arc::new(config)))
+                .app_data(limiter.clone())
+                .app_data(audit)
+                .configure(api_routes),
+        )
+        .await;
+        let guess = |key: &str| {
+            test::TestRequest::get()
+                .uri("/api/audit")
+                .insert_header(("X-API-Key", key.to_string()))
+                .to_request()
+        };
+
+        // Une requête authentifiée ne compte qu'une fois par adresse
+        assert_eq!(test::call_service(&app, guess("secret")).await.status(), 200);
+        assert_eq!(test::call_service(&app, guess("essai-1")).await.status(), 401);
+        let resp = test::call_service(&app, guess("essai-2")).await;
+        assert_eq!(resp.status(), 429);
+
+        let api = &limiter.stats()[&RateRoute::Api];
+        assert_eq!((api[&LimitKind::Ip].allowed, api[&LimitKind::Ip].limited), (2, 1));
+    }
+}
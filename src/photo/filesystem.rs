@@ -0,0 +1,82 @@
+//! Stockage des photos dans un répertoire local (un fichier par photo).
+
+use async_trait::async_trait;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use super::store::{validate_key, PhotoStore, StoreError, StoreResult};
+
+/// Photos enregistrées sous un répertoire racine
+pub struct FilesystemStore {
+    root: PathBuf,
+}
+
+impl FilesystemStore {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    /// Chemin du fichier d'une photo ; la clé est validée pour ne jamais
+    /// sortir du répertoire racine
+    fn path(&self, key: &str) -> StoreResult<PathBuf> {
+        validate_key(key)?;
+        Ok(self.root.join(key))
+    }
+}
+
+#[async_trait]
+impl PhotoStore for FilesystemStore {
+    async fn put(&self, key: &str, data: Vec<u8>) -> StoreResult<()> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        // Écriture dans un fichier voisin puis renommage : jamais de photo tronquée
+        let partial = path.with_extension("jpg.part");
+        tokio::fs::write(&partial, data).await?;
+        tokio::fs::rename(&partial, &path).await?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> StoreResult<Vec<u8>> {
+        match tokio::fs::read(self.path(key)?).await {
+            Ok(data) => Ok(data),
+            Err(e) if e.kind() == ErrorKind::NotFound => Err(StoreError::NotFound(key.to_string())),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> StoreResult<()> {
+        match tokio::fs::remove_file(self.path(key)?).await {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn backend_name(&self) -> &'static str {
+        "filesystem"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_put_get_delete_and_reject_foreign_keys() {
+        let root = tempfile::tempdir().unwrap();
+        let store = FilesystemStore::new(root.path().to_path_buf());
+        let key = "photos/7f2c3c0e-8d4a-4b51-9a57-2f0d2c1b6e11.jpg";
+
+        store.put(key, b"jpeg".to_vec()).await.unwrap();
+        assert_eq!(store.get(key).await.unwrap(), b"jpeg");
+
+        store.delete(key).await.unwrap();
+        assert!(matches!(store.get(key).await, Err(StoreError::NotFound(_))));
+        store.delete(key).await.unwrap();
+
+        for key in ["../secret.jpg", "photos/../../etc/passwd", "photos/a.jpg"] {
+            assert!(matches!(store.get(key).await, Err(StoreError::InvalidKey(_))));
+        }
+    }
+}
@@ -0,0 +1,465 @@
+//! Traitement des photos de cartes de visite.
+//!
+//! Les photos reçues en base64 sont décodées, leur format réel est détecté
+//! d'après leur contenu, puis elles sont redressées selon l'orientation EXIF,
+//! réduites à une dimension maximale et recompressées en JPEG. Les données
+//! qui ne sont pas une image sont refusées.
+//!
+//! La recompression ne conserve aucune métadonnée (EXIF, XMP, coordonnées
+//! GPS, modèle de l'appareil) : seules les photos ainsi traitées sont
+//! stockées et jointes aux emails. Les photos contenant une position sont
+//! comptées dans les compteurs d'audit.
+//!
+//! Les photos traitées restent dans la base, ou sont déposées dans un
+//! stockage externe (répertoire local, bucket S3) selon `PHOTO_STORE`.
+
+mod filesystem;
+mod s3;
+mod store;
+
+pub use filesystem::FilesystemStore;
+pub use s3::S3Store;
+pub use store::{PhotoStorage, PhotoStore, StoreError};
+
+use crate::config::{PhotoConfig, PhotoStoreBackend, PhotoStoreConfig};
+use crate::domain::ContactFiche;
+use crate::storage::AuditRepository;
+use base64::Engine;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Cursor, Seek};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Type de contenu des photos après traitement
+pub const PHOTO_CONTENT_TYPE: &str = "image/jpeg";
+
+/// Compteurs d'audit alimentés par le traitement des photos
+pub const AUDIT_PHOTOS_PROCESSED: &str = "photos_processed";
+pub const AUDIT_PHOTOS_WITH_LOCATION: &str = "photos_with_location";
+
+/// Construit l'accès au stockage des photos configuré
+pub fn build_storage(config: &PhotoStoreConfig) -> PhotoStorage {
+    let store: Option<Arc<dyn PhotoStore>> = match &config.backend {
+        PhotoStoreBackend::Database => None,
+        PhotoStoreBackend::Filesystem { root } => {
+            Some(Arc::new(FilesystemStore::new(root.clone())))
+        }
+        PhotoStoreBackend::S3(s3) => Some(Arc::new(S3Store::new(s3.clone()))),
+    };
+    PhotoStorage::new(
+        store,
+        config.link_ttl,
+        config.public_base_url.clone(),
+        &config.link_secret,
+    )
+}
+
+/// Formats d'image acceptés en entrée
+const ACCEPTED_FORMATS: [ImageFormat; 3] =
+    [ImageFormat::Jpeg, ImageFormat::Png, ImageFormat::WebP];
+
+/// Résultat d'un traitement de photo
+pub type PhotoResult<T> = Result<T, PhotoError>;
+
+/// Erreurs possibles lors du traitement d'une photo
+#[derive(Debug, thiserror::Error)]
+pub enum PhotoError {
+    #[error("base64 invalide: {0}")]
+    Base64(#[from] base64::DecodeError),
+
+    #[error("le fichier n'est pas une image")]
+    NotAnImage,
+
+    #[error("format d'image non pris en charge: {0}")]
+    UnsupportedFormat(String),
+
+    #[error("image illisible: {0}")]
+    Decode(image::ImageError),
+
+    #[error("lecture de la photo impossible: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("recompression impossible: {0}")]
+    Encode(image::ImageError),
+
+    #[error("photo de {societe}: {source}")]
+    Fiche {
+        societe: String,
+        #[source]
+        source: Box<PhotoError>,
+    },
+
+    #[error("Erreur du traitement des photos: {0}")]
+    TaskFailed(String),
+}
+
+impl PhotoError {
+    /// Vrai si la photo envoyée est en cause (réponse 400)
+    pub fn is_invalid_photo(&self) -> bool {
+        match self {
+            Self::Fiche { source, .. } => source.is_invalid_photo(),
+            Self::Io(_) | Self::TaskFailed(_) => false,
+            _ => true,
+        }
+    }
+}
+
+/// Photo traitée
+pub struct ProcessedPhoto {
+    /// JPEG recompressé, sans métadonnées
+    pub data: Vec<u8>,
+    /// La photo d'origine contenait des coordonnées GPS
+    pub had_location: bool,
+}
+
+/// Traitement des photos reçues, avec suivi dans les compteurs d'audit
+#[derive(Clone)]
+pub struct PhotoProcessor {
+    config: PhotoConfig,
+    audit: AuditRepository,
+}
+
+impl PhotoProcessor {
+    pub fn new(config: PhotoConfig, audit: AuditRepository) -> Self {
+        Self { config, audit }
+    }
+
+    /// Traite, dans le pool bloquant, les photos des fiches contenues dans `value`
+    ///
+    /// `fiches` désigne les fiches à traiter (lot d'export, fiche seule,
+    /// modifications de synchronisation, ...).
+    pub async fn process_photos<T>(
+        &self,
+        mut value: T,
+        fiches: fn(&mut T) -> Vec<&mut ContactFiche>,
+    ) -> PhotoResult<T>
+    where
+        T: Send + 'static,
+    {
+        let config = self.config.clone();
+
+        let (value, processed, with_location) = tokio::task::spawn_blocking(move || {
+            let (mut processed, mut with_location) = (0, 0);
+            for fiche in fiches(&mut value) {
+                let had_location = process_fiche(fiche, &config).map_err(|e| PhotoError::Fiche {
+                    societe: fiche.societe.clone(),
+                    source: Box::new(e),
+                })?;
+                match had_location {
+                    Some(true) => (processed, with_location) = (processed + 1, with_location + 1),
+                    Some(false) => processed += 1,
+                    None => {}
+                }
+            }
+            Ok::<_, PhotoError>((value, processed, with_location))
+        })
+        .await
+        .map_err(|e| PhotoError::TaskFailed(e.to_string()))??;
+
+        self.record(processed, with_location).await;
+        Ok(value)
+    }
+
+    /// Traite les photos reçues en fichiers (envoi multipart) et les associe à
+    /// leurs fiches
+    ///
+    /// `uploads` associe la position d'une fiche au fichier temporaire de sa
+    /// photo, décodée directement depuis le fichier.
+    pub async fn process_uploads(
+        &self,
+        mut fiches: Vec<ContactFiche>,
+        uploads: Vec<(usize, File)>,
+    ) -> PhotoResult<Vec<ContactFiche>> {
+        let config = self.config.clone();
+
+        let (fiches, processed, with_location) = tokio::task::spawn_blocking(move || {
+            let (mut processed, mut with_location) = (0, 0);
+            for (index, file) in uploads {
+                let fiche = &mut fiches[index];
+                let photo = process_reader(BufReader::new(file), &config).map_err(|e| {
+                    PhotoError::Fiche {
+                        societe: fiche.societe.clone(),
+                        source: Box::new(e),
+                    }
+                })?;
+                processed += 1;
+                with_location += u64::from(photo.had_location);
+                set_photo(fiche, photo.data);
+            }
+            Ok::<_, PhotoError>((fiches, processed, with_location))
+        })
+        .await
+        .map_err(|e| PhotoError::TaskFailed(e.to_string()))??;
+
+        self.record(processed, with_location).await;
+        Ok(fiches)
+    }
+
+    /// Met à jour les compteurs d'audit après un traitement
+    async fn record(&self, processed: u64, with_location: u64) {
+        if with_location > 0 {
+            info!(with_location, "Coordonnées GPS retirées des photos reçues");
+        }
+        // Un compteur non mis à jour ne doit pas faire échouer la réception des fiches
+        let counters = vec![
+            (AUDIT_PHOTOS_PROCESSED, processed),
+            (AUDIT_PHOTOS_WITH_LOCATION, with_location),
+        ];
+        if let Err(e) = self.audit.increment(counters).await {
+            warn!(error = %e, "Mise à jour des compteurs d'audit impossible");
+        }
+    }
+}
+
+/// Remplace la photo d'une fiche par sa version traitée
+///
+/// Retourne `None` sans photo, sinon si la photo contenait une position.
+fn process_fiche(fiche: &mut ContactFiche, config: &PhotoConfig) -> PhotoResult<Option<bool>> {
+    let Some(data) = fiche.photo_base64.as_deref() else {
+        return Ok(None);
+    };
+    let data = base64::engine::general_purpose::STANDARD.decode(data.trim())?;
+    let photo = process(&data, config)?;
+
+    set_photo(fiche, photo.data);
+    Ok(Some(photo.had_location))
+}
+
+/// Remplace la photo d'une fiche par le JPEG traité
+fn set_photo(fiche: &mut ContactFiche, jpeg: Vec<u8>) {
+    fiche.photo_base64 = Some(base64::engine::general_purpose::STANDARD.encode(jpeg));
+    // Le nom fourni par l'appareil garde sa base mais prend l'extension du JPEG produit
+    if let Some(filename) = &fiche.photo_filename {
+        let stem = filename.rsplit_once('.').map_or(filename.as_str(), |(stem, _)| stem);
+        fiche.photo_filename = Some(format!("{}.jpg", stem));
+    }
+}
+
+/// Redresse, réduit et recompresse une image en JPEG sans ses métadonnées
+pub fn process(data: &[u8], config: &PhotoConfig) -> PhotoResult<ProcessedPhoto> {
+    process_reader(Cursor::new(data), config)
+}
+
+/// Comme [`process`], à partir d'un lecteur (fichier temporaire, ...)
+fn process_reader<R: BufRead + Seek>(
+    reader: R,
+    config: &PhotoConfig,
+) -> PhotoResult<ProcessedPhoto> {
+    let reader = ImageReader::new(reader).with_guessed_format()?;
+    let format = reader.format().ok_or(PhotoError::NotAnImage)?;
+    if !ACCEPTED_FORMATS.contains(&format) {
+        return Err(PhotoError::UnsupportedFormat(format!("{:?}", format)));
+    }
+
+    let mut decoder = reader.into_decoder().map_err(PhotoError::Decode)?;
+    // Des métadonnées illisibles n'empêchent pas d'utiliser l'image
+    let had_location = decoder.exif_metadata().ok().flatten().is_some_and(exif_has_location)
+        || decoder.xmp_metadata().ok().flatten().is_some_and(|xmp| xmp_has_location(&xmp));
+    let orientation = decoder.orientation().ok();
+    let mut image = DynamicImage::from_decoder(decoder).map_err(PhotoError::Decode)?;
+    if let Some(orientation) = orientation {
+        image.apply_orientation(orientation);
+    }
+
+    if image.width() > config.max_dimension || image.height() > config.max_dimension {
+        image = image.resize(config.max_dimension, config.max_dimension, FilterType::CatmullRom);
+    }
+
+    // Le JPEG n'a pas de transparence : le canal alpha éventuel est ignoré
+    let mut jpeg = Vec::new();
+    image
+        .to_rgb8()
+        .write_with_encoder(JpegEncoder::new_with_quality(&mut jpeg, config.jpeg_quality))
+        .map_err(PhotoError::Encode)?;
+    Ok(ProcessedPhoto {
+        data: jpeg,
+        had_location,
+    })
+}
+
+/// Vrai si le bloc EXIF contient une latitude ou une longitude
+fn exif_has_location(exif: Vec<u8>) -> bool {
+    let Ok(exif) = exif::Reader::new().read_raw(exif) else {
+        return false;
+    };
+    [exif::Tag::GPSLatitude, exif::Tag::GPSLongitude]
+        .into_iter()
+        .any(|tag| exif.get_field(tag, exif::In::PRIMARY).is_some())
+}
+
+/// Vrai si le paquet XMP contient une latitude ou une longitude
+fn xmp_has_location(xmp: &[u8]) -> bool {
+    let xmp = String::from_utf8_lossy(xmp);
+    xmp.contains("GPSLatitude") || xmp.contains("GPSLongitude")
+}
+
+/// Retire sans recompression les métadonnées d'un JPEG (segments APP1 à APP15
+/// hors APP14, commentaires) ; `None` si les données ne sont pas un JPEG valide
+///
+/// Les segments JFIF (APP0) et Adobe (APP14), nécessaires au décodage des
+/// couleurs, sont conservés.
+pub fn strip_metadata(jpeg: &[u8]) -> Option<Vec<u8>> {
+    if !jpeg.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+    let mut stripped = Vec::with_capacity(jpeg.len());
+    stripped.extend_from_slice(&jpeg[..2]);
+
+    let mut i = 2;
+    loop {
+        if *jpeg.get(i)? != 0xFF {
+            return None;
+        }
+        let marker = *jpeg.get(i + 1)?;
+        if marker == 0xFF {
+            i += 1;
+            continue;
+        }
+        // Segments sans longueur
+        if marker == 0x01 || (0xD0..=0xD7).contains(&marker) {
+            stripped.extend_from_slice(&jpeg[i..i + 2]);
+            i += 2;
+            continue;
+        }
+        if marker == 0xD9 {
+            stripped.extend_from_slice(&jpeg[i..i + 2]);
+            return Some(stripped);
+        }
+
+        let length = u16::from_be_bytes([*jpeg.get(i + 2)?, *jpeg.get(i + 3)?]) as usize;
+        let end = i + 2 + length;
+        let segment = jpeg.get(i..end)?;
+        // Début des données compressées : le reste du fichier est recopié
+        if marker == 0xDA {
+            stripped.extend_from_slice(&jpeg[i..]);
+            return Some(stripped);
+        }
+        let is_metadata = matches!(marker, 0xE1..=0xEF if marker != 0xEE) || marker == 0xFE;
+        if !is_metadata {
+            stripped.extend_from_slice(segment);
+        }
+        i = end;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Database;
+    use image::{ImageEncoder, RgbImage};
+
+    fn config() -> PhotoConfig {
+        PhotoConfig {
+            max_dimension: 100,
+            jpeg_quality: 80,
+        }
+    }
+
+    /// EXIF (TIFF big-endian) sans aucune entrée
+    const EMPTY_EXIF: &[u8] = b"MM\0\x2a\0\0\0\x08\0\0\0\0\0\0";
+
+    fn processor() -> (PhotoProcessor, AuditRepository) {
+        let audit = AuditRepository::new(Database::open_in_memory().unwrap());
+        (PhotoProcessor::new(config(), audit.clone()), audit)
+    }
+
+    fn fiche(photo: &[u8]) -> ContactFiche {
+        ContactFiche {
+            photo_base64: Some(base64::engine::general_purpose::STANDARD.encode(photo)),
+            ..ContactFiche::sample("ACME")
+        }
+    }
+
+    fn jpeg_with_exif(exif: &[u8]) -> Vec<u8> {
+        let mut jpeg = Vec::new();
+        let mut encoder = JpegEncoder::new(&mut jpeg);
+        encoder.set_exif_metadata(exif.to_vec()).unwrap();
+        encoder
+            .write_image(&[0; 40 * 20 * 3], 40, 20, image::ExtendedColorType::Rgb8)
+            .unwrap();
+        jpeg
+    }
+
+    fn exif_of(jpeg: &[u8]) -> Option<Vec<u8>> {
+        let reader = ImageReader::with_format(Cursor::new(jpeg), ImageFormat::Jpeg);
+        reader.into_decoder().unwrap().exif_metadata().unwrap()
+    }
+
+    fn dimensions(jpeg: &[u8]) -> (u32, u32) {
+        let image = image::load_from_memory_with_format(jpeg, ImageFormat::Jpeg).unwrap();
+        (image.width(), image.height())
+    }
+
+    #[test]
+    fn test_png_is_downscaled_and_recompressed() {
+        let mut png = Vec::new();
+        RgbImage::new(400, 200)
+            .write_with_encoder(image::codecs::png::PngEncoder::new(&mut png))
+            .unwrap();
+
+        let photo = process(&png, &config()).unwrap();
+        assert_eq!(image::guess_format(&photo.data).unwrap(), ImageFormat::Jpeg);
+        assert_eq!(dimensions(&photo.data), (100, 50));
+        assert!(!photo.had_location);
+    }
+
+    #[test]
+    fn test_exif_orientation_is_applied() {
+        // EXIF (TIFF big-endian) avec une seule entrée : orientation 6 (rotation 90°)
+        let exif = [
+            b'M', b'M', 0, 42, 0, 0, 0, 8, 0, 1, 0x01, 0x12, 0, 3, 0, 0, 0, 1, 0, 6, 0, 0, 0,
+            0, 0, 0,
+        ];
+        let jpeg = jpeg_with_exif(&exif);
+
+        assert_eq!(dimensions(&process(&jpeg, &config()).unwrap().data), (20, 40));
+    }
+
+    #[tokio::test]
+    async fn test_gps_metadata_is_removed_and_counted() {
+        // IFD0 pointant vers un IFD GPS contenant une latitude (48° 51' 0")
+        let exif = [
+            b'M', b'M', 0, 42, 0, 0, 0, 8, // en-tête TIFF
+            0, 1, 0x88, 0x25, 0, 4, 0, 0, 0, 1, 0, 0, 0, 26, 0, 0, 0, 0, // IFD0
+            0, 1, 0, 2, 0, 5, 0, 0, 0, 3, 0, 0, 0, 44, 0, 0, 0, 0, // IFD GPS
+            0, 0, 0, 48, 0, 0, 0, 1, 0, 0, 0, 51, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 1,
+        ];
+        let jpeg = jpeg_with_exif(&exif);
+        assert!(process(&jpeg, &config()).unwrap().had_location);
+
+        let (processor, audit) = processor();
+        let fiches = vec![fiche(&jpeg), fiche(&jpeg_with_exif(EMPTY_EXIF))];
+        let fiches = processor.process_photos(fiches, |f| f.iter_mut().collect()).await.unwrap();
+        let stored = fiches[0].decode_photo().unwrap().unwrap();
+        assert!(exif_of(&stored).is_none());
+
+        let counters = audit.counters().await.unwrap();
+        assert_eq!(counters.get(AUDIT_PHOTOS_PROCESSED), Some(&2));
+        assert_eq!(counters.get(AUDIT_PHOTOS_WITH_LOCATION), Some(&1));
+    }
+
+    #[test]
+    fn test_strip_metadata_keeps_image_data() {
+        let jpeg = jpeg_with_exif(EMPTY_EXIF);
+        assert!(exif_of(&jpeg).is_some());
+
+        let stripped = strip_metadata(&jpeg).unwrap();
+        assert!(stripped.len() < jpeg.len());
+        assert!(exif_of(&stripped).is_none());
+        assert_eq!(dimensions(&stripped), (40, 20));
+        assert!(strip_metadata(b"%PDF-1.4").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_non_images_are_rejected() {
+        assert!(matches!(process(b"%PDF-1.4", &config()), Err(PhotoError::NotAnImage)));
+
+        let (processor, _) = processor();
+        let error = processor.process_photos(fiche(b"hello"), |f| vec![f]).await.unwrap_err();
+        assert!(error.is_invalid_photo());
+        assert_eq!(error.to_string(), "photo de ACME: le fichier n'est pas une image");
+    }
+}
@@ -0,0 +1,285 @@
+//! Stockage des photos hors de la base (système de fichiers, bucket S3).
+//!
+//! Les photos traitées sont envoyées au stockage avant l'enregistrement des
+//! fiches : la base ne conserve que leur clé. Les emails d'export peuvent
+//! alors pointer vers la photo par un lien temporaire au lieu de la joindre.
+
+use async_trait::async_trait;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::domain::{ContactFiche, PhotoLinks};
+use crate::storage::ContactRepository;
+
+/// Résultat d'une opération sur le stockage des photos
+pub type StoreResult<T> = Result<T, StoreError>;
+
+/// Erreurs possibles du stockage des photos
+#[derive(Debug, thiserror::Error)]
+pub enum StoreError {
+    #[error("Clé de photo invalide: {0}")]
+    InvalidKey(String),
+
+    #[error("Photo introuvable: {0}")]
+    NotFound(String),
+
+    #[error("Photo illisible: {0}")]
+    InvalidPhoto(#[from] base64::DecodeError),
+
+    #[error("Erreur d'accès aux fichiers: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Erreur du stockage objet: {0}")]
+    Remote(String),
+}
+
+/// Emplacement de stockage des photos
+#[async_trait]
+pub trait PhotoStore: Send + Sync {
+    /// Enregistre une photo JPEG sous `key`
+    async fn put(&self, key: &str, data: Vec<u8>) -> StoreResult<()>;
+
+    /// Relit une photo
+    async fn get(&self, key: &str) -> StoreResult<Vec<u8>>;
+
+    /// Supprime une photo (sans erreur si elle n'existe plus)
+    async fn delete(&self, key: &str) -> StoreResult<()>;
+
+    /// URL de lecture valable `expires_in`, si le stockage sait en produire
+    fn presigned_url(&self, _key: &str, _expires_in: Duration) -> Option<String> {
+        None
+    }
+
+    /// Nom du stockage pour le logging
+    fn backend_name(&self) -> &'static str;
+}
+
+/// Vérifie qu'une clé a la forme produite par `new_key` (`photos/<uuid>.jpg`)
+pub fn validate_key(key: &str) -> StoreResult<()> {
+    let valid = key
+        .strip_prefix("photos/")
+        .and_then(|name| name.strip_suffix(".jpg"))
+        .is_some_and(|id| Uuid::parse_str(id).is_ok());
+    if valid {
+        Ok(())
+    } else {
+        Err(StoreError::InvalidKey(key.to_string()))
+    }
+}
+
+fn new_key() -> String {
+    format!("photos/{}.jpg", Uuid::new_v4())
+}
+
+/// Accès aux photos stockées hors de la base, s'il y a lieu
+#[derive(Clone)]
+pub struct PhotoStorage {
+    /// `None` : photos conservées dans la base
+    store: Option<Arc<dyn PhotoStore>>,
+    link_ttl: Duration,
+    /// Liens servis par le serveur, pour les stockages sans URL présignée
+    public_base_url: Option<String>,
+    link_secret: Arc<str>,
+}
+
+impl PhotoStorage {
+    pub fn new(
+        store: Option<Arc<dyn PhotoStore>>,
+        link_ttl: Duration,
+        public_base_url: Option<String>,
+        link_secret: &str,
+    ) -> Self {
+        Self {
+            store,
+            link_ttl,
+            public_base_url,
+            link_secret: link_secret.into(),
+        }
+    }
+
+    /// Envoie les photos des fiches au stockage et renseigne leur `photo_key`
+    ///
+    /// La photo reste dans la fiche pour les pièces jointes de l'export ;
+    /// sans stockage configuré, rien n'est fait. En cas d'échec, les photos
+    /// déjà déposées sont supprimées.
+    pub async fn upload<'a>(
+        &self,
+        fiches: impl IntoIterator<Item = &'a mut ContactFiche>,
+    ) -> StoreResult<()> {
+        let Some(store) = &self.store else {
+            return Ok(());
+        };
+        let mut uploaded = Vec::new();
+        for fiche in fiches.into_iter().filter(|f| f.photo_key.is_none()) {
+            let result = match fiche.decode_photo() {
+                Ok(Some(data)) => {
+                    let key = new_key();
+                    store.put(&key, data).await.map(|()| Some(key))
+                }
+                Ok(None) => Ok(None),
+                Err(e) => Err(e.into()),
+            };
+            match result {
+                Ok(key) => {
+                    uploaded.extend(key.clone());
+                    fiche.photo_key = key;
+                }
+                Err(e) => {
+                    self.discard(uploaded).await;
+                    return Err(e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Supprime des photos déposées pour des fiches finalement non enregistrées
+    ///
+    /// Une photo qui ne peut pas être supprimée reste orpheline dans le stockage.
+    pub async fn discard(&self, keys: Vec<String>) {
+        let Some(store) = &self.store else {
+            return;
+        };
+        for key in keys {
+            if let Err(e) = store.delete(&key).await {
+                warn!(key = %key, error = %e, "Suppression de photo impossible");
+            }
+        }
+    }
+
+    /// Recharge depuis le stockage les photos désignées par `photo_key`
+    pub async fn load(&self, fiches: &mut [ContactFiche]) -> StoreResult<()> {
+        let Some(store) = &self.store else {
+            return Ok(());
+        };
+        for fiche in fiches.iter_mut().filter(|f| f.photo_base64.is_none()) {
+            if let Some(key) = &fiche.photo_key {
+                let data = store.get(key).await?;
+                fiche.photo_base64 = Some(base64::engine::general_purpose::STANDARD.encode(data));
+            }
+        }
+        Ok(())
+    }
+
+    /// Liens temporaires vers les photos stockées des fiches
+    ///
+    /// `None` si les photos sont en base, ou si le stockage ne produit pas
+    /// d'URL présignée et qu'aucune adresse publique n'est configurée.
+    pub fn links(&self, fiches: &[ContactFiche]) -> Option<PhotoLinks> {
+        let store = self.store.as_ref()?;
+        let expires_at = Utc::now() + self.link_ttl;
+        let urls: Vec<Option<String>> = fiches
+            .iter()
+            .map(|fiche| {
+                let key = fiche.photo_key.as_deref()?;
+                store
+                    .presigned_url(key, self.link_ttl)
+                    .or_else(|| self.signed_link(key, expires_at))
+            })
+            .collect();
+
+        let missing = fiches
+            .iter()
+            .zip(&urls)
+            .any(|(fiche, url)| fiche.photo_key.is_some() && url.is_none());
+        if missing {
+            warn!(
+                store = store.backend_name(),
+                "Liens vers les photos indisponibles sans PUBLIC_BASE_URL"
+            );
+            return None;
+        }
+        Some(PhotoLinks { urls, expires_at })
+    }
+
+    /// Lien servi par le serveur (`GET /{clé}`, soit `/photos/<uuid>.jpg`), signé et daté
+    fn signed_link(&self, key: &str, expires_at: DateTime<Utc>) -> Option<String> {
+        let base = self.public_base_url.as_deref()?;
+        let expires = expires_at.timestamp();
+        Some(format!(
+            "{}/{}?expires={}&signature={}",
+            base,
+            key,
+            expires,
+            hex::encode(self.link_mac(key, expires).finalize().into_bytes())
+        ))
+    }
+
+    fn link_mac(&self, key: &str, expires: i64) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.link_secret.as_bytes())
+            .expect("HMAC accepte toute taille de clé");
+        mac.update(format!("{}\n{}", key, expires).as_bytes());
+        mac
+    }
+
+    /// Photo désignée par un lien signé, ou `None` si le lien est invalide ou expiré
+    pub async fn open_link(
+        &self,
+        key: &str,
+        expires: i64,
+        signature: &str,
+    ) -> StoreResult<Option<Vec<u8>>> {
+        let Some(store) = &self.store else {
+            return Ok(None);
+        };
+        let Ok(signature) = hex::decode(signature) else {
+            return Ok(None);
+        };
+        if expires < Utc::now().timestamp()
+            || self.link_mac(key, expires).verify_slice(&signature).is_err()
+        {
+            return Ok(None);
+        }
+        validate_key(key)?;
+        store.get(key).await.map(Some)
+    }
+
+    /// Supprime du stockage les photos libérées par la suppression de fiches
+    pub async fn purge_released(&self, contacts: &ContactRepository) {
+        let Some(store) = &self.store else {
+            return;
+        };
+        let keys = match contacts.released_photo_keys(100).await {
+            Ok(keys) => keys,
+            Err(e) => {
+                warn!(error = %e, "Lecture des photos à supprimer impossible");
+                return;
+            }
+        };
+
+        let mut deleted = Vec::with_capacity(keys.len());
+        for key in keys {
+            match store.delete(&key).await {
+                Ok(()) => deleted.push(key),
+                Err(e) => warn!(key = %key, error = %e, "Suppression de photo impossible"),
+            }
+        }
+        if deleted.is_empty() {
+            return;
+        }
+        info!(count = deleted.len(), "Photos supprimées du stockage");
+        if let Err(e) = contacts.forget_released_photo_keys(deleted).await {
+            warn!(error = %e, "Mise à jour des photos supprimées impossible");
+        }
+    }
+
+    /// Démarre la suppression périodique des photos libérées
+    pub fn spawn_cleanup(self, contacts: ContactRepository) {
+        if self.store.is_none() {
+            return;
+        }
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(60));
+            loop {
+                interval.tick().await;
+                self.purge_released(&contacts).await;
+            }
+        });
+    }
+}
@@ -0,0 +1,307 @@
+//! Registre des clés d'API des appareils.
+//!
+//! Les clés sont générées par le serveur et remises une seule fois ; seule
+//! leur empreinte SHA-256 est enregistrée. Une clé aléatoire de 244 bits ne
+//! craint pas la recherche exhaustive : un hachage lent est inutile.
+//!
+//! Le secret de signature des requêtes est en revanche conservé en clair :
+//! le serveur en a besoin pour recalculer chaque signature.
+
+use super::{now_millis, Database, StorageResult};
+use crate::domain::{ApiKey, ApiKeySecrets, IssueApiKeyRequest, RecipientPolicy, Scope};
+use rusqlite::{params, OptionalExtension, Row};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+/// Colonnes lues pour reconstruire une clé
+const KEY_COLUMNS: &str = "id, device_id, label, prefix, scopes, created_at, expires_at, \
+                           last_used_at, revoked_at, recipients";
+
+/// Intervalle minimal entre deux mises à jour de `last_used_at` (ms)
+const LAST_USED_RESOLUTION_MS: i64 = 60_000;
+
+/// Longueur du préfixe affiché (`smp_` et 8 caractères)
+const PREFIX_LEN: usize = 12;
+
+/// Accès aux clés d'API
+#[derive(Clone)]
+pub struct ApiKeyRepository {
+    db: Database,
+}
+
+impl ApiKeyRepository {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    /// Émet une nouvelle clé ; retourne la clé enregistrée et ses secrets,
+    /// qui ne pourront plus être relus
+    pub async fn issue(
+        &self,
+        request: IssueApiKeyRequest,
+    ) -> StorageResult<(ApiKey, ApiKeySecrets)> {
+        self.db
+            .call(move |conn| {
+                let secret = format!("smp_{}", random_token());
+                let signing_secret = random_token();
+                let now = now_millis();
+                let mut scopes = request.scopes;
+                scopes.sort_by_key(|s| s.as_str());
+                scopes.dedup();
+
+                let key = ApiKey {
+                    id: Uuid::new_v4().to_string(),
+                    device_id: request.device_id,
+                    label: request.label,
+                    prefix: secret[..PREFIX_LEN].to_string(),
+                    scopes,
+                    recipients: request.recipients.map(|policy| {
+                        RecipientPolicy::new(policy.domains, policy.addresses)
+                    }),
+                    created_at: now,
+                    expires_at: request
+                        .expires_in_days
+                        .map(|days| now + i64::from(days) * 24 * 3600 * 1000),
+                    last_used_at: None,
+                    revoked_at: None,
+                };
+
+                let scopes: Vec<&str> = key.scopes.iter().map(Scope::as_str).collect();
+                conn.execute(
+                    "INSERT INTO api_keys (id, device_id, label, prefix, key_hash, scopes,
+                                           created_at, expires_at, signing_secret, recipients)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                    params![
+                        key.id,
+                        key.device_id,
+                        key.label,
+                        key.prefix,
+                        key_hash(&secret),
+                        serde_json::to_string(&scopes).unwrap_or_default(),
+                        key.created_at,
+                        key.expires_at,
+                        signing_secret,
+                        key.recipients
+                            .as_ref()
+                            .map(|policy| serde_json::to_string(policy).unwrap_or_default()),
+                    ],
+                )?;
+                Ok((
+                    key,
+                    ApiKeySecrets {
+                        key: secret,
+                        signing_secret,
+                    },
+                ))
+            })
+            .await
+    }
+
+    /// Clé valide (ni révoquée ni expirée) correspondant au secret présenté
+    ///
+    /// La date de dernière utilisation est mise à jour au plus une fois par minute.
+    pub async fn authenticate(&self, secret: &str) -> StorageResult<Option<ApiKey>> {
+        let hash = key_hash(secret);
+        self.db
+            .call(move |conn| {
+                let now = now_millis();
+                let key = conn
+                    .query_row(
+                        &format!(
+                            "SELECT {} FROM api_keys WHERE key_hash = ?1 AND revoked_at IS NULL
+                             AND (expires_at IS NULL OR expires_at > ?2)",
+                            KEY_COLUMNS
+                        ),
+                        params![hash, now],
+                        key_from_row,
+                    )
+                    .optional()?;
+
+                if let Some(key) = &key {
+                    touch(conn, &key.id, now)?;
+                }
+                Ok(key)
+            })
+            .await
+    }
+
+    /// Clé valide d'identifiant `id` et son secret de signature
+    ///
+    /// Les clés émises avant la signature des requêtes n'ont pas de secret :
+    /// elles ne peuvent pas signer.
+    pub async fn signing_key(&self, id: &str) -> StorageResult<Option<(ApiKey, String)>> {
+        let id = id.to_string();
+        self.db
+            .call(move |conn| {
+                let now = now_millis();
+                let found = conn
+                    .query_row(
+                        &format!(
+                            "SELECT {}, signing_secret FROM api_keys
+                             WHERE id = ?1 AND signing_secret IS NOT NULL AND revoked_at IS NULL
+                             AND (expires_at IS NULL OR expires_at > ?2)",
+                            KEY_COLUMNS
+                        ),
+                        params![id, now],
+                        |row| Ok((key_from_row(row)?, row.get::<_, String>(10)?)),
+                    )
+                    .optional()?;
+
+                if let Some((key, _)) = &found {
+                    touch(conn, &key.id, now)?;
+                }
+                Ok(found)
+            })
+            .await
+    }
+
+    /// Toutes les clés, les plus récentes d'abord
+    pub async fn list(&self) -> StorageResult<Vec<ApiKey>> {
+        self.db
+            .call(|conn| {
+                let mut stmt = conn.prepare(&format!(
+                    "SELECT {} FROM api_keys ORDER BY created_at DESC, id",
+                    KEY_COLUMNS
+                ))?;
+                let keys = stmt.query_map([], key_from_row)?.collect::<Result<_, _>>()?;
+                Ok(keys)
+            })
+            .await
+    }
+
+    /// Révoque une clé ; retourne `false` si elle n'existe pas ou est déjà révoquée
+    pub async fn revoke(&self, id: &str) -> StorageResult<bool> {
+        let id = id.to_string();
+        self.db
+            .call(move |conn| {
+                let revoked = conn.execute(
+                    "UPDATE api_keys SET revoked_at = ?2 WHERE id = ?1 AND revoked_at IS NULL",
+                    params![id, now_millis()],
+                )?;
+                Ok(revoked > 0)
+            })
+            .await
+    }
+}
+
+/// Met à jour la date de dernière utilisation, au plus une fois par minute
+fn touch(conn: &rusqlite::Connection, id: &str, now: i64) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE api_keys SET last_used_at = ?2
+         WHERE id = ?1 AND (last_used_at IS NULL OR last_used_at < ?3)",
+        params![id, now, now - LAST_USED_RESOLUTION_MS],
+    )?;
+    Ok(())
+}
+
+/// Jeton aléatoire de 244 bits (64 caractères hexadécimaux)
+pub(super) fn random_token() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+/// Empreinte enregistrée d'une clé (ou d'un jeton)
+pub(super) fn key_hash(secret: &str) -> String {
+    hex::encode(Sha256::digest(secret.as_bytes()))
+}
+
+fn key_from_row(row: &Row<'_>) -> rusqlite::Result<ApiKey> {
+    let id: String = row.get(0)?;
+    let scopes: String = row.get(4)?;
+    let scopes: Vec<String> = serde_json::from_str(&scopes).unwrap_or_default();
+    // Une politique illisible n'autorise aucun destinataire
+    let recipients = row.get::<_, Option<String>>(9)?.map(|json| {
+        serde_json::from_str(&json).unwrap_or_else(|e| {
+            tracing::error!(key_id = %id, error = %e, "Destinataires de la clé illisibles");
+            RecipientPolicy::default()
+        })
+    });
+
+    Ok(ApiKey {
+        id,
+        device_id: row.get(1)?,
+        label: row.get(2)?,
+        prefix: row.get(3)?,
+        // Un droit inconnu (retiré depuis) est ignoré
+        scopes: scopes.iter().filter_map(|s| Scope::parse(s)).collect(),
+        recipients,
+        created_at: row.get(5)?,
+        expires_at: row.get(6)?,
+        last_used_at: row.get(7)?,
+        revoked_at: row.get(8)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(scopes: Vec<Scope>, expires_in_days: Option<u32>) -> IssueApiKeyRequest {
+        IssueApiKeyRequest {
+            label: "Tablette stand A".to_string(),
+            device_id: Some("tablet-1".to_string()),
+            scopes,
+            expires_in_days,
+            recipients: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_issue_authenticate_and_revoke() {
+        let repo = ApiKeyRepository::new(Database::open_in_memory().unwrap());
+
+        let scopes = vec![Scope::Sync, Scope::ExportSend, Scope::Sync];
+        let (key, secrets) = repo.issue(request(scopes, Some(30))).await.unwrap();
+        let secret = secrets.key;
+        assert!(secret.starts_with(&key.prefix));
+        assert_eq!(key.scopes, vec![Scope::ExportSend, Scope::Sync]);
+
+        let found = repo.authenticate(&secret).await.unwrap().unwrap();
+        assert_eq!(found.id, key.id);
+        assert_eq!(found.scopes, vec![Scope::ExportSend, Scope::Sync]);
+        assert!(repo.list().await.unwrap()[0].last_used_at.is_some());
+        assert!(repo.authenticate("smp_inconnue").await.unwrap().is_none());
+
+        assert!(repo.revoke(&key.id).await.unwrap());
+        assert!(!repo.revoke(&key.id).await.unwrap());
+        assert!(repo.authenticate(&secret).await.unwrap().is_none());
+        assert!(repo.signing_key(&key.id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_expired_key_is_refused() {
+        let db = Database::open_in_memory().unwrap();
+        let repo = ApiKeyRepository::new(db.clone());
+        let (key, secrets) = repo.issue(request(vec![Scope::Sync], Some(1))).await.unwrap();
+        let (_, signing_secret) = repo.signing_key(&key.id).await.unwrap().unwrap();
+        assert_eq!(signing_secret, secrets.signing_secret);
+
+        let id = key.id.clone();
+        db.call(move |conn| {
+            conn.execute("UPDATE api_keys SET expires_at = 1 WHERE id = ?1", [id])?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+        assert!(repo.authenticate(&secrets.key).await.unwrap().is_none());
+        assert!(repo.signing_key(&key.id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_unreadable_recipients_allow_nobody() {
+        let db = Database::open_in_memory().unwrap();
+        let repo = ApiKeyRepository::new(db.clone());
+        let (key, secrets) = repo.issue(request(vec![Scope::HistorySend], None)).await.unwrap();
+        assert!(key.recipients.is_none());
+
+        let id = key.id.clone();
+        db.call(move |conn| {
+            conn.execute("UPDATE api_keys SET recipients = '{' WHERE id = ?1", [id])?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+        let found = repo.authenticate(&secrets.key).await.unwrap().unwrap();
+        let recipients = found.recipients.unwrap();
+        assert!(!recipients.allows("commercial@smp-moules.com"));
+    }
+}
@@ -0,0 +1,69 @@
+//! Compteurs d'audit.
+//!
+//! Compteurs cumulés et persistés (par exemple le nombre de photos reçues
+//! contenant des coordonnées GPS), consultables via `/api/audit`.
+
+use super::{now_millis, Database, StorageResult};
+use rusqlite::params;
+use std::collections::BTreeMap;
+
+/// Accès aux compteurs d'audit
+#[derive(Clone)]
+pub struct AuditRepository {
+    db: Database,
+}
+
+impl AuditRepository {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    /// Ajoute des valeurs aux compteurs (créés au besoin)
+    pub async fn increment(&self, counters: Vec<(&'static str, u64)>) -> StorageResult<()> {
+        self.db
+            .call(move |conn| {
+                let tx = conn.transaction()?;
+                let now = now_millis();
+                for (name, value) in counters.into_iter().filter(|(_, value)| *value > 0) {
+                    tx.execute(
+                        "INSERT INTO audit_counters (name, value, updated_at) VALUES (?1, ?2, ?3)
+                         ON CONFLICT(name) DO UPDATE
+                         SET value = value + excluded.value, updated_at = excluded.updated_at",
+                        params![name, value as i64, now],
+                    )?;
+                }
+                tx.commit()?;
+                Ok(())
+            })
+            .await
+    }
+
+    /// Valeur de tous les compteurs
+    pub async fn counters(&self) -> StorageResult<BTreeMap<String, i64>> {
+        self.db
+            .call(|conn| {
+                let mut stmt = conn.prepare("SELECT name, value FROM audit_counters")?;
+                let counters = stmt
+                    .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                    .collect::<Result<_, _>>()?;
+                Ok(counters)
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_counters_accumulate() {
+        let audit = AuditRepository::new(Database::open_in_memory().unwrap());
+        audit.increment(vec![("photos_processed", 2), ("photos_with_location", 0)]).await.unwrap();
+        audit.increment(vec![("photos_processed", 3)]).await.unwrap();
+
+        let counters = audit.counters().await.unwrap();
+        assert_eq!(counters.get("photos_processed"), Some(&5));
+        assert!(!counters.contains_key("photos_with_location"));
+    }
+}
@@ -0,0 +1,823 @@
+//! Dépôt des fiches contacts.
+
+use super::{now_millis, Database, StorageError, StorageResult};
+use crate::domain::{
+    merge_fiches, ContactFiche, ContactStatus, DuplicateKeys, DuplicateMatch, PhotoRef,
+    StoredContact,
+};
+use crate::config::PhotoConfig;
+use crate::photo::{self, PHOTO_CONTENT_TYPE};
+use rusqlite::{params, Connection, OptionalExtension, Row, ToSql, Transaction};
+use tracing::warn;
+use uuid::Uuid;
+
+/// Colonnes lues pour reconstruire une fiche
+pub(super) const CONTACT_COLUMNS: &str =
+    "id, societe, contact, email, telephone, notes, sectors, status, \
+     created_at, device_id, received_at, updated_at, version, deleted";
+
+/// Critères de recherche des fiches
+#[derive(Debug, Clone, Default)]
+pub struct ContactFilter {
+    /// Recherche texte sur société, contact et email
+    pub search: Option<String>,
+    pub device_id: Option<String>,
+    pub limit: u32,
+    pub offset: u32,
+}
+
+/// Accès aux fiches contacts persistées
+#[derive(Clone)]
+pub struct ContactRepository {
+    pub(super) db: Database,
+}
+
+impl ContactRepository {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    /// Enregistre un lot de fiches (avec leurs photos) dans une seule transaction
+    pub async fn insert_many(
+        &self,
+        fiches: Vec<ContactFiche>,
+        device_id: Option<String>,
+    ) -> StorageResult<Vec<StoredContact>> {
+        self.db
+            .call(move |conn| {
+                let tx = conn.transaction()?;
+                let stored = fiches
+                    .into_iter()
+                    .map(|fiche| insert_contact(&tx, None, fiche, device_id.clone()))
+                    .collect::<StorageResult<Vec<_>>>()?;
+                tx.commit()?;
+                Ok(stored)
+            })
+            .await
+    }
+
+    /// Enregistre une fiche unique
+    pub async fn insert(
+        &self,
+        fiche: ContactFiche,
+        device_id: Option<String>,
+    ) -> StorageResult<StoredContact> {
+        let mut stored = self.insert_many(vec![fiche], device_id).await?;
+        stored
+            .pop()
+            .ok_or_else(|| StorageError::InvalidData("aucune fiche enregistrée".to_string()))
+    }
+
+    /// Lit une fiche par son identifiant (les fiches supprimées sont ignorées)
+    pub async fn get(&self, id: &str) -> StorageResult<Option<StoredContact>> {
+        let id = id.to_string();
+        self.db
+            .call(move |conn| Ok(find_contact(conn, &id)?.filter(|c| !c.deleted)))
+            .await
+    }
+
+    /// Liste les fiches selon un filtre, avec le nombre total de résultats
+    pub async fn list(&self, filter: ContactFilter) -> StorageResult<(Vec<StoredContact>, i64)> {
+        self.db
+            .call(move |conn| {
+                let mut clauses = vec!["deleted = 0"];
+                let mut args: Vec<Box<dyn ToSql>> = Vec::new();
+
+                if let Some(search) = filter.search.filter(|s| !s.trim().is_empty()) {
+                    clauses.push("(societe LIKE ? OR contact LIKE ? OR email LIKE ?)");
+                    let pattern = format!("%{}%", search.trim());
+                    args.push(Box::new(pattern.clone()));
+                    args.push(Box::new(pattern.clone()));
+                    args.push(Box::new(pattern));
+                }
+                if let Some(device_id) = filter.device_id {
+                    clauses.push("device_id = ?");
+                    args.push(Box::new(device_id));
+                }
+
+                let where_sql = format!("WHERE {}", clauses.join(" AND "));
+                let arg_refs: Vec<&dyn ToSql> = args.iter().map(|a| a.as_ref()).collect();
+
+                let total: i64 = conn.query_row(
+                    &format!("SELECT COUNT(*) FROM contacts {}", where_sql),
+                    arg_refs.as_slice(),
+                    |row| row.get(0),
+                )?;
+
+                let mut stmt = conn.prepare(&format!(
+                    "SELECT {} FROM contacts {} ORDER BY received_at DESC, id LIMIT {} OFFSET {}",
+                    CONTACT_COLUMNS, where_sql, filter.limit, filter.offset
+                ))?;
+                let rows = stmt
+                    .query_map(arg_refs.as_slice(), contact_from_row)?
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                let contacts = rows
+                    .into_iter()
+                    .map(|mut contact| {
+                        contact.photos = load_photos(conn, &contact.id)?;
+                        Ok(contact)
+                    })
+                    .collect::<StorageResult<Vec<_>>>()?;
+
+                Ok((contacts, total))
+            })
+            .await
+    }
+
+    /// Met à jour les champs d'une fiche existante
+    ///
+    /// La photo n'est remplacée que si la fiche fournie en contient une.
+    pub async fn update(
+        &self,
+        id: &str,
+        fiche: ContactFiche,
+    ) -> StorageResult<Option<StoredContact>> {
+        let id = id.to_string();
+        self.db
+            .call(move |conn| {
+                let tx = conn.transaction()?;
+                if !update_contact(&tx, &id, fiche)? {
+                    return Ok(None);
+                }
+                let stored = find_contact(&tx, &id)?;
+                tx.commit()?;
+                Ok(stored)
+            })
+            .await
+    }
+
+    /// Recherche, pour chaque fiche, une fiche déjà enregistrée qui lui ressemble
+    pub async fn find_duplicates(
+        &self,
+        fiches: Vec<ContactFiche>,
+    ) -> StorageResult<Vec<DuplicateMatch>> {
+        self.db
+            .call(move |conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT id, email_key, phone_key, name_key FROM contacts
+                     WHERE deleted = 0 AND (email_key = ?1 OR phone_key = ?2 OR name_key = ?3)
+                     ORDER BY received_at, id",
+                )?;
+
+                let mut matches = Vec::new();
+                for (index, fiche) in fiches.iter().enumerate() {
+                    let keys = DuplicateKeys::of(fiche);
+                    let candidates = stmt
+                        .query_map(params![keys.email, keys.phone, keys.name], |row| {
+                            let candidate = DuplicateKeys {
+                                email: row.get(1)?,
+                                phone: row.get(2)?,
+                                name: row.get(3)?,
+                            };
+                            Ok((row.get::<_, String>(0)?, candidate))
+                        })?
+                        .collect::<Result<Vec<_>, _>>()?;
+
+                    // Le candidat partageant le plus de critères (le plus ancien à égalité)
+                    let best = candidates
+                        .into_iter()
+                        .map(|(id, candidate)| (id, keys.reasons(&candidate)))
+                        .filter(|(_, reasons)| !reasons.is_empty())
+                        .fold(None::<(String, Vec<_>)>, |best, current| match best {
+                            Some(best) if best.1.len() >= current.1.len() => Some(best),
+                            _ => Some(current),
+                        });
+
+                    if let Some((id, reasons)) = best {
+                        matches.push(DuplicateMatch {
+                            index,
+                            duplicate_of_index: None,
+                            duplicate_of_id: Some(id),
+                            reasons,
+                        });
+                    }
+                }
+                Ok(matches)
+            })
+            .await
+    }
+
+    /// Fusionne `duplicate_id` dans `id` : champs les plus complets, photos des deux fiches
+    ///
+    /// La fiche en double est ensuite supprimée. Retourne `None` si l'une des
+    /// deux fiches est introuvable.
+    pub async fn merge(
+        &self,
+        id: &str,
+        duplicate_id: &str,
+    ) -> StorageResult<Option<StoredContact>> {
+        if id == duplicate_id {
+            return Err(StorageError::InvalidData(
+                "une fiche ne peut pas être fusionnée avec elle-même".to_string(),
+            ));
+        }
+
+        let (id, duplicate_id) = (id.to_string(), duplicate_id.to_string());
+        self.db
+            .call(move |conn| {
+                let tx = conn.transaction()?;
+
+                let primary = find_contact(&tx, &id)?.filter(|c| !c.deleted);
+                let duplicate = find_contact(&tx, &duplicate_id)?.filter(|c| !c.deleted);
+                let (Some(primary), Some(duplicate)) = (primary, duplicate) else {
+                    return Ok(None);
+                };
+
+                let merged = merge_fiches(&primary.fiche, &duplicate.fiche);
+
+                // Les photos du doublon sont rattachées avant sa suppression
+                tx.execute(
+                    "UPDATE contact_photos SET contact_id = ?1 WHERE contact_id = ?2",
+                    params![id, duplicate_id],
+                )?;
+                update_contact(&tx, &id, merged)?;
+                delete_contact(&tx, &duplicate_id)?;
+
+                let stored = find_contact(&tx, &id)?;
+                tx.commit()?;
+                Ok(stored)
+            })
+            .await
+    }
+
+    /// Supprime une fiche et ses photos ; retourne `false` si elle n'existait pas
+    ///
+    /// La ligne est conservée comme marqueur de suppression pour la synchronisation.
+    pub async fn delete(&self, id: &str) -> StorageResult<bool> {
+        let id = id.to_string();
+        self.db
+            .call(move |conn| {
+                let tx = conn.transaction()?;
+                let deleted = delete_contact(&tx, &id)?;
+                tx.commit()?;
+                Ok(deleted)
+            })
+            .await
+    }
+
+    /// Relit des fiches avec leur première photo encodée en base64, dans l'ordre
+    /// des identifiants (les fiches supprimées depuis sont ignorées)
+    ///
+    /// Une photo déposée dans un stockage externe n'est désignée que par sa
+    /// clé (`photo_key`).
+    pub async fn fiches_with_photo(&self, ids: Vec<String>) -> StorageResult<Vec<ContactFiche>> {
+        use base64::Engine;
+
+        self.db
+            .call(move |conn| {
+                let mut fiches = Vec::with_capacity(ids.len());
+                for id in &ids {
+                    let Some(contact) = find_contact(conn, id)?.filter(|c| !c.deleted) else {
+                        continue;
+                    };
+                    let mut fiche = contact.fiche;
+                    if let Some(photo) = contact.photos.first() {
+                        let (data, key): (Vec<u8>, Option<String>) = conn.query_row(
+                            "SELECT data, storage_key FROM contact_photos WHERE id = ?1",
+                            [&photo.id],
+                            |row| Ok((row.get(0)?, row.get(1)?)),
+                        )?;
+                        match key {
+                            Some(key) => fiche.photo_key = Some(key),
+                            None => {
+                                fiche.photo_base64 =
+                                    Some(base64::engine::general_purpose::STANDARD.encode(data))
+                            }
+                        }
+                        fiche.photo_filename = Some(photo.filename.clone());
+                    }
+                    fiches.push(fiche);
+                }
+                Ok(fiches)
+            })
+            .await
+    }
+
+    /// Clés des photos externes dont la fiche a été supprimée ou la photo remplacée
+    pub async fn released_photo_keys(&self, limit: u32) -> StorageResult<Vec<String>> {
+        self.db
+            .call(move |conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT storage_key FROM released_photo_objects
+                     ORDER BY released_at, storage_key LIMIT ?1",
+                )?;
+                let keys = stmt
+                    .query_map([limit], |row| row.get(0))?
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(keys)
+            })
+            .await
+    }
+
+    /// Oublie des photos externes une fois supprimées du stockage
+    pub async fn forget_released_photo_keys(&self, keys: Vec<String>) -> StorageResult<()> {
+        self.db
+            .call(move |conn| {
+                let tx = conn.transaction()?;
+                for key in &keys {
+                    tx.execute("DELETE FROM released_photo_objects WHERE storage_key = ?1", [key])?;
+                }
+                tx.commit()?;
+                Ok(())
+            })
+            .await
+    }
+}
+
+/// Prochain numéro de séquence de modification (curseur de synchronisation)
+pub(super) fn next_seq(tx: &Transaction<'_>) -> StorageResult<i64> {
+    Ok(tx.query_row("SELECT COALESCE(MAX(seq), 0) + 1 FROM contacts", [], |row| row.get(0))?)
+}
+
+/// Lit une fiche (même supprimée) et ses références de photos
+pub(super) fn find_contact(conn: &Connection, id: &str) -> StorageResult<Option<StoredContact>> {
+    let contact = conn
+        .query_row(
+            &format!("SELECT {} FROM contacts WHERE id = ?1", CONTACT_COLUMNS),
+            [id],
+            contact_from_row,
+        )
+        .optional()?;
+
+    match contact {
+        Some(mut contact) => {
+            contact.photos = load_photos(conn, id)?;
+            Ok(Some(contact))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Construit une fiche depuis une ligne `CONTACT_COLUMNS` (photos non chargées)
+pub(super) fn contact_from_row(row: &Row<'_>) -> rusqlite::Result<StoredContact> {
+    let status: Option<String> = row.get(7)?;
+
+    Ok(StoredContact {
+        id: row.get(0)?,
+        fiche: ContactFiche {
+            societe: row.get(1)?,
+            contact: row.get(2)?,
+            email: row.get(3)?,
+            telephone: row.get(4)?,
+            notes: row.get(5)?,
+            sectors: row.get(6)?,
+            status: status.as_deref().and_then(ContactStatus::parse),
+            created_at: row.get(8)?,
+            photo_base64: None,
+            photo_filename: None,
+            photo_key: None,
+        },
+        device_id: row.get(9)?,
+        photos: vec![],
+        received_at: row.get(10)?,
+        updated_at: row.get(11)?,
+        version: row.get(12)?,
+        deleted: row.get(13)?,
+    })
+}
+
+/// Charge les références des photos d'une fiche
+pub(super) fn load_photos(conn: &Connection, contact_id: &str) -> StorageResult<Vec<PhotoRef>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, filename, content_type, size_bytes FROM contact_photos
+         WHERE contact_id = ?1 ORDER BY created_at, id",
+    )?;
+    let photos = stmt
+        .query_map([contact_id], |row| {
+            Ok(PhotoRef {
+                id: row.get(0)?,
+                filename: row.get(1)?,
+                content_type: row.get(2)?,
+                size_bytes: row.get(3)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(photos)
+}
+
+/// Insère une photo rattachée à une fiche
+///
+/// Une photo déposée dans un stockage externe (`storage_key`) n'est pas
+/// recopiée en base : seules sa clé et sa taille sont enregistrées.
+fn insert_photo(
+    tx: &Transaction<'_>,
+    contact_id: &str,
+    filename: &str,
+    data: Vec<u8>,
+    storage_key: Option<&str>,
+) -> StorageResult<PhotoRef> {
+    let photo_ref = PhotoRef {
+        id: Uuid::new_v4().to_string(),
+        filename: filename.to_string(),
+        content_type: PHOTO_CONTENT_TYPE.to_string(),
+        size_bytes: data.len() as i64,
+    };
+
+    let data = if storage_key.is_some() { Vec::new() } else { data };
+
+    tx.execute(
+        "INSERT INTO contact_photos (id, contact_id, filename, content_type, data, created_at,
+                                     storage_key, size_bytes)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            photo_ref.id,
+            contact_id,
+            photo_ref.filename,
+            photo_ref.content_type,
+            data,
+            now_millis(),
+            storage_key,
+            photo_ref.size_bytes,
+        ],
+    )?;
+    Ok(photo_ref)
+}
+
+/// Décode la photo d'une fiche, en la signalant comme donnée invalide en cas d'échec
+fn decode_photo(fiche: &ContactFiche) -> StorageResult<Option<Vec<u8>>> {
+    fiche
+        .decode_photo()
+        .map_err(|e| StorageError::InvalidData(format!("photo de {}: {}", fiche.societe, e)))
+}
+
+/// Insère une fiche et sa photo éventuelle
+///
+/// L'identifiant est généré s'il n'est pas fourni par l'appareil.
+pub(super) fn insert_contact(
+    tx: &Transaction<'_>,
+    id: Option<String>,
+    mut fiche: ContactFiche,
+    device_id: Option<String>,
+) -> StorageResult<StoredContact> {
+    let id = id.unwrap_or_else(|| Uuid::new_v4().to_string());
+    let now = now_millis();
+    let seq = next_seq(tx)?;
+
+    let photo = decode_photo(&fiche)?;
+    let keys = DuplicateKeys::of(&fiche);
+
+    tx.execute(
+        "INSERT INTO contacts (id, societe, contact, email, telephone, notes, sectors, status,
+                               created_at, device_id, received_at, updated_at, version, seq,
+                               email_key, phone_key, name_key)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?11, 1, ?12, ?13, ?14, ?15)",
+        params![
+            id,
+            fiche.societe,
+            fiche.contact,
+            fiche.email,
+            fiche.telephone,
+            fiche.notes,
+            fiche.sectors,
+            fiche.status.as_ref().map(|s| s.as_str()),
+            fiche.created_at,
+            device_id,
+            now,
+            seq,
+            keys.email,
+            keys.phone,
+            keys.name,
+        ],
+    )?;
+
+    let photos = match photo {
+        Some(data) => {
+            let filename = fiche.safe_photo_filename();
+            vec![insert_photo(tx, &id, &filename, data, fiche.photo_key.as_deref())?]
+        }
+        None => vec![],
+    };
+
+    // Le contenu binaire reste en base ; seule la référence est exposée
+    fiche.photo_base64 = None;
+
+    Ok(StoredContact {
+        id,
+        fiche,
+        device_id,
+        photos,
+        received_at: now,
+        updated_at: now,
+        version: 1,
+        deleted: false,
+    })
+}
+
+/// Met à jour une fiche non supprimée et incrémente sa version
+///
+/// La photo n'est remplacée que si la fiche fournie en contient une.
+pub(super) fn update_contact(
+    tx: &Transaction<'_>,
+    id: &str,
+    fiche: ContactFiche,
+) -> StorageResult<bool> {
+    let photo = decode_photo(&fiche)?;
+    let keys = DuplicateKeys::of(&fiche);
+    let seq = next_seq(tx)?;
+
+    let updated = tx.execute(
+        "UPDATE contacts SET societe = ?2, contact = ?3, email = ?4, telephone = ?5,
+                             notes = ?6, sectors = ?7, status = ?8, created_at = ?9,
+                             updated_at = ?10, version = version + 1, seq = ?11,
+                             email_key = ?12, phone_key = ?13, name_key = ?14
+         WHERE id = ?1 AND deleted = 0",
+        params![
+            id,
+            fiche.societe,
+            fiche.contact,
+            fiche.email,
+            fiche.telephone,
+            fiche.notes,
+            fiche.sectors,
+            fiche.status.as_ref().map(|s| s.as_str()),
+            fiche.created_at,
+            now_millis(),
+            seq,
+            keys.email,
+            keys.phone,
+            keys.name,
+        ],
+    )?;
+    if updated == 0 {
+        return Ok(false);
+    }
+
+    if let Some(data) = photo {
+        tx.execute("DELETE FROM contact_photos WHERE contact_id = ?1", [id])?;
+        insert_photo(tx, id, &fiche.safe_photo_filename(), data, fiche.photo_key.as_deref())?;
+    }
+
+    Ok(true)
+}
+
+/// Calcule les clés de doublons des fiches enregistrées avant leur introduction
+pub(super) fn backfill_duplicate_keys(tx: &Transaction<'_>) -> StorageResult<()> {
+    let rows = {
+        let mut stmt = tx.prepare("SELECT id, societe, contact, email, telephone FROM contacts")?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    normalized_keys(row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?),
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        rows
+    };
+
+    for (id, keys) in rows {
+        tx.execute(
+            "UPDATE contacts SET email_key = ?2, phone_key = ?3, name_key = ?4 WHERE id = ?1",
+            params![id, keys.email, keys.phone, keys.name],
+        )?;
+    }
+    Ok(())
+}
+
+/// Traite les photos enregistrées avant leur traitement à la réception
+///
+/// Les JPEG portant des métadonnées sont redressés selon leur orientation EXIF
+/// et recompressés comme à la réception ; les photos sont chargées une à une.
+pub(super) fn process_stored_photos(tx: &Transaction<'_>) -> StorageResult<()> {
+    let ids = {
+        let mut stmt = tx.prepare("SELECT id FROM contact_photos")?;
+        let ids = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        ids
+    };
+
+    let config = PhotoConfig::default();
+    for id in ids {
+        let data: Vec<u8> =
+            tx.query_row("SELECT data FROM contact_photos WHERE id = ?1", [&id], |row| {
+                row.get(0)
+            })?;
+        let Some(stripped) = photo::strip_metadata(&data).filter(|s| s.len() < data.len()) else {
+            continue;
+        };
+        // Une photo indécodable perd au moins ses métadonnées
+        let data = match photo::process(&data, &config) {
+            Ok(processed) => processed.data,
+            Err(e) => {
+                warn!(photo_id = %id, error = %e, "Photo stockée illisible, non recompressée");
+                stripped
+            }
+        };
+        tx.execute("UPDATE contact_photos SET data = ?2 WHERE id = ?1", params![id, data])?;
+    }
+    Ok(())
+}
+
+fn normalized_keys(
+    societe: String,
+    contact: String,
+    email: String,
+    telephone: String,
+) -> DuplicateKeys {
+    DuplicateKeys::of(&ContactFiche {
+        societe,
+        contact,
+        email,
+        telephone,
+        notes: String::new(),
+        sectors: String::new(),
+        status: None,
+        created_at: 0,
+        photo_base64: None,
+        photo_filename: None,
+        photo_key: None,
+    })
+}
+
+/// Marque une fiche comme supprimée et libère ses photos
+pub(super) fn delete_contact(tx: &Transaction<'_>, id: &str) -> StorageResult<bool> {
+    let seq = next_seq(tx)?;
+    let deleted = tx.execute(
+        "UPDATE contacts SET deleted = 1, version = version + 1, seq = ?2, updated_at = ?3
+         WHERE id = ?1 AND deleted = 0",
+        params![id, seq, now_millis()],
+    )?;
+
+    if deleted > 0 {
+        tx.execute("DELETE FROM contact_photos WHERE contact_id = ?1", [id])?;
+    }
+    Ok(deleted > 0)
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+
+    pub(in crate::storage) fn fiche(societe: &str, photo: Option<&str>) -> ContactFiche {
+        ContactFiche {
+            email: "jean@example.com".to_string(),
+            status: Some(ContactStatus::Pending),
+            photo_base64: photo.map(str::to_string),
+            ..ContactFiche::sample(societe)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_insert_many_persists_contacts_and_photos() {
+        let db = Database::open_in_memory().unwrap();
+        let repo = ContactRepository::new(db.clone());
+
+        let stored = repo
+            .insert_many(
+                vec![fiche("ACME", Some("aGVsbG8=")), fiche("Globex", None)],
+                Some("tablet-1".to_string()),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(stored.len(), 2);
+        assert_eq!(stored[0].photos.len(), 1);
+        assert_eq!(stored[0].photos[0].size_bytes, 5);
+        assert!(stored[0].fiche.photo_base64.is_none());
+
+        let (contacts, photos): (i64, i64) = db
+            .call(|conn| {
+                Ok(conn.query_row(
+                    "SELECT (SELECT COUNT(*) FROM contacts), (SELECT COUNT(*) FROM contact_photos)",
+                    [],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )?)
+            })
+            .await
+            .unwrap();
+        assert_eq!((contacts, photos), (2, 1));
+
+        // Relecture pour un rapport : photo réencodée, ordre des identifiants conservé
+        let ids = vec![stored[1].id.clone(), stored[0].id.clone(), "inconnu".to_string()];
+        let fiches = repo.fiches_with_photo(ids).await.unwrap();
+        assert_eq!(fiches.len(), 2);
+        assert_eq!(fiches[0].societe, "Globex");
+        assert_eq!(fiches[1].photo_base64.as_deref(), Some("aGVsbG8="));
+    }
+
+    #[tokio::test]
+    async fn test_external_photos_keep_only_key_and_are_released_on_delete() {
+        let repo = ContactRepository::new(Database::open_in_memory().unwrap());
+        let mut stored_fiche = fiche("ACME", Some("aGVsbG8="));
+        stored_fiche.photo_key = Some("photos/acme.jpg".to_string());
+
+        let contact = repo.insert(stored_fiche, None).await.unwrap();
+        assert_eq!(contact.photos[0].size_bytes, 5);
+
+        let fiches = repo.fiches_with_photo(vec![contact.id.clone()]).await.unwrap();
+        assert_eq!(fiches[0].photo_key.as_deref(), Some("photos/acme.jpg"));
+        assert!(fiches[0].photo_base64.is_none());
+
+        assert!(repo.released_photo_keys(10).await.unwrap().is_empty());
+        repo.delete(&contact.id).await.unwrap();
+        let released = repo.released_photo_keys(10).await.unwrap();
+        assert_eq!(released, vec!["photos/acme.jpg".to_string()]);
+
+        repo.forget_released_photo_keys(released).await.unwrap();
+        assert!(repo.released_photo_keys(10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_insert_many_rejects_invalid_photo() {
+        let repo = ContactRepository::new(Database::open_in_memory().unwrap());
+
+        let result = repo
+            .insert_many(vec![fiche("ACME", None), fiche("Bad", Some("%%%"))], None)
+            .await;
+
+        assert!(matches!(result, Err(StorageError::InvalidData(_))));
+    }
+
+    #[tokio::test]
+    async fn test_crud_round_trip() {
+        let repo = ContactRepository::new(Database::open_in_memory().unwrap());
+
+        let created = repo.insert(fiche("ACME", Some("aGVsbG8=")), None).await.unwrap();
+
+        let mut corrected = created.fiche.clone();
+        corrected.societe = "ACME Industries".to_string();
+        let updated = repo.update(&created.id, corrected).await.unwrap().unwrap();
+        assert_eq!(updated.fiche.societe, "ACME Industries");
+        assert_eq!(updated.photos.len(), 1, "la photo existante est conservée");
+        assert_eq!(updated.version, 2);
+
+        let (found, total) = repo
+            .list(ContactFilter {
+                search: Some("industries".to_string()),
+                limit: 10,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(found[0].id, created.id);
+
+        assert!(repo.delete(&created.id).await.unwrap());
+        assert!(repo.get(&created.id).await.unwrap().is_none());
+        assert!(!repo.delete(&created.id).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_find_duplicates_and_merge() {
+        let repo = ContactRepository::new(Database::open_in_memory().unwrap());
+        let first = repo.insert(fiche("ACME", Some("aGVsbG8=")), None).await.unwrap();
+
+        let mut rescan = fiche("Acme", Some("d29ybGQ="));
+        rescan.email = "JEAN@example.com ".to_string();
+        rescan.notes = "Rappeler lundi".to_string();
+
+        let matches = repo.find_duplicates(vec![rescan.clone()]).await.unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].duplicate_of_id.as_deref(), Some(first.id.as_str()));
+
+        let second = repo.insert(rescan, None).await.unwrap();
+        let merged = repo.merge(&first.id, &second.id).await.unwrap().unwrap();
+        assert_eq!(merged.fiche.notes, "Rappeler lundi");
+        assert_eq!(merged.photos.len(), 2, "les photos des deux fiches sont conservées");
+        assert!(repo.get(&second.id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_stored_photos_are_rotated_before_their_metadata_is_dropped() {
+        use base64::Engine;
+        use image::ImageEncoder;
+
+        // JPEG 40 x 20 dont l'EXIF indique une rotation de 90° (orientation 6)
+        let exif = [
+            b'M', b'M', 0, 42, 0, 0, 0, 8, 0, 1, 0x01, 0x12, 0, 3, 0, 0, 0, 1, 0, 6, 0, 0, 0,
+            0, 0, 0,
+        ];
+        let mut jpeg = Vec::new();
+        let mut encoder = image::codecs::jpeg::JpegEncoder::new(&mut jpeg);
+        encoder.set_exif_metadata(exif.to_vec()).unwrap();
+        encoder
+            .write_image(&[0; 40 * 20 * 3], 40, 20, image::ExtendedColorType::Rgb8)
+            .unwrap();
+        let photo = base64::engine::general_purpose::STANDARD.encode(&jpeg);
+
+        let db = Database::open_in_memory().unwrap();
+        let repo = ContactRepository::new(db.clone());
+        let stored = repo.insert(fiche("ACME", Some(&photo)), None).await.unwrap();
+        let untouched = repo.insert(fiche("Globex", Some("aGVsbG8=")), None).await.unwrap();
+
+        db.call(|conn| {
+            let tx = conn.transaction()?;
+            process_stored_photos(&tx)?;
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+        let fiches = repo.fiches_with_photo(vec![stored.id, untouched.id]).await.unwrap();
+        let data = base64::engine::general_purpose::STANDARD
+            .decode(fiches[0].photo_base64.as_deref().unwrap())
+            .unwrap();
+        assert!(photo::strip_metadata(&data).is_some_and(|s| s.len() == data.len()));
+        let image = image::load_from_memory(&data).unwrap();
+        assert_eq!((image.width(), image.height()), (20, 40));
+        assert_eq!(fiches[1].photo_base64.as_deref(), Some("aGVsbG8="));
+    }
+}
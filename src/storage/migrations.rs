@@ -0,0 +1,218 @@
+//! Migrations du schéma SQLite.
+//!
+//! La version courante est suivie via `PRAGMA user_version` ; chaque
+//! migration est appliquée une seule fois, dans l'ordre, au démarrage.
+
+use super::contacts::{backfill_duplicate_keys, process_stored_photos};
+use super::StorageResult;
+use rusqlite::{Connection, Transaction};
+use tracing::info;
+
+/// Une migration : script SQL, suivi d'une éventuelle reprise des données en Rust
+struct Migration {
+    sql: &'static str,
+    backfill: Option<fn(&Transaction<'_>) -> StorageResult<()>>,
+}
+
+const fn sql(sql: &'static str) -> Migration {
+    Migration {
+        sql,
+        backfill: None,
+    }
+}
+
+/// Migrations ordonnées ; l'index + 1 correspond à la version du schéma
+const MIGRATIONS: &[Migration] = &[
+    // 1. Fiches contacts et photos de cartes de visite
+    sql(r#"
+    CREATE TABLE contacts (
+        id              TEXT PRIMARY KEY,
+        societe         TEXT NOT NULL,
+        contact         TEXT NOT NULL,
+        email           TEXT NOT NULL,
+        telephone       TEXT NOT NULL,
+        notes           TEXT NOT NULL,
+        sectors         TEXT NOT NULL,
+        status          TEXT,
+        created_at      INTEGER NOT NULL,
+        device_id       TEXT,
+        received_at     INTEGER NOT NULL,
+        updated_at      INTEGER NOT NULL
+    );
+
+    CREATE INDEX idx_contacts_device ON contacts(device_id);
+
+    CREATE TABLE contact_photos (
+        id              TEXT PRIMARY KEY,
+        contact_id      TEXT NOT NULL REFERENCES contacts(id) ON DELETE CASCADE,
+        filename        TEXT NOT NULL,
+        content_type    TEXT NOT NULL,
+        data            BLOB NOT NULL,
+        created_at      INTEGER NOT NULL
+    );
+
+    CREATE INDEX idx_contact_photos_contact ON contact_photos(contact_id);
+    "#),
+    // 2. Versions et séquence de modification pour la synchronisation des appareils
+    sql(r#"
+    ALTER TABLE contacts ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+    ALTER TABLE contacts ADD COLUMN seq INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE contacts ADD COLUMN deleted INTEGER NOT NULL DEFAULT 0;
+
+    UPDATE contacts SET seq = rowid;
+
+    CREATE INDEX idx_contacts_seq ON contacts(seq);
+    "#),
+    // 3. Clés normalisées pour la détection des doublons
+    Migration {
+        sql: r#"
+        ALTER TABLE contacts ADD COLUMN email_key TEXT;
+        ALTER TABLE contacts ADD COLUMN phone_key TEXT;
+        ALTER TABLE contacts ADD COLUMN name_key TEXT;
+
+        CREATE INDEX idx_contacts_email_key ON contacts(email_key);
+        CREATE INDEX idx_contacts_phone_key ON contacts(phone_key);
+        CREATE INDEX idx_contacts_name_key ON contacts(name_key);
+        "#,
+        backfill: Some(backfill_duplicate_keys),
+    },
+    // 4. File d'envoi des emails d'export
+    sql(r#"
+    CREATE TABLE email_outbox (
+        id              TEXT PRIMARY KEY,
+        device_id       TEXT,
+        recipient       TEXT NOT NULL,
+        subject         TEXT NOT NULL,
+        payload         TEXT NOT NULL,
+        contact_ids     TEXT NOT NULL,
+        state           TEXT NOT NULL,
+        attempts        INTEGER NOT NULL DEFAULT 0,
+        next_attempt_at INTEGER NOT NULL,
+        last_error_kind TEXT,
+        last_error      TEXT,
+        message_id      TEXT,
+        provider        TEXT,
+        created_at      INTEGER NOT NULL,
+        updated_at      INTEGER NOT NULL,
+        sent_at         INTEGER
+    );
+
+    CREATE INDEX idx_email_outbox_due ON email_outbox(state, next_attempt_at);
+    CREATE INDEX idx_email_outbox_device ON email_outbox(device_id, created_at);
+    "#),
+    // 5. Salon associé à un export (rapport PDF)
+    sql(r#"
+    ALTER TABLE email_outbox ADD COLUMN salon TEXT;
+    "#),
+    // 6. Compteurs d'audit ; photos déjà stockées redressées et sans métadonnées
+    Migration {
+        sql: r#"
+        CREATE TABLE audit_counters (
+            name       TEXT PRIMARY KEY,
+            value      INTEGER NOT NULL DEFAULT 0,
+            updated_at INTEGER NOT NULL
+        );
+        "#,
+        backfill: Some(process_stored_photos),
+    },
+    // 7. Photos déposées dans un stockage externe (clé de l'objet) ; objets à supprimer
+    sql(r#"
+    ALTER TABLE contact_photos ADD COLUMN storage_key TEXT;
+    ALTER TABLE contact_photos ADD COLUMN size_bytes INTEGER;
+
+    UPDATE contact_photos SET size_bytes = LENGTH(data);
+
+    CREATE TABLE released_photo_objects (
+        storage_key TEXT PRIMARY KEY,
+        released_at INTEGER NOT NULL
+    );
+
+    CREATE TRIGGER release_photo_object AFTER DELETE ON contact_photos
+    WHEN OLD.storage_key IS NOT NULL
+    BEGIN
+        INSERT OR IGNORE INTO released_photo_objects (storage_key, released_at)
+        VALUES (OLD.storage_key, CAST(strftime('%s', 'now') AS INTEGER) * 1000);
+    END;
+    "#),
+    // 8. Clés d'API des appareils (empreinte seulement)
+    sql(r#"
+    CREATE TABLE api_keys (
+        id           TEXT PRIMARY KEY,
+        device_id    TEXT,
+        label        TEXT NOT NULL,
+        prefix       TEXT NOT NULL,
+        key_hash     TEXT NOT NULL UNIQUE,
+        scopes       TEXT NOT NULL,
+        created_at   INTEGER NOT NULL,
+        expires_at   INTEGER,
+        last_used_at INTEGER,
+        revoked_at   INTEGER
+    );
+    "#),
+    // 9. Secret de signature des requêtes, par clé (les clés existantes n'en ont pas)
+    sql(r#"
+    ALTER TABLE api_keys ADD COLUMN signing_secret TEXT;
+    "#),
+    // 10. Comptes du back-office et jetons de rafraîchissement (empreintes seulement)
+    sql(r#"
+    CREATE TABLE users (
+        id            TEXT PRIMARY KEY,
+        username      TEXT NOT NULL UNIQUE COLLATE NOCASE,
+        password_hash TEXT NOT NULL,
+        role          TEXT NOT NULL,
+        created_at    INTEGER NOT NULL,
+        disabled_at   INTEGER
+    );
+
+    CREATE TABLE refresh_tokens (
+        token_hash TEXT PRIMARY KEY,
+        user_id    TEXT NOT NULL REFERENCES users(id),
+        created_at INTEGER NOT NULL,
+        expires_at INTEGER NOT NULL,
+        revoked_at INTEGER
+    );
+
+    CREATE INDEX idx_refresh_tokens_user ON refresh_tokens(user_id);
+    "#),
+    // 11. Destinataires autorisés propres à une clé (JSON ; politique globale si NULL)
+    sql(r#"
+    ALTER TABLE api_keys ADD COLUMN recipients TEXT;
+    "#),
+];
+
+/// Applique les migrations manquantes
+pub fn run(conn: &mut Connection) -> StorageResult<()> {
+    let current: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(current as usize) {
+        let version = index as i64 + 1;
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration.sql)?;
+        if let Some(backfill) = migration.backfill {
+            backfill(&tx)?;
+        }
+        tx.pragma_update(None, "user_version", version)?;
+        tx.commit()?;
+
+        info!(version, "Migration appliquée");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrations_are_idempotent() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        run(&mut conn).unwrap();
+        run(&mut conn).unwrap();
+
+        let version: i64 = conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .unwrap();
+        assert_eq!(version as usize, MIGRATIONS.len());
+    }
+}
@@ -0,0 +1,157 @@
+//! Persistance locale des données (SQLite).
+//!
+//! Le serveur devient le système de référence des fiches collectées :
+//! chaque fiche reçue est enregistrée avant l'envoi de l'email.
+//!
+//! Les accès à SQLite sont synchrones ; ils sont exécutés sur le pool
+//! bloquant de tokio pour ne pas bloquer les workers actix.
+
+mod api_keys;
+mod audit;
+mod contacts;
+mod migrations;
+mod outbox;
+mod sync;
+mod users;
+
+pub use api_keys::ApiKeyRepository;
+pub use audit::AuditRepository;
+pub use contacts::{ContactFilter, ContactRepository};
+pub use outbox::{ExportJobFilter, OutboxJob, OutboxRepository};
+pub use users::UserRepository;
+
+use rusqlite::{Connection, OpenFlags};
+use std::sync::{Arc, Mutex};
+use tracing::info;
+
+/// Résultat d'une opération de persistance
+pub type StorageResult<T> = Result<T, StorageError>;
+
+/// Erreurs possibles de la couche de persistance
+#[derive(Debug, thiserror::Error)]
+pub enum StorageError {
+    #[error("Erreur base de données: {0}")]
+    Database(#[from] rusqlite::Error),
+
+    #[error("URL de base de données invalide: {0}")]
+    InvalidUrl(String),
+
+    #[error("Données invalides: {0}")]
+    InvalidData(String),
+
+    #[error("Tâche base de données interrompue: {0}")]
+    TaskFailed(String),
+}
+
+/// Connexion partagée à la base SQLite
+#[derive(Clone)]
+pub struct Database {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl Database {
+    /// Ouvre la base depuis une URL `sqlite:chemin?mode=rwc` et applique les migrations
+    pub fn open(url: &str) -> StorageResult<Self> {
+        let (path, flags) = parse_sqlite_url(url)?;
+
+        let conn = if path == ":memory:" {
+            Connection::open_in_memory()?
+        } else {
+            Connection::open_with_flags(&path, flags)?
+        };
+
+        info!(path = %path, "Base de données ouverte");
+        Self::from_connection(conn)
+    }
+
+    /// Ouvre une base en mémoire (tests)
+    #[cfg(test)]
+    pub fn open_in_memory() -> StorageResult<Self> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(mut conn: Connection) -> StorageResult<Self> {
+        conn.pragma_update(None, "foreign_keys", "ON")?;
+        conn.busy_timeout(std::time::Duration::from_secs(5))?;
+        migrations::run(&mut conn)?;
+
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    /// Exécute une opération sur la connexion dans le pool bloquant
+    pub async fn call<F, R>(&self, f: F) -> StorageResult<R>
+    where
+        F: FnOnce(&mut Connection) -> StorageResult<R> + Send + 'static,
+        R: Send + 'static,
+    {
+        let conn = self.conn.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let mut guard = conn
+                .lock()
+                .map_err(|_| StorageError::TaskFailed("verrou empoisonné".to_string()))?;
+            f(&mut guard)
+        })
+        .await
+        .map_err(|e| StorageError::TaskFailed(e.to_string()))?
+    }
+}
+
+/// Décompose une URL `sqlite:` en chemin et drapeaux d'ouverture
+fn parse_sqlite_url(url: &str) -> StorageResult<(String, OpenFlags)> {
+    let rest = url
+        .strip_prefix("sqlite://")
+        .or_else(|| url.strip_prefix("sqlite:"))
+        .ok_or_else(|| StorageError::InvalidUrl(url.to_string()))?;
+
+    let (path, query) = match rest.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (rest, None),
+    };
+
+    if path.is_empty() {
+        return Err(StorageError::InvalidUrl(url.to_string()));
+    }
+
+    let mode = query
+        .into_iter()
+        .flat_map(|q| q.split('&'))
+        .find_map(|param| param.strip_prefix("mode="))
+        .unwrap_or("rwc");
+
+    let base = OpenFlags::SQLITE_OPEN_NO_MUTEX | OpenFlags::SQLITE_OPEN_URI;
+    let flags = match mode {
+        "ro" => base | OpenFlags::SQLITE_OPEN_READ_ONLY,
+        "rw" => base | OpenFlags::SQLITE_OPEN_READ_WRITE,
+        "rwc" => base | OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE,
+        other => return Err(StorageError::InvalidUrl(format!("mode inconnu: {}", other))),
+    };
+
+    Ok((path.to_string(), flags))
+}
+
+/// Horodatage courant en millisecondes
+pub(crate) fn now_millis() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sqlite_url() {
+        let (path, flags) = parse_sqlite_url("sqlite:contacts.db?mode=rwc").unwrap();
+        assert_eq!(path, "contacts.db");
+        assert!(flags.contains(OpenFlags::SQLITE_OPEN_CREATE));
+
+        let (path, flags) = parse_sqlite_url("sqlite://data/contacts.db?mode=ro").unwrap();
+        assert_eq!(path, "data/contacts.db");
+        assert!(flags.contains(OpenFlags::SQLITE_OPEN_READ_ONLY));
+
+        assert!(parse_sqlite_url("postgres://localhost/db").is_err());
+        assert!(parse_sqlite_url("sqlite:db?mode=weird").is_err());
+    }
+}
@@ -0,0 +1,490 @@
+//! File d'envoi persistante des emails d'export.
+//!
+//! Chaque export est enregistré dans `email_outbox` avant d'être envoyé par
+//! le worker de la file : une coupure réseau ou une indisponibilité du
+//! provider ne fait plus perdre l'envoi, qui est retenté plus tard.
+
+use super::{now_millis, Database, StorageError, StorageResult};
+use crate::domain::{Email, ExportJob, ExportJobState};
+use rusqlite::{params, OptionalExtension, Row, ToSql};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+/// Colonnes lues pour reconstruire le suivi d'un envoi
+const JOB_COLUMNS: &str =
+    "id, device_id, recipient, subject, state, attempts, last_error_kind, last_error, \
+     message_id, provider, contact_ids, created_at, updated_at, next_attempt_at, sent_at, salon";
+
+/// Critères de recherche des envois
+#[derive(Debug, Clone, Default)]
+pub struct ExportJobFilter {
+    pub device_id: Option<String>,
+    /// Envois créés à partir de cette date (ms)
+    pub since: Option<i64>,
+    /// Envois créés avant cette date (ms)
+    pub until: Option<i64>,
+    pub limit: u32,
+    pub offset: u32,
+}
+
+/// Envoi réservé par le worker
+#[derive(Debug, Clone)]
+pub struct OutboxJob {
+    pub id: String,
+    pub email: Email,
+    /// Numéro de la tentative en cours (1 pour le premier envoi)
+    pub attempt: u32,
+}
+
+/// Accès à la file d'envoi
+#[derive(Clone)]
+pub struct OutboxRepository {
+    db: Database,
+    /// Réveille le worker dès qu'un envoi est ajouté
+    notify: Arc<Notify>,
+}
+
+impl OutboxRepository {
+    pub fn new(db: Database) -> Self {
+        Self {
+            db,
+            notify: Arc::new(Notify::new()),
+        }
+    }
+
+    /// Ajoute un email à la file et retourne l'identifiant de l'envoi (tests)
+    #[cfg(test)]
+    pub async fn enqueue(
+        &self,
+        email: Email,
+        device_id: Option<String>,
+        contact_ids: Vec<String>,
+        salon: Option<String>,
+    ) -> StorageResult<String> {
+        let mut ids = self.enqueue_all(vec![(email, contact_ids)], device_id, salon).await?;
+        Ok(ids.remove(0))
+    }
+
+    /// Ajoute plusieurs emails à la file (parties d'un même export), tous ou aucun
+    ///
+    /// Retourne les identifiants des envois dans l'ordre des emails.
+    pub async fn enqueue_all(
+        &self,
+        emails: Vec<(Email, Vec<String>)>,
+        device_id: Option<String>,
+        salon: Option<String>,
+    ) -> StorageResult<Vec<String>> {
+        let mut jobs = Vec::with_capacity(emails.len());
+        for (email, contact_ids) in emails {
+            let payload = serde_json::to_string(&email).map_err(|e| {
+                StorageError::InvalidData(format!("email non sérialisable: {}", e))
+            })?;
+            let contact_ids = serde_json::to_string(&contact_ids)
+                .map_err(|e| StorageError::InvalidData(e.to_string()))?;
+            jobs.push((Uuid::new_v4().to_string(), email, payload, contact_ids));
+        }
+        let ids: Vec<String> = jobs.iter().map(|(id, ..)| id.clone()).collect();
+
+        self.db
+            .call(move |conn| {
+                let now = now_millis();
+                let tx = conn.transaction()?;
+                for (id, email, payload, contact_ids) in jobs {
+                    tx.execute(
+                        "INSERT INTO email_outbox (id, device_id, recipient, subject, payload, \
+                         contact_ids, state, attempts, next_attempt_at, created_at, \
+                         updated_at, salon) \
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 0, ?8, ?8, ?8, ?9)",
+                        params![
+                            id,
+                            device_id,
+                            email.to,
+                            email.subject,
+                            payload,
+                            contact_ids,
+                            ExportJobState::Queued.as_str(),
+                            now,
+                            salon,
+                        ],
+                    )?;
+                }
+                tx.commit()?;
+                Ok(())
+            })
+            .await?;
+
+        self.notify.notify_one();
+        Ok(ids)
+    }
+
+    /// Réserve les envois dont l'échéance est passée et les passe à l'état `sending`
+    pub async fn claim_due(&self, limit: u32) -> StorageResult<Vec<OutboxJob>> {
+        self.db
+            .call(move |conn| {
+                let tx = conn.transaction()?;
+                let now = now_millis();
+
+                // À échéance égale, les parties d'un même export partent dans l'ordre
+                let due = {
+                    let mut stmt = tx.prepare(
+                        "SELECT id, payload, attempts FROM email_outbox \
+                         WHERE state = ?1 AND next_attempt_at <= ?2 \
+                         ORDER BY next_attempt_at, rowid LIMIT ?3",
+                    )?;
+                    let rows = stmt.query_map(
+                        params![ExportJobState::Queued.as_str(), now, limit as i64],
+                        |row| {
+                            Ok((
+                                row.get::<_, String>(0)?,
+                                row.get::<_, String>(1)?,
+                                row.get::<_, u32>(2)?,
+                            ))
+                        },
+                    )?;
+                    rows.collect::<Result<Vec<_>, _>>()?
+                };
+
+                let mut jobs = Vec::with_capacity(due.len());
+                for (id, payload, attempts) in due {
+                    // Un envoi illisible ne bloque pas les autres : il est écarté
+                    let email: Email = match serde_json::from_str(&payload) {
+                        Ok(email) => email,
+                        Err(e) => {
+                            tracing::error!(job_id = %id, error = %e, "Envoi illisible écarté");
+                            tx.execute(
+                                "UPDATE email_outbox SET state = ?2, last_error_kind = ?3, \
+                                 last_error = ?4, updated_at = ?5 WHERE id = ?1",
+                                params![
+                                    id,
+                                    ExportJobState::DeadLettered.as_str(),
+                                    "invalid_payload",
+                                    format!("envoi illisible: {}", e),
+                                    now,
+                                ],
+                            )?;
+                            continue;
+                        }
+                    };
+                    tx.execute(
+                        "UPDATE email_outbox SET state = ?2, attempts = attempts + 1, \
+                         updated_at = ?3 WHERE id = ?1",
+                        params![id, ExportJobState::Sending.as_str(), now],
+                    )?;
+                    jobs.push(OutboxJob {
+                        id,
+                        email,
+                        attempt: attempts + 1,
+                    });
+                }
+
+                tx.commit()?;
+                Ok(jobs)
+            })
+            .await
+    }
+
+    /// Enregistre un envoi réussi
+    pub async fn mark_sent(
+        &self,
+        id: &str,
+        message_id: String,
+        provider: &'static str,
+    ) -> StorageResult<()> {
+        let id = id.to_string();
+        self.db
+            .call(move |conn| {
+                let now = now_millis();
+                conn.execute(
+                    "UPDATE email_outbox SET state = ?2, message_id = ?3, provider = ?4, \
+                     sent_at = ?5, updated_at = ?5 WHERE id = ?1",
+                    params![id, ExportJobState::Sent.as_str(), message_id, provider, now],
+                )?;
+                Ok(())
+            })
+            .await
+    }
+
+    /// Enregistre un échec et planifie une nouvelle tentative après `delay`
+    pub async fn mark_retry(
+        &self,
+        id: &str,
+        error_kind: &'static str,
+        error: String,
+        delay: Duration,
+    ) -> StorageResult<()> {
+        let id = id.to_string();
+        self.db
+            .call(move |conn| {
+                let now = now_millis();
+                conn.execute(
+                    "UPDATE email_outbox SET state = ?2, last_error_kind = ?3, last_error = ?4, \
+                     next_attempt_at = ?5, updated_at = ?6 WHERE id = ?1",
+                    params![
+                        id,
+                        ExportJobState::Queued.as_str(),
+                        error_kind,
+                        error,
+                        now + delay.as_millis() as i64,
+                        now,
+                    ],
+                )?;
+                Ok(())
+            })
+            .await
+    }
+
+    /// Enregistre un échec définitif (`Failed` ou `DeadLettered`)
+    pub async fn mark_failed(
+        &self,
+        id: &str,
+        state: ExportJobState,
+        error_kind: &'static str,
+        error: String,
+    ) -> StorageResult<()> {
+        let id = id.to_string();
+        self.db
+            .call(move |conn| {
+                conn.execute(
+                    "UPDATE email_outbox SET state = ?2, last_error_kind = ?3, last_error = ?4, \
+                     updated_at = ?5 WHERE id = ?1",
+                    params![id, state.as_str(), error_kind, error, now_millis()],
+                )?;
+                Ok(())
+            })
+            .await
+    }
+
+    /// Remet en file les envois interrompus par un arrêt du serveur
+    pub async fn requeue_interrupted(&self) -> StorageResult<usize> {
+        self.db
+            .call(|conn| {
+                Ok(conn.execute(
+                    "UPDATE email_outbox SET state = ?1, updated_at = ?3 WHERE state = ?2",
+                    params![
+                        ExportJobState::Queued.as_str(),
+                        ExportJobState::Sending.as_str(),
+                        now_millis(),
+                    ],
+                )?)
+            })
+            .await
+    }
+
+    /// Attend un nouvel envoi, au plus `timeout`
+    pub async fn wait_for_jobs(&self, timeout: Duration) {
+        let _ = tokio::time::timeout(timeout, self.notify.notified()).await;
+    }
+
+    /// Lit le suivi d'un envoi
+    pub async fn get(&self, id: &str) -> StorageResult<Option<ExportJob>> {
+        let id = id.to_string();
+        self.db
+            .call(move |conn| {
+                Ok(conn
+                    .query_row(
+                        &format!("SELECT {} FROM email_outbox WHERE id = ?1", JOB_COLUMNS),
+                        params![id],
+                        job_from_row,
+                    )
+                    .optional()?)
+            })
+            .await
+    }
+
+    /// Liste les envois selon un filtre (plus récents d'abord), avec le nombre total
+    pub async fn list(&self, filter: ExportJobFilter) -> StorageResult<(Vec<ExportJob>, i64)> {
+        self.db
+            .call(move |conn| {
+                let mut clauses = vec!["1 = 1"];
+                let mut args: Vec<Box<dyn ToSql>> = Vec::new();
+
+                if let Some(device_id) = filter.device_id {
+                    clauses.push("device_id = ?");
+                    args.push(Box::new(device_id));
+                }
+                if let Some(since) = filter.since {
+                    clauses.push("created_at >= ?");
+                    args.push(Box::new(since));
+                }
+                if let Some(until) = filter.until {
+                    clauses.push("created_at < ?");
+                    args.push(Box::new(until));
+                }
+
+                let where_sql = format!("WHERE {}", clauses.join(" AND "));
+                let arg_refs: Vec<&dyn ToSql> = args.iter().map(|a| a.as_ref()).collect();
+
+                let total: i64 = conn.query_row(
+                    &format!("SELECT COUNT(*) FROM email_outbox {}", where_sql),
+                    arg_refs.as_slice(),
+                    |row| row.get(0),
+                )?;
+
+                let mut stmt = conn.prepare(&format!(
+                    "SELECT {} FROM email_outbox {} \
+                     ORDER BY created_at DESC, id LIMIT {} OFFSET {}",
+                    JOB_COLUMNS, where_sql, filter.limit, filter.offset
+                ))?;
+                let jobs = stmt
+                    .query_map(arg_refs.as_slice(), job_from_row)?
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                Ok((jobs, total))
+            })
+            .await
+    }
+}
+
+/// Reconstruit le suivi d'un envoi à partir d'une ligne (`JOB_COLUMNS`)
+fn job_from_row(row: &Row<'_>) -> rusqlite::Result<ExportJob> {
+    let state: String = row.get(4)?;
+    let state = ExportJobState::parse(&state).ok_or_else(|| {
+        rusqlite::Error::FromSqlConversionFailure(
+            4,
+            rusqlite::types::Type::Text,
+            format!("état d'envoi inconnu: {}", state).into(),
+        )
+    })?;
+    let contact_ids: String = row.get(10)?;
+    let contact_ids = serde_json::from_str(&contact_ids).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(10, rusqlite::types::Type::Text, e.into())
+    })?;
+
+    Ok(ExportJob {
+        id: row.get(0)?,
+        device_id: row.get(1)?,
+        recipient: row.get(2)?,
+        subject: row.get(3)?,
+        state,
+        attempts: row.get(5)?,
+        last_error_kind: row.get(6)?,
+        last_error: row.get(7)?,
+        message_id: row.get(8)?,
+        provider: row.get(9)?,
+        contact_ids,
+        created_at: row.get(11)?,
+        updated_at: row.get(12)?,
+        next_attempt_at: (state == ExportJobState::Queued)
+            .then(|| row.get(13))
+            .transpose()?,
+        sent_at: row.get(14)?,
+        salon: row.get(15)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn email() -> Email {
+        Email {
+            to: "commercial@smp-moules.com".to_string(),
+            subject: "Export".to_string(),
+            html_body: "<p>Bonjour</p>".to_string(),
+            attachments: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_jobs_are_claimed_once_and_retried_after_delay() {
+        let outbox = OutboxRepository::new(Database::open_in_memory().unwrap());
+        let id = outbox.enqueue(email(), Some("tablet-1".into()), vec![], None).await.unwrap();
+
+        let jobs = outbox.claim_due(10).await.unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].attempt, 1);
+        assert_eq!(jobs[0].email.to, "commercial@smp-moules.com");
+        assert!(outbox.claim_due(10).await.unwrap().is_empty());
+
+        // Nouvelle tentative planifiée dans le futur : pas encore due
+        outbox
+            .mark_retry(&id, "connection_error", "timeout".into(), Duration::from_secs(60))
+            .await
+            .unwrap();
+        assert!(outbox.claim_due(10).await.unwrap().is_empty());
+
+        outbox.mark_retry(&id, "rate_limited", "429".into(), Duration::ZERO).await.unwrap();
+        let jobs = outbox.claim_due(10).await.unwrap();
+        assert_eq!(jobs[0].attempt, 2);
+    }
+
+    #[tokio::test]
+    async fn test_export_parts_are_claimed_in_order() {
+        let outbox = OutboxRepository::new(Database::open_in_memory().unwrap());
+        let parts = (1..=3).map(|n| (email(), vec![format!("c{}", n)])).collect();
+        let ids = outbox.enqueue_all(parts, None, Some("Pharmapack".into())).await.unwrap();
+        assert_eq!(ids.len(), 3);
+
+        let claimed: Vec<String> =
+            outbox.claim_due(10).await.unwrap().into_iter().map(|j| j.id).collect();
+        assert_eq!(claimed, ids);
+        let job = outbox.get(&ids[2]).await.unwrap().unwrap();
+        assert_eq!(job.contact_ids, vec!["c3".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_unreadable_job_is_dead_lettered_without_blocking_others() {
+        let outbox = OutboxRepository::new(Database::open_in_memory().unwrap());
+        let broken = outbox.enqueue(email(), None, vec![], None).await.unwrap();
+        let ok = outbox.enqueue(email(), None, vec![], None).await.unwrap();
+        let id = broken.clone();
+        outbox
+            .db
+            .call(move |conn| {
+                conn.execute("UPDATE email_outbox SET payload = '{' WHERE id = ?1", [id])?;
+                Ok(())
+            })
+            .await
+            .unwrap();
+
+        let claimed: Vec<String> =
+            outbox.claim_due(10).await.unwrap().into_iter().map(|j| j.id).collect();
+        assert_eq!(claimed, vec![ok]);
+        let job = outbox.get(&broken).await.unwrap().unwrap();
+        assert_eq!(job.state, ExportJobState::DeadLettered);
+        assert_eq!(job.last_error_kind.as_deref(), Some("invalid_payload"));
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_jobs_are_requeued() {
+        let outbox = OutboxRepository::new(Database::open_in_memory().unwrap());
+        let id = outbox.enqueue(email(), None, vec![], None).await.unwrap();
+        outbox.claim_due(10).await.unwrap();
+
+        assert_eq!(outbox.requeue_interrupted().await.unwrap(), 1);
+        let job = outbox.get(&id).await.unwrap().unwrap();
+        assert_eq!(job.state, ExportJobState::Queued);
+        assert_eq!(job.attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn test_list_filters_by_device_and_date() {
+        let outbox = OutboxRepository::new(Database::open_in_memory().unwrap());
+        let sent = outbox
+            .enqueue(email(), Some("tablet-1".into()), vec!["c1".into()], None)
+            .await
+            .unwrap();
+        outbox.enqueue(email(), Some("tablet-2".into()), vec![], None).await.unwrap();
+        outbox.mark_sent(&sent, "msg-1".into(), "resend").await.unwrap();
+
+        let filter = ExportJobFilter {
+            device_id: Some("tablet-1".into()),
+            limit: 50,
+            ..Default::default()
+        };
+        let (jobs, total) = outbox.list(filter).await.unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(jobs[0].state, ExportJobState::Sent);
+        assert_eq!(jobs[0].message_id.as_deref(), Some("msg-1"));
+        assert_eq!(jobs[0].contact_ids, vec!["c1".to_string()]);
+
+        let filter = ExportJobFilter {
+            since: Some(now_millis() + 60_000),
+            limit: 50,
+            ..Default::default()
+        };
+        assert_eq!(outbox.list(filter).await.unwrap().1, 0);
+    }
+}
@@ -0,0 +1,255 @@
+//! Synchronisation bidirectionnelle des fiches avec les appareils.
+//!
+//! Chaque écriture attribue à la fiche un numéro de séquence croissant ;
+//! un appareil envoie le dernier numéro reçu (curseur) et récupère toutes
+//! les modifications postérieures. Les modifications locales portent la
+//! version serveur sur laquelle elles se basent : si la fiche a changé
+//! entre-temps, la modification est refusée et signalée comme conflit.
+
+use super::contacts::{
+    contact_from_row, delete_contact, find_contact, insert_contact, load_photos, update_contact,
+    CONTACT_COLUMNS,
+};
+use super::{ContactRepository, StorageError, StorageResult};
+use crate::domain::{StoredContact, SyncApplied, SyncChange, SyncConflict};
+use rusqlite::{params, Transaction};
+
+/// Résultat d'une synchronisation
+#[derive(Debug)]
+pub struct SyncOutcome {
+    pub applied: Vec<SyncApplied>,
+    pub conflicts: Vec<SyncConflict>,
+    pub changes: Vec<StoredContact>,
+    pub cursor: i64,
+    pub has_more: bool,
+}
+
+impl ContactRepository {
+    /// Applique les modifications d'un appareil puis retourne les modifications
+    /// serveur postérieures à son curseur (au plus `limit`)
+    ///
+    /// Avec `owner` (clé remise à un appareil), seules les fiches de cet
+    /// appareil sont renvoyées, et celles des autres ne peuvent être modifiées.
+    pub async fn sync(
+        &self,
+        device_id: String,
+        owner: Option<String>,
+        cursor: i64,
+        changes: Vec<SyncChange>,
+        limit: u32,
+    ) -> StorageResult<SyncOutcome> {
+        self.db
+            .call(move |conn| {
+                let tx = conn.transaction()?;
+
+                let mut applied = Vec::new();
+                let mut conflicts = Vec::new();
+                for change in changes {
+                    match apply_change(&tx, &device_id, owner.as_deref(), change)? {
+                        Ok(done) => applied.push(done),
+                        Err(conflict) => conflicts.push(conflict),
+                    }
+                }
+
+                let (changes, has_more) = changes_since(&tx, owner.as_deref(), cursor, limit)?;
+                let cursor = changes.last().map(|(_, seq)| *seq).unwrap_or(cursor);
+                let changes = changes.into_iter().map(|(contact, _)| contact).collect();
+
+                tx.commit()?;
+
+                Ok(SyncOutcome {
+                    applied,
+                    conflicts,
+                    changes,
+                    cursor,
+                    has_more,
+                })
+            })
+            .await
+    }
+}
+
+/// Applique une modification si elle se base sur la version serveur courante
+fn apply_change(
+    tx: &Transaction<'_>,
+    device_id: &str,
+    owner: Option<&str>,
+    change: SyncChange,
+) -> StorageResult<Result<SyncApplied, SyncConflict>> {
+    let current = find_contact(tx, &change.id)?;
+
+    let conflict = |reason: &str, server: StoredContact| {
+        Ok(Err(SyncConflict {
+            id: change.id.clone(),
+            reason: reason.to_string(),
+            server: Some(server),
+        }))
+    };
+
+    match current {
+        // Fiche d'un autre appareil : ni modifiée ni dévoilée
+        Some(server) if owner.is_some_and(|o| server.device_id.as_deref() != Some(o)) => {
+            Ok(Err(SyncConflict {
+                id: change.id,
+                reason: "Fiche d'un autre appareil".to_string(),
+                server: None,
+            }))
+        }
+
+        // Fiche inconnue : création (une suppression n'a rien à faire)
+        None if change.deleted => Ok(Ok(SyncApplied {
+            id: change.id,
+            version: 0,
+        })),
+        None => {
+            let fiche = change.fiche.ok_or_else(|| missing_fiche(&change.id))?;
+            let stored = insert_contact(tx, Some(change.id), fiche, Some(device_id.to_string()))?;
+            Ok(Ok(SyncApplied {
+                id: stored.id,
+                version: stored.version,
+            }))
+        }
+
+        // Suppression déjà propagée : rien à faire
+        Some(server) if server.deleted && change.deleted => Ok(Ok(SyncApplied {
+            id: change.id,
+            version: server.version,
+        })),
+
+        Some(server) if change.base_version != Some(server.version) => {
+            conflict("Fiche modifiée sur le serveur depuis la dernière synchronisation", server)
+        }
+        Some(server) if server.deleted => conflict("Fiche supprimée sur le serveur", server),
+
+        Some(server) => {
+            if change.deleted {
+                delete_contact(tx, &change.id)?;
+            } else {
+                let fiche = change.fiche.ok_or_else(|| missing_fiche(&change.id))?;
+                update_contact(tx, &change.id, fiche)?;
+            }
+            Ok(Ok(SyncApplied {
+                id: change.id,
+                version: server.version + 1,
+            }))
+        }
+    }
+}
+
+fn missing_fiche(id: &str) -> StorageError {
+    StorageError::InvalidData(format!("contenu manquant pour la fiche {}", id))
+}
+
+/// Modifications postérieures au curseur (des fiches de `owner` s'il est
+/// donné), avec leur numéro de séquence
+fn changes_since(
+    tx: &Transaction<'_>,
+    owner: Option<&str>,
+    cursor: i64,
+    limit: u32,
+) -> StorageResult<(Vec<(StoredContact, i64)>, bool)> {
+    let mut stmt = tx.prepare(&format!(
+        "SELECT {}, seq FROM contacts WHERE seq > ?1 AND (?3 IS NULL OR device_id = ?3)
+         ORDER BY seq LIMIT ?2",
+        CONTACT_COLUMNS
+    ))?;
+    let mut rows = stmt
+        .query_map(params![cursor, limit as i64 + 1, owner], |row| {
+            Ok((contact_from_row(row)?, row.get::<_, i64>(14)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let has_more = rows.len() > limit as usize;
+    rows.truncate(limit as usize);
+
+    for (contact, _) in rows.iter_mut() {
+        contact.photos = load_photos(tx, &contact.id)?;
+    }
+
+    Ok((rows, has_more))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::contacts::tests::fiche;
+    use super::*;
+    use crate::storage::Database;
+
+    fn change(id: &str, base_version: Option<i64>, societe: &str) -> SyncChange {
+        SyncChange {
+            id: id.to_string(),
+            base_version,
+            deleted: false,
+            fiche: Some(fiche(societe, None)),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_devices_converge_and_concurrent_edits_conflict() {
+        let repo = ContactRepository::new(Database::open_in_memory().unwrap());
+        let id = "6f1c1e0e-8a4e-4a53-9f55-2f1a4c2b7d10";
+
+        // La tablette A crée une fiche
+        let a = repo
+            .sync("tablet-a".into(), None, 0, vec![change(id, None, "ACME")], 100)
+            .await
+            .unwrap();
+        assert_eq!(a.applied[0].version, 1);
+
+        // La tablette B la reçoit
+        let b = repo.sync("tablet-b".into(), None, 0, vec![], 100).await.unwrap();
+        assert_eq!(b.changes.len(), 1);
+        assert_eq!(b.changes[0].fiche.societe, "ACME");
+
+        // A et B corrigent la même fiche à partir de la version 1
+        let a2 = repo
+            .sync("tablet-a".into(), None, a.cursor, vec![change(id, Some(1), "ACME SA")], 100)
+            .await
+            .unwrap();
+        assert!(a2.conflicts.is_empty());
+
+        let b2 = repo
+            .sync("tablet-b".into(), None, b.cursor, vec![change(id, Some(1), "Acme")], 100)
+            .await
+            .unwrap();
+        assert_eq!(b2.conflicts.len(), 1);
+        let server = b2.conflicts[0].server.as_ref().unwrap();
+        assert_eq!(server.fiche.societe, "ACME SA");
+        assert_eq!(server.version, 2);
+
+        // B reçoit quand même la version gagnante dans les modifications
+        assert_eq!(b2.changes.last().unwrap().fiche.societe, "ACME SA");
+    }
+
+    #[tokio::test]
+    async fn test_deletions_are_propagated() {
+        let repo = ContactRepository::new(Database::open_in_memory().unwrap());
+        let stored = repo.insert(fiche("ACME", None), None).await.unwrap();
+
+        let initial = repo.sync("tablet-a".into(), None, 0, vec![], 100).await.unwrap();
+        repo.delete(&stored.id).await.unwrap();
+
+        let after = repo
+            .sync("tablet-a".into(), None, initial.cursor, vec![], 100)
+            .await
+            .unwrap();
+        assert_eq!(after.changes.len(), 1);
+        assert!(after.changes[0].deleted);
+    }
+
+    #[tokio::test]
+    async fn test_changes_are_paginated() {
+        let repo = ContactRepository::new(Database::open_in_memory().unwrap());
+        repo.insert_many(vec![fiche("A", None), fiche("B", None), fiche("C", None)], None)
+            .await
+            .unwrap();
+
+        let first = repo.sync("tablet-a".into(), None, 0, vec![], 2).await.unwrap();
+        assert_eq!(first.changes.len(), 2);
+        assert!(first.has_more);
+
+        let second = repo.sync("tablet-a".into(), None, first.cursor, vec![], 2).await.unwrap();
+        assert_eq!(second.changes.len(), 1);
+        assert!(!second.has_more);
+    }
+}
@@ -0,0 +1,344 @@
+//! Comptes du back-office et jetons de rafraîchissement.
+//!
+//! Les mots de passe sont hachés avec argon2, hors du verrou de la base : le
+//! hachage est volontairement coûteux. Les jetons de rafraîchissement sont
+//! aléatoires, à usage unique, et seule leur empreinte est enregistrée.
+
+use super::api_keys::{key_hash, random_token};
+use super::{now_millis, Database, StorageError, StorageResult};
+use crate::domain::{CreateUserRequest, Role, User};
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use rusqlite::{params, OptionalExtension, Row};
+use std::sync::OnceLock;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Colonnes lues pour reconstruire un compte
+const USER_COLUMNS: &str = "id, username, role, created_at, disabled_at";
+
+/// Accès aux comptes utilisateurs
+#[derive(Clone)]
+pub struct UserRepository {
+    db: Database,
+}
+
+impl UserRepository {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    /// Crée un compte ; retourne `None` si l'identifiant est déjà pris
+    pub async fn create(&self, request: CreateUserRequest) -> StorageResult<Option<User>> {
+        let password_hash = hash_password(request.password).await?;
+        self.db
+            .call(move |conn| {
+                let user = User {
+                    id: Uuid::new_v4().to_string(),
+                    username: request.username,
+                    role: request.role,
+                    created_at: now_millis(),
+                    disabled_at: None,
+                };
+                let inserted = conn.execute(
+                    "INSERT INTO users (id, username, password_hash, role, created_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5)
+                     ON CONFLICT (username) DO NOTHING",
+                    params![
+                        user.id,
+                        user.username,
+                        password_hash,
+                        user.role.as_str(),
+                        user.created_at
+                    ],
+                )?;
+                Ok((inserted > 0).then_some(user))
+            })
+            .await
+    }
+
+    /// Compte actif correspondant aux identifiants
+    ///
+    /// Un identifiant inconnu coûte le même hachage qu'un mot de passe faux :
+    /// la durée de réponse ne révèle pas les comptes existants.
+    pub async fn verify_credentials(
+        &self,
+        username: &str,
+        password: &str,
+    ) -> StorageResult<Option<User>> {
+        let username = username.to_string();
+        let found = self
+            .db
+            .call(move |conn| {
+                conn.query_row(
+                    &format!(
+                        "SELECT {}, password_hash FROM users
+                         WHERE username = ?1 AND disabled_at IS NULL",
+                        USER_COLUMNS
+                    ),
+                    [username],
+                    |row| Ok((user_from_row(row)?, row.get::<_, String>(5)?)),
+                )
+                .optional()
+                .map_err(StorageError::from)
+            })
+            .await?;
+
+        let (user, stored) = match found {
+            Some((user, stored)) => (Some(user), stored),
+            None => (None, dummy_hash().to_string()),
+        };
+        let valid = verify_password(password.to_string(), stored).await?;
+        Ok(user.filter(|_| valid))
+    }
+
+    /// Tous les comptes, par identifiant
+    pub async fn list(&self) -> StorageResult<Vec<User>> {
+        self.db
+            .call(|conn| {
+                let mut stmt = conn.prepare(&format!(
+                    "SELECT {} FROM users ORDER BY username",
+                    USER_COLUMNS
+                ))?;
+                let users = stmt.query_map([], user_from_row)?.collect::<Result<_, _>>()?;
+                Ok(users)
+            })
+            .await
+    }
+
+    /// Désactive un compte et révoque ses jetons de rafraîchissement ;
+    /// retourne `false` s'il n'existe pas ou est déjà désactivé
+    pub async fn disable(&self, id: &str) -> StorageResult<bool> {
+        let id = id.to_string();
+        self.db
+            .call(move |conn| {
+                let now = now_millis();
+                let tx = conn.transaction()?;
+                let disabled = tx.execute(
+                    "UPDATE users SET disabled_at = ?2 WHERE id = ?1 AND disabled_at IS NULL",
+                    params![id, now],
+                )?;
+                revoke_all(&tx, &id, now)?;
+                tx.commit()?;
+                Ok(disabled > 0)
+            })
+            .await
+    }
+
+    /// Émet un jeton de rafraîchissement pour un compte
+    pub async fn issue_refresh_token(&self, user_id: &str, ttl: Duration) -> StorageResult<String> {
+        let user_id = user_id.to_string();
+        self.db
+            .call(move |conn| insert_refresh_token(conn, &user_id, ttl))
+            .await
+    }
+
+    /// Échange un jeton de rafraîchissement contre un nouveau
+    ///
+    /// Le jeton présenté est révoqué. Un jeton déjà utilisé trahit un vol :
+    /// tous les jetons du compte sont alors révoqués.
+    pub async fn rotate_refresh_token(
+        &self,
+        token: &str,
+        ttl: Duration,
+    ) -> StorageResult<Option<(User, String)>> {
+        let hash = key_hash(token);
+        self.db
+            .call(move |conn| {
+                let now = now_millis();
+                let tx = conn.transaction()?;
+                let found = tx
+                    .query_row(
+                        "SELECT user_id, expires_at, revoked_at FROM refresh_tokens
+                         WHERE token_hash = ?1",
+                        [&hash],
+                        |row| {
+                            Ok((
+                                row.get::<_, String>(0)?,
+                                row.get::<_, i64>(1)?,
+                                row.get::<_, Option<i64>>(2)?,
+                            ))
+                        },
+                    )
+                    .optional()?;
+
+                let user_id = match found {
+                    None => return Ok(None),
+                    Some((user_id, _, Some(_))) => {
+                        tracing::warn!(user_id = %user_id, "Jeton de rafraîchissement réutilisé");
+                        revoke_all(&tx, &user_id, now)?;
+                        tx.commit()?;
+                        return Ok(None);
+                    }
+                    Some((_, expires_at, None)) if expires_at <= now => return Ok(None),
+                    Some((user_id, _, None)) => user_id,
+                };
+
+                let user = tx
+                    .query_row(
+                        &format!(
+                            "SELECT {} FROM users WHERE id = ?1 AND disabled_at IS NULL",
+                            USER_COLUMNS
+                        ),
+                        [&user_id],
+                        user_from_row,
+                    )
+                    .optional()?;
+                let Some(user) = user else {
+                    return Ok(None);
+                };
+
+                tx.execute(
+                    "UPDATE refresh_tokens SET revoked_at = ?2 WHERE token_hash = ?1",
+                    params![hash, now],
+                )?;
+                let token = insert_refresh_token(&tx, &user.id, ttl)?;
+                tx.commit()?;
+                Ok(Some((user, token)))
+            })
+            .await
+    }
+
+    /// Révoque un jeton de rafraîchissement (déconnexion)
+    pub async fn revoke_refresh_token(&self, token: &str) -> StorageResult<bool> {
+        let hash = key_hash(token);
+        self.db
+            .call(move |conn| {
+                let revoked = conn.execute(
+                    "UPDATE refresh_tokens SET revoked_at = ?2
+                     WHERE token_hash = ?1 AND revoked_at IS NULL",
+                    params![hash, now_millis()],
+                )?;
+                Ok(revoked > 0)
+            })
+            .await
+    }
+}
+
+fn insert_refresh_token(
+    conn: &rusqlite::Connection,
+    user_id: &str,
+    ttl: Duration,
+) -> StorageResult<String> {
+    let token = random_token();
+    let now = now_millis();
+    conn.execute(
+        "INSERT INTO refresh_tokens (token_hash, user_id, created_at, expires_at)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![key_hash(&token), user_id, now, now + ttl.as_millis() as i64],
+    )?;
+    Ok(token)
+}
+
+fn revoke_all(conn: &rusqlite::Connection, user_id: &str, now: i64) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE refresh_tokens SET revoked_at = ?2 WHERE user_id = ?1 AND revoked_at IS NULL",
+        params![user_id, now],
+    )?;
+    Ok(())
+}
+
+/// Hache un mot de passe (argon2id, paramètres par défaut)
+async fn hash_password(password: String) -> StorageResult<String> {
+    tokio::task::spawn_blocking(move || {
+        let salt = SaltString::generate(&mut OsRng);
+        Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .map_err(|e| StorageError::InvalidData(e.to_string()))
+    })
+    .await
+    .map_err(|e| StorageError::TaskFailed(e.to_string()))?
+}
+
+async fn verify_password(password: String, stored: String) -> StorageResult<bool> {
+    tokio::task::spawn_blocking(move || {
+        let hash = PasswordHash::new(&stored)
+            .map_err(|e| StorageError::InvalidData(e.to_string()))?;
+        Ok(Argon2::default().verify_password(password.as_bytes(), &hash).is_ok())
+    })
+    .await
+    .map_err(|e| StorageError::TaskFailed(e.to_string()))?
+}
+
+/// Empreinte comparée quand l'identifiant est inconnu
+fn dummy_hash() -> &'static str {
+    static DUMMY: OnceLock<String> = OnceLock::new();
+    DUMMY.get_or_init(|| {
+        Argon2::default()
+            .hash_password(b"compte-inexistant", &SaltString::generate(&mut OsRng))
+            .map(|hash| hash.to_string())
+            .unwrap_or_default()
+    })
+}
+
+fn user_from_row(row: &Row<'_>) -> rusqlite::Result<User> {
+    let role: String = row.get(2)?;
+    Ok(User {
+        id: row.get(0)?,
+        username: row.get(1)?,
+        // Un rôle inconnu (retiré depuis) ne garde que la consultation
+        role: Role::parse(&role).unwrap_or(Role::Viewer),
+        created_at: row.get(3)?,
+        disabled_at: row.get(4)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TTL: Duration = Duration::from_secs(3600);
+
+    async fn repo_with_user() -> (UserRepository, User) {
+        let repo = UserRepository::new(Database::open_in_memory().unwrap());
+        let user = repo
+            .create(CreateUserRequest {
+                username: "claire".to_string(),
+                password: "mot-de-passe-solide".to_string(),
+                role: Role::Manager,
+            })
+            .await
+            .unwrap()
+            .unwrap();
+        (repo, user)
+    }
+
+    #[tokio::test]
+    async fn test_credentials_are_checked_against_argon2_hash() {
+        let (repo, user) = repo_with_user().await;
+
+        let found = repo.verify_credentials("claire", "mot-de-passe-solide").await.unwrap();
+        assert_eq!(found.unwrap().id, user.id);
+        assert!(repo.verify_credentials("claire", "autre").await.unwrap().is_none());
+        assert!(repo.verify_credentials("inconnu", "x").await.unwrap().is_none());
+
+        let duplicate = CreateUserRequest {
+            username: "claire".to_string(),
+            password: "mot-de-passe-solide".to_string(),
+            role: Role::Admin,
+        };
+        assert!(repo.create(duplicate).await.unwrap().is_none());
+
+        assert!(repo.disable(&user.id).await.unwrap());
+        assert!(repo
+            .verify_credentials("claire", "mot-de-passe-solide")
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_refresh_token_rotation_detects_reuse() {
+        let (repo, user) = repo_with_user().await;
+        let first = repo.issue_refresh_token(&user.id, TTL).await.unwrap();
+
+        let (rotated_user, second) = repo.rotate_refresh_token(&first, TTL).await.unwrap().unwrap();
+        assert_eq!(rotated_user.id, user.id);
+
+        // Réutiliser le premier jeton révoque aussi le second
+        assert!(repo.rotate_refresh_token(&first, TTL).await.unwrap().is_none());
+        assert!(repo.rotate_refresh_token(&second, TTL).await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,293 @@
+//! Réception des exports envoyés en `multipart/form-data`.
+//!
+//! Les fiches sont transmises dans une partie JSON `metadata` (même format
+//! que `/api/export-fiches`) et les photos dans des parties binaires. Une
+//! fiche désigne sa photo par `photo_id`, le nom de la partie qui la contient.
+//! Les photos sont écrites au fil de l'eau dans des fichiers temporaires,
+//! supprimés automatiquement, sans passer par le base64 ni par la mémoire.
+
+use actix_multipart::{Field, Multipart};
+use futures_util::TryStreamExt;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Seek;
+use tokio::io::AsyncWriteExt;
+
+use crate::config::UploadConfig;
+use crate::domain::ExportFichesRequest;
+
+/// Nom de la partie JSON contenant les fiches
+const METADATA_FIELD: &str = "metadata";
+
+/// Taille maximale de la partie JSON (les photos n'y figurent pas)
+const MAX_METADATA_BYTES: usize = 2 * 1024 * 1024;
+
+/// Résultat de lecture d'un envoi multipart
+pub type UploadResult<T> = Result<T, UploadError>;
+
+/// Erreurs possibles lors de la lecture d'un envoi multipart
+#[derive(Debug, thiserror::Error)]
+pub enum UploadError {
+    #[error("Envoi multipart invalide: {0}")]
+    Multipart(#[from] actix_multipart::MultipartError),
+
+    #[error("Partie `metadata` manquante")]
+    MissingMetadata,
+
+    #[error("Partie `metadata` invalide: {0}")]
+    InvalidMetadata(#[from] serde_json::Error),
+
+    #[error("Partie en double: {0}")]
+    DuplicatePart(String),
+
+    #[error("Photo {0} absente de l'envoi ou déjà utilisée par une autre fiche")]
+    MissingPhoto(String),
+
+    #[error("Partie {name} trop volumineuse (maximum {max} octets)")]
+    PartTooLarge { name: String, max: usize },
+
+    #[error("Envoi trop volumineux (maximum {0} octets de photos)")]
+    TooLarge(usize),
+
+    #[error("Trop de photos dans l'envoi (maximum {0})")]
+    TooManyPhotos(usize),
+
+    #[error("Écriture du fichier temporaire impossible: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl UploadError {
+    /// Vrai si la taille de l'envoi est en cause (réponse 413)
+    pub fn is_too_large(&self) -> bool {
+        matches!(self, Self::PartTooLarge { .. } | Self::TooLarge(_) | Self::TooManyPhotos(_))
+    }
+}
+
+/// Export reçu : fiches et fichiers temporaires de leurs photos
+pub struct UploadedExport {
+    pub request: ExportFichesRequest,
+    /// Photo de chaque fiche qui en référence une (position dans `request.contacts`)
+    pub photos: Vec<(usize, File)>,
+}
+
+/// Photo reçue, en attente d'association à sa fiche
+struct UploadedPhoto {
+    file: File,
+    filename: Option<String>,
+}
+
+/// Références des photos dans la partie JSON
+#[derive(Deserialize)]
+struct PhotoIds {
+    contacts: Vec<PhotoId>,
+}
+
+#[derive(Deserialize)]
+struct PhotoId {
+    #[serde(default)]
+    photo_id: Option<String>,
+}
+
+/// Lit un envoi multipart et associe chaque photo à sa fiche
+///
+/// Les parties peuvent arriver dans n'importe quel ordre ; une photo qui
+/// n'est référencée par aucune fiche est ignorée.
+pub async fn read_export(
+    mut payload: Multipart,
+    limits: &UploadConfig,
+) -> UploadResult<UploadedExport> {
+    let mut metadata = None;
+    let mut photos: HashMap<String, UploadedPhoto> = HashMap::new();
+    let mut total = 0;
+
+    while let Some(field) = payload.try_next().await? {
+        let name = field.name().unwrap_or_default().to_string();
+        if name == METADATA_FIELD {
+            if metadata.is_some() {
+                return Err(UploadError::DuplicatePart(name));
+            }
+            metadata = Some(read_metadata(field).await?);
+        } else {
+            if photos.contains_key(&name) {
+                return Err(UploadError::DuplicatePart(name));
+            }
+            // Chaque photo garde un fichier ouvert jusqu'à la fin de la lecture
+            if photos.len() >= limits.max_photos {
+                return Err(UploadError::TooManyPhotos(limits.max_photos));
+            }
+            let filename = field
+                .content_disposition()
+                .and_then(|cd| cd.get_filename())
+                .map(str::to_string);
+            let file = store_photo(field, &name, limits, &mut total).await?;
+            photos.insert(name, UploadedPhoto { file, filename });
+        }
+    }
+
+    let metadata = metadata.ok_or(UploadError::MissingMetadata)?;
+    let mut request: ExportFichesRequest = serde_json::from_slice(&metadata)?;
+    let ids: PhotoIds = serde_json::from_slice(&metadata)?;
+
+    let mut files = Vec::new();
+    for (index, id) in ids.contacts.into_iter().enumerate() {
+        let Some(id) = id.photo_id else { continue };
+        let photo = photos.remove(&id).ok_or(UploadError::MissingPhoto(id))?;
+        let fiche = &mut request.contacts[index];
+        if fiche.photo_filename.is_none() {
+            fiche.photo_filename = photo.filename;
+        }
+        files.push((index, photo.file));
+    }
+
+    Ok(UploadedExport {
+        request,
+        photos: files,
+    })
+}
+
+/// Lit la partie JSON en mémoire, dans la limite de `MAX_METADATA_BYTES`
+async fn read_metadata(mut field: Field) -> UploadResult<Vec<u8>> {
+    let mut data = Vec::new();
+    while let Some(chunk) = field.try_next().await? {
+        if data.len() + chunk.len() > MAX_METADATA_BYTES {
+            return Err(UploadError::PartTooLarge {
+                name: METADATA_FIELD.to_string(),
+                max: MAX_METADATA_BYTES,
+            });
+        }
+        data.extend_from_slice(&chunk);
+    }
+    Ok(data)
+}
+
+/// Écrit une photo dans un fichier temporaire, morceau par morceau
+async fn store_photo(
+    mut field: Field,
+    name: &str,
+    limits: &UploadConfig,
+    total: &mut usize,
+) -> UploadResult<File> {
+    let mut file = tokio::fs::File::from_std(tempfile::tempfile()?);
+    let mut size = 0;
+
+    while let Some(chunk) = field.try_next().await? {
+        size += chunk.len();
+        *total += chunk.len();
+        if size > limits.max_photo_bytes {
+            return Err(UploadError::PartTooLarge {
+                name: name.to_string(),
+                max: limits.max_photo_bytes,
+            });
+        }
+        if *total > limits.max_total_bytes {
+            return Err(UploadError::TooLarge(limits.max_total_bytes));
+        }
+        file.write_all(&chunk).await?;
+    }
+
+    file.flush().await?;
+    let mut file = file.into_std().await;
+    file.rewind()?;
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::header::{self, HeaderMap, HeaderValue};
+    use actix_web::web::Bytes;
+    use std::io::Read;
+
+    const BOUNDARY: &str = "fiches";
+
+    /// Corps multipart : (nom de la partie, nom de fichier, contenu)
+    fn multipart(parts: &[(&str, Option<&str>, &[u8])]) -> Multipart {
+        let mut body = Vec::new();
+        for (name, filename, content) in parts {
+            body.extend_from_slice(format!("--{}\r\n", BOUNDARY).as_bytes());
+            let filename = filename.map(|f| format!("; filename=\"{}\"", f)).unwrap_or_default();
+            body.extend_from_slice(
+                format!("Content-Disposition: form-data; name=\"{}\"{}\r\n\r\n", name, filename)
+                    .as_bytes(),
+            );
+            body.extend_from_slice(content);
+            body.extend_from_slice(b"\r\n");
+        }
+        body.extend_from_slice(format!("--{}--\r\n", BOUNDARY).as_bytes());
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_str(&format!("multipart/form-data; boundary={}", BOUNDARY)).unwrap(),
+        );
+        let stream = futures_util::stream::iter([Ok(Bytes::from(body))]);
+        Multipart::new(&headers, stream)
+    }
+
+    fn metadata(photo_ids: &[Option<&str>]) -> Vec<u8> {
+        let contacts: Vec<serde_json::Value> = photo_ids
+            .iter()
+            .map(|photo_id| {
+                serde_json::json!({
+                    "societe": "ACME",
+                    "contact": "Jean",
+                    "email": "jean@acme.fr",
+                    "telephone": "",
+                    "notes": "",
+                    "sectors": "",
+                    "created_at": 0,
+                    "photo_id": photo_id
+                })
+            })
+            .collect();
+        serde_json::to_vec(&serde_json::json!({ "contacts": contacts })).unwrap()
+    }
+
+    #[actix_web::test]
+    async fn test_photos_are_matched_to_fiches_in_any_order() {
+        let metadata = metadata(&[None, Some("carte-2")]);
+        let payload = multipart(&[
+            ("carte-2", Some("IMG_0002.png"), b"binaire"),
+            ("inutile", None, b"ignore"),
+            ("metadata", None, &metadata),
+        ]);
+
+        let upload = read_export(payload, &UploadConfig::default()).await.unwrap();
+        assert_eq!(upload.request.contacts.len(), 2);
+        assert_eq!(upload.request.contacts[1].photo_filename.as_deref(), Some("IMG_0002.png"));
+
+        let (index, mut file) = upload.photos.into_iter().next().unwrap();
+        assert_eq!(index, 1);
+        let mut content = Vec::new();
+        file.read_to_end(&mut content).unwrap();
+        assert_eq!(content, b"binaire");
+    }
+
+    #[actix_web::test]
+    async fn test_limits_and_missing_photos_are_rejected() {
+        let limits = UploadConfig {
+            max_photo_bytes: 4,
+            max_total_bytes: 100,
+            max_photos: 1,
+        };
+        let metadata = metadata(&[Some("carte-1")]);
+
+        let payload = multipart(&[("metadata", None, &metadata), ("carte-1", None, b"12345")]);
+        let error = read_export(payload, &limits).await.err().unwrap();
+        assert!(error.is_too_large());
+
+        let payload = multipart(&[("metadata", None, &metadata), ("carte-9", None, b"1234")]);
+        let error = read_export(payload, &limits).await.err().unwrap();
+        assert!(matches!(error, UploadError::MissingPhoto(id) if id == "carte-1"));
+
+        // Des parties vides comptent aussi : une par fichier temporaire
+        let payload = multipart(&[
+            ("carte-1", None, b""),
+            ("vide", None, b""),
+            ("metadata", None, &metadata),
+        ]);
+        let error = read_export(payload, &limits).await.err().unwrap();
+        assert!(matches!(error, UploadError::TooManyPhotos(1)));
+    }
+}